@@ -1,21 +1,28 @@
-use std::ffi::OsStr;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
 use std::io::BufRead;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Output;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use crossbeam_channel::Receiver;
 use lazy_static::lazy_static;
 use regex::Regex;
+use rustix::path::Arg;
 use simple_cmd::prelude::OutputExt;
-use simple_cmd::{Cmd, CommandBuilder};
+use tracing::warn;
 use which::which;
 
 use crate::error::Error;
 use crate::prelude::*;
 use crate::result::Result;
-use crate::types::{Adb, AdbDevice, ConnectionType};
+use crate::types::{
+	Adb, AdbDevice, AdbDeviceEntry, AdbInstallOptions, Client, CommandObserver, ConnectionType, DeviceConnection, DeviceState,
+};
 
 impl Adb {
 	/// Create a new adb instance, or error if abd cannot be found in the user PATH.
@@ -62,7 +69,7 @@ impl Adb {
 		I: IntoIterator<Item = S>,
 		S: AsRef<OsStr>,
 	{
-		let builder = CommandBuilder::adb(&self)
+		let builder = AdbCommandBuilder::new(&self)
 			.addr(addr)
 			.with_debug(debug)
 			.args(args)
@@ -81,7 +88,7 @@ impl Adb {
 	///    	println!("mdns available: {mdns}");
 	/// ```
 	pub fn mdns_check(&self, debug: bool) -> bool {
-		CommandBuilder::adb(&self)
+		AdbCommandBuilder::new(&self)
 			.with_debug(debug)
 			.args(&[
 				"mdns", "check",
@@ -116,7 +123,7 @@ impl Adb {
 	///
 	/// ```
 	pub fn list_devices(&self, debug: bool) -> Result<Vec<AdbDevice>> {
-		let output = Cmd::builder(self.0.as_path())
+		let output = AdbCommandBuilder::new(self)
 			.args([
 				"devices", "-l",
 			])
@@ -168,6 +175,33 @@ impl Adb {
 		Ok(devices)
 	}
 
+	/// List all the devices known to the adb server, including the offline and unauthorized ones.
+	///
+	/// # Examples
+	/// ```rust
+	/// use radb_client::types::{Adb, DeviceState};
+	///
+	/// let adb = Adb::new().expect("failed to find adb");
+	/// for device in adb.devices(false).expect("failed to list devices") {
+	///     if device.state == DeviceState::Unauthorized {
+	///         println!("accept the debugging authorization on {}", device.serial);
+	///     }
+	/// }
+	/// ```
+	pub fn devices(&self, debug: bool) -> Result<Vec<AdbDeviceEntry>> {
+		let output = AdbCommandBuilder::new(self)
+			.args([
+				"devices", "-l",
+			])
+			.with_debug(debug)
+			.build()
+			.output()?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_devices(Arg::as_str(&output.stdout)?))
+	}
+
 	/// Disconnect all connected devices.
 	///
 	/// # Arguments
@@ -178,7 +212,7 @@ impl Adb {
 	///
 	/// * `Result<bool>` - A boolean indicating whether the disconnection was successful.
 	pub fn disconnect_all(&self, debug: bool) -> Result<bool> {
-		match Cmd::builder(self.0.as_path())
+		match AdbCommandBuilder::new(self)
 			.with_debug(debug)
 			.arg("disconnect")
 			.build()
@@ -199,7 +233,7 @@ impl Adb {
 	///
 	/// * `Result<bool>` - A boolean indicating whether the server was successfully killed.
 	pub fn kill_server(&self, debug: bool) -> Result<bool> {
-		let output = Cmd::builder(self.0.as_path())
+		let output = AdbCommandBuilder::new(self)
 			.with_debug(debug)
 			.arg("kill-server")
 			.build()
@@ -218,7 +252,7 @@ impl Adb {
 	/// * `Result<bool>` - A boolean indicating whether the server was successfully started.
 	///
 	pub fn start_server(&self, debug: bool) -> Result<bool> {
-		let output = Cmd::builder(self.0.as_path())
+		let output = AdbCommandBuilder::new(self)
 			.with_debug(debug)
 			.arg("start-server")
 			.build()
@@ -249,7 +283,7 @@ impl Adb {
 			static ref RE: Regex = Regex::new(r#"^Version\s+(?P<version>[\d+\.-]+)$"#).unwrap();
 		}
 
-		let output = CommandBuilder::adb(&self)
+		let output = AdbCommandBuilder::new(&self)
 			.with_debug(debug)
 			.arg("--version")
 			.build()
@@ -272,14 +306,150 @@ impl Adb {
 			.ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?)
 	}
 
+	/// Install the same apk on every connected device, in parallel.
+	/// Returns the install result for each device, in the same order of [`Adb::list_devices`].
+	/// Offline devices are skipped, and no device is returned when the devices cannot be listed.
+	pub fn install_on_all<P: AsRef<Path>>(
+		&self,
+		apk: P,
+		options: Option<AdbInstallOptions>,
+	) -> Vec<(AdbDevice, Result<()>)> {
+		let devices = match self.list_devices(false) {
+			Ok(devices) => connected_devices(devices),
+			Err(err) => {
+				warn!("failed to list the devices: {err}");
+				return vec![];
+			}
+		};
+		let apk = apk.as_ref();
+		let max_threads = std::thread::available_parallelism()
+			.map(|s| s.get())
+			.unwrap_or(num_cpus::get());
+
+		fan_out(devices, max_threads, |device| {
+			let client = Client::new(self.clone(), device.addr, false);
+			client.install(apk, options.clone())
+		})
+	}
+
+	/// Set a callback invoked with the full argument vector of every adb command built through this adb
+	/// (or any of its clones, eg. the one held by a [`Client`]), right before it's executed.
+	/// Replaces any previously set observer.
+	///
+	/// # Examples
+	/// ```rust
+	/// use radb_client::types::Adb;
+	///
+	/// let adb = Adb::new().expect("failed to find adb");
+	/// adb.set_command_observer(|args| println!("adb {:?}", args));
+	/// ```
+	pub fn set_command_observer<F>(&self, observer: F)
+	where
+		F: Fn(&[OsString]) + Send + Sync + 'static,
+	{
+		*self.1.write().unwrap() = Some(Arc::new(observer));
+	}
+
+	/// Remove the observer set with [`Adb::set_command_observer`], if any.
+	pub fn clear_command_observer(&self) {
+		*self.1.write().unwrap() = None;
+	}
+
+	pub(crate) fn command_observer(&self) -> Option<CommandObserver> {
+		self.1.read().unwrap().clone()
+	}
+
 	pub fn as_os_str(&self) -> &OsStr {
 		self.as_ref()
 	}
 }
 
+/// Parse the output of `adb devices -l`. Devices are returned whatever their state, the states not known by
+/// [`DeviceState`] are reported as [`DeviceState::Unknown`]
+pub(crate) fn parse_devices(output: &str) -> Vec<AdbDeviceEntry> {
+	output
+		.lines()
+		.filter(|line| !line.starts_with("List of devices") && !line.starts_with('*'))
+		.filter_map(|line| {
+			let mut fields = line.split_whitespace();
+			let serial = fields.next()?;
+			let state = match fields.next()? {
+				// `no permissions (<reason>); see [<url>]`
+				"no" => DeviceState::NoPermissions,
+				state => DeviceState::try_from(state).unwrap_or_else(|_| DeviceState::Unknown(state.to_string())),
+			};
+			let properties: HashMap<&str, &str> = fields.filter_map(|field| field.split_once(':')).collect();
+			let usb = properties.get("usb").map(|s| s.to_string());
+
+			Some(AdbDeviceEntry {
+				serial: serial.to_string(),
+				state,
+				product: properties.get("product").map(|s| s.to_string()),
+				model: properties.get("model").map(|s| s.to_string()),
+				device: properties.get("device").map(|s| s.to_string()),
+				transport_id: properties.get("transport_id").and_then(|s| s.parse().ok()),
+				connection: if usb.is_some() { DeviceConnection::Usb } else { DeviceConnection::Tcp },
+				usb,
+			})
+		})
+		.collect()
+}
+
+/// Returns only the devices which are online
+pub(crate) fn connected_devices(devices: Vec<AdbDevice>) -> Vec<AdbDevice> {
+	devices.into_iter().filter(|device| device.connected).collect()
+}
+
+/// Run `f` for each item on a pool of at most `max_threads` workers, each picking the next pending item
+/// as soon as it's done with the previous one. Results are returned in the same order of the items.
+pub(crate) fn fan_out<T, R, F>(items: Vec<T>, max_threads: usize, f: F) -> Vec<(T, R)>
+where
+	T: Sync,
+	R: Send,
+	F: Fn(&T) -> R + Sync,
+{
+	let next = AtomicUsize::new(0);
+	let workers = max_threads.max(1).min(items.len());
+
+	let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+		let handles: Vec<_> = (0..workers)
+			.map(|_| {
+				scope.spawn(|| {
+					let mut results = vec![];
+					loop {
+						let index = next.fetch_add(1, Ordering::SeqCst);
+						let Some(item) = items.get(index) else {
+							break;
+						};
+						results.push((index, f(item)));
+					}
+					results
+				})
+			})
+			.collect();
+		handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+	});
+	results.sort_by_key(|(index, _)| *index);
+	items.into_iter().zip(results.into_iter().map(|(_, result)| result)).collect()
+}
+
 impl From<PathBuf> for Adb {
 	fn from(value: PathBuf) -> Self {
-		Adb(value)
+		Adb(value, Arc::new(RwLock::new(None)))
+	}
+}
+
+impl PartialEq for Adb {
+	fn eq(&self, other: &Self) -> bool {
+		self.0 == other.0
+	}
+}
+
+impl Eq for Adb {}
+
+impl Hash for Adb {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.0.hash(state);
 	}
 }
 
@@ -309,11 +479,18 @@ impl Into<PathBuf> for Adb {
 
 #[cfg(test)]
 pub(crate) mod test {
+	use std::ffi::OsString;
+	use std::fs::remove_file;
+	use std::os::unix::fs::PermissionsExt;
 	use std::path::PathBuf;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::{Arc, Mutex};
+	use std::time::Duration;
 	use which::which;
 
-	use crate::test::test::init_log;
-	use crate::types::{Adb, Client, ConnectionType};
+	use crate::adb::{connected_devices, fan_out, parse_devices};
+	use crate::test::test::{init_log, temp_dir, test_files_dir};
+	use crate::types::{Adb, AdbDevice, Client, ConnectionType, DeviceConnection, DeviceState};
 
 	static DEVICE_IP: &'static str = "192.168.1.101:5555";
 
@@ -381,6 +558,156 @@ pub(crate) mod test {
 		assert_eq!(devices_count, clients.len());
 	}
 
+	#[test]
+	fn test_fan_out() {
+		let running = AtomicUsize::new(0);
+		let max_running = AtomicUsize::new(0);
+		let items: Vec<u32> = (0..10).collect();
+
+		let results = fan_out(items, 3, |item| {
+			let current = running.fetch_add(1, Ordering::SeqCst) + 1;
+			max_running.fetch_max(current, Ordering::SeqCst);
+			std::thread::sleep(Duration::from_millis(10));
+			running.fetch_sub(1, Ordering::SeqCst);
+			item * 2
+		});
+
+		assert!(max_running.load(Ordering::SeqCst) <= 3);
+		assert_eq!((0..10).map(|i| (i, i * 2)).collect::<Vec<_>>(), results);
+
+		// a slow item must not hold back the remaining ones
+		let completed = Mutex::new(vec![]);
+		let results = fan_out((0..6).collect::<Vec<u32>>(), 2, |item| {
+			std::thread::sleep(Duration::from_millis(if *item == 0 { 800 } else { 50 }));
+			completed.lock().unwrap().push(*item);
+			*item
+		});
+		assert_eq!((0..6).map(|i| (i, i)).collect::<Vec<_>>(), results);
+		assert_eq!(Some(&0), completed.lock().unwrap().last());
+	}
+
+	#[test]
+	fn test_connected_devices() {
+		let device = |name: &str, connected: bool, transport_id: u8| AdbDevice {
+			name: name.to_string(),
+			product: "product".to_string(),
+			model: "model".to_string(),
+			device: "device".to_string(),
+			connected,
+			addr: ConnectionType::Transport(transport_id),
+		};
+
+		let devices = connected_devices(vec![
+			device("emulator-5554", true, 1),
+			device("192.168.1.101:5555", false, 2),
+			device("192.168.1.102:5555", true, 3),
+		]);
+		assert_eq!(
+			vec![
+				"emulator-5554",
+				"192.168.1.102:5555",
+			],
+			devices.iter().map(|d| d.name.as_str()).collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_parse_devices() {
+		let output = "List of devices attached
+emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1
+192.168.1.101:5555     offline product:atv model:Smart_TV device:sabrina transport_id:2
+R58M123ABC             unauthorized usb:1-1 transport_id:3
+0123456789             no permissions (missing udev rules? user is in the plugdev group); see [http://developer.android.com/tools/device.html] usb:1-2 transport_id:4
+192.168.1.102:5555     authorizing transport_id:300
+
+";
+		let devices = parse_devices(output);
+		assert_eq!(5, devices.len());
+
+		assert_eq!("emulator-5554", devices[0].serial);
+		assert_eq!(DeviceState::Device, devices[0].state);
+		assert_eq!(Some("sdk_gphone64_x86_64"), devices[0].model.as_deref());
+		assert_eq!(Some("emu64xa"), devices[0].device.as_deref());
+		assert_eq!(Some(1), devices[0].transport_id);
+		assert_eq!(DeviceConnection::Tcp, devices[0].connection);
+
+		assert_eq!(DeviceState::Offline, devices[1].state);
+		assert_eq!(Some("Smart_TV"), devices[1].model.as_deref());
+		assert_eq!(DeviceConnection::Tcp, devices[1].connection);
+
+		assert_eq!("R58M123ABC", devices[2].serial);
+		assert_eq!(DeviceState::Unauthorized, devices[2].state);
+		assert_eq!(None, devices[2].product);
+		assert_eq!(Some("1-1"), devices[2].usb.as_deref());
+		assert_eq!(Some(3), devices[2].transport_id);
+		assert_eq!(DeviceConnection::Usb, devices[2].connection);
+
+		assert_eq!("0123456789", devices[3].serial);
+		assert_eq!(DeviceState::NoPermissions, devices[3].state);
+		assert_eq!(Some("1-2"), devices[3].usb.as_deref());
+		assert_eq!(Some(4), devices[3].transport_id);
+
+		assert_eq!("192.168.1.102:5555", devices[4].serial);
+		assert_eq!(DeviceState::Unknown("authorizing".to_string()), devices[4].state);
+		assert_eq!(Some(300), devices[4].transport_id);
+	}
+
+	#[test]
+	fn test_install_on_all() {
+		init_log();
+		let adb = Adb::new().expect("failed to find adb");
+		let apk = test_files_dir().join("app-debug.apk");
+		let results = adb.install_on_all(apk, None);
+		for (device, result) in results {
+			println!("{device}: {result:?}");
+		}
+	}
+
+	#[test]
+	fn test_install_on_all_targets() {
+		let dir = temp_dir();
+		let log = dir.join("install_on_all_adb.log");
+		let _ = remove_file(&log);
+		// two online devices and an offline one, the install fails on 192.168.1.102
+		let adb = dir.join("install_on_all_adb.sh");
+		std::fs::write(
+			&adb,
+			format!(
+				"#!/bin/sh\n\
+				case \"$*\" in\n\
+				\"devices -l\")\n\
+				echo 'List of devices attached'\n\
+				echo '192.168.1.101:5555 device product:a model:A device:a transport_id:1'\n\
+				echo '192.168.1.102:5555 device product:b model:B device:b transport_id:2'\n\
+				echo '192.168.1.103:5555 offline product:c model:C device:c transport_id:3' ;;\n\
+				*192.168.1.102*) echo 'Failure [INSTALL_FAILED_INSUFFICIENT_STORAGE]' >&2; exit 1 ;;\n\
+				*) echo \"$@\" >> {} ;;\n\
+				esac\n",
+				log.display()
+			),
+		)
+		.expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+
+		let results = Adb::from(adb).install_on_all("/tmp/app.apk", None);
+		assert_eq!(
+			vec![
+				"192.168.1.101:5555",
+				"192.168.1.102:5555",
+			],
+			results.iter().map(|(device, _)| device.name.as_str()).collect::<Vec<_>>()
+		);
+		assert!(results[0].1.is_ok());
+		assert!(results[1].1.is_err());
+		assert_eq!(
+			"-s 192.168.1.101:5555 install /tmp/app.apk",
+			std::fs::read_to_string(&log).unwrap().trim()
+		);
+
+		let results = Adb::from(PathBuf::from("false")).install_on_all("/tmp/app.apk", None);
+		assert!(results.is_empty());
+	}
+
 	#[test]
 	fn test_disconnect_all() {
 		init_log();
@@ -404,4 +731,32 @@ pub(crate) mod test {
 		let version = adb.version(true).expect("failed to get adb version");
 		println!("version: {version}");
 	}
+
+	#[test]
+	fn test_command_observer() {
+		let adb = Adb::from(PathBuf::from("true"));
+		let seen: Arc<Mutex<Vec<Vec<OsString>>>> = Arc::new(Mutex::new(vec![]));
+		let observed = seen.clone();
+		adb.set_command_observer(move |args| observed.lock().unwrap().push(args.to_vec()));
+
+		// other instances using the same adb don't share the observer
+		let other = Adb::from(PathBuf::from("true"));
+		other.exec(ConnectionType::USB, vec!["devices"], None, None, false).expect("failed to exec");
+
+		let addr = ConnectionType::try_from("192.168.1.100:5555").unwrap();
+		adb.exec(addr, vec!["get-state"], None, None, false).expect("failed to exec");
+		adb.clear_command_observer();
+		adb.exec(addr, vec!["get-state"], None, None, false).expect("failed to exec");
+
+		let seen = seen.lock().unwrap();
+		assert_eq!(1, seen.len());
+		assert_eq!(
+			vec![
+				OsString::from("-s"),
+				OsString::from("192.168.1.100:5555"),
+				OsString::from("get-state"),
+			],
+			seen[0]
+		);
+	}
 }
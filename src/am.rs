@@ -1,12 +1,81 @@
 use std::ffi::OsString;
 use std::process::Output;
+use std::str::FromStr;
 
 use cmd_lib::AsOsStr;
 use simple_cmd::prelude::OutputExt;
 
+use crate::dump_util::{parse_recent_tasks, parse_service_info};
+use crate::error::Error;
 use crate::result::Result;
+use crate::shell::shell_quote;
 use crate::traits::AsArgs;
-use crate::types::{ActivityManager, Intent, MemoryStatus, UserOption};
+use crate::types::{
+	ActivityManager, ComponentName, InstrumentOptions, InstrumentResult, Intent, MemoryStatus, NightMode, RecentTask, ServiceInfo,
+	SettingsType, UserOption,
+};
+
+static FONT_SCALE_SETTING: &str = "font_scale";
+
+static ACTION_VIEW: &str = "android.intent.action.VIEW";
+static INSTRUMENTATION_STATUS_CODE: &str = "INSTRUMENTATION_STATUS_CODE: ";
+static INSTRUMENTATION_RESULT: &str = "INSTRUMENTATION_RESULT: ";
+static INSTRUMENTATION_CODE: &str = "INSTRUMENTATION_CODE: ";
+static RESUMED_ACTIVITY_KEYS: [&str; 3] = ["mResumedActivity:", "ResumedActivity:", "topResumedActivity="];
+static CURRENT_FOCUS_KEYS: [&str; 1] = ["mCurrentFocus="];
+
+/// Build a VIEW intent for the given uri, optionally constrained to a package.
+/// The uri is single-quoted, so that it's passed unchanged through the device shell.
+pub(crate) fn make_view_intent(uri: &str, package: Option<&str>) -> Intent {
+	let mut intent = Intent::from_action(ACTION_VIEW);
+	intent.data = Some(shell_quote(uri));
+	intent.package = package.map(|p| p.to_string());
+	intent
+}
+
+/// Returns the `font_scale` setting value, or an error if the scale is not a positive number
+pub(crate) fn make_font_scale_value(scale: f32) -> Result<String> {
+	if scale.is_finite() && scale > 0.0 {
+		Ok(scale.to_string())
+	} else {
+		Err(Error::from(std::io::ErrorKind::InvalidInput))
+	}
+}
+
+/// Parse the `INSTRUMENTATION_STATUS_CODE`, `INSTRUMENTATION_RESULT` and `INSTRUMENTATION_CODE` lines
+/// printed by `am instrument -r`. Status code 1 marks the start of a test and is not counted.
+pub(crate) fn parse_instrument_output(output: &str) -> InstrumentResult {
+	let mut result = InstrumentResult {
+		raw: output.to_string(),
+		..Default::default()
+	};
+
+	for line in output.lines().map(|line| line.trim_end()) {
+		if let Some(code) = line.strip_prefix(INSTRUMENTATION_STATUS_CODE) {
+			match code.trim().parse::<i32>() {
+				Ok(0) => result.passed += 1,
+				Ok(-1) | Ok(-2) => result.failed += 1,
+				Ok(-3) | Ok(-4) => result.ignored += 1,
+				_ => {}
+			}
+		} else if let Some(value) = line.strip_prefix(INSTRUMENTATION_RESULT) {
+			if let Some((key, value)) = value.split_once('=') {
+				result.results.insert(key.to_string(), value.to_string());
+			}
+		} else if let Some(code) = line.strip_prefix(INSTRUMENTATION_CODE) {
+			result.code = code.trim().parse().ok();
+		}
+	}
+	result
+}
+
+/// Find the first line starting with one of the `keys` and parse its `package/class` token.
+/// A class starting with `.` is relative to the package.
+pub(crate) fn parse_focused_component(output: &str, keys: &[&str]) -> Option<ComponentName> {
+	let line = output.lines().map(str::trim_start).find(|line| keys.iter().any(|key| line.starts_with(key)))?;
+	line.split_whitespace()
+		.find_map(|token| ComponentName::from_str(token.trim_end_matches('}')).ok())
+}
 
 impl<'a> ActivityManager<'a> {
 	/// Force stop a package
@@ -37,7 +106,7 @@ impl<'a> ActivityManager<'a> {
 		ActivityManager::handle_result(result)
 	}
 
-	/// Start a foreground service (using am start-service)
+	/// Start a foreground service (using am start-foreground-service)
 	pub fn start_foreground_service(&self, intent: &Intent) -> Result<()> {
 		let result = self.parent.exec(
 			vec![
@@ -51,6 +120,20 @@ impl<'a> ActivityManager<'a> {
 		ActivityManager::handle_result(result)
 	}
 
+	/// Stop a service (using am stopservice)
+	pub fn stop_service(&self, intent: &Intent) -> Result<()> {
+		let result = self.parent.exec(
+			vec![
+				"am",
+				"stopservice",
+				format!("{:}", intent).as_str(),
+			],
+			None,
+			None,
+		)?;
+		ActivityManager::handle_result(result)
+	}
+
 	pub fn start(&self, intent: &Intent) -> Result<()> {
 		let result = self.parent.exec(
 			vec![
@@ -64,6 +147,16 @@ impl<'a> ActivityManager<'a> {
 		ActivityManager::handle_result(result)
 	}
 
+	/// Open the given url with the default VIEW handler
+	pub fn open_url(&self, url: &str) -> Result<()> {
+		self.open_deeplink(url, None)
+	}
+
+	/// Start a VIEW intent for the given deep-link uri, optionally handled by the given package only
+	pub fn open_deeplink(&self, uri: &str, package: Option<&str>) -> Result<()> {
+		self.start(&make_view_intent(uri, package))
+	}
+
 	pub fn broadcast(&self, intent: &Intent) -> Result<()> {
 		let result = self.parent.exec(
 			vec![
@@ -131,6 +224,114 @@ impl<'a> ActivityManager<'a> {
 		ActivityManager::handle_result(result)
 	}
 
+	/// Run the instrumentation of the given component (`package/runner`), returning the tests outcome.
+	/// Use [`InstrumentOptions::raw`] to get the per-test status codes.
+	pub fn instrument(&self, component: &str, options: InstrumentOptions) -> Result<InstrumentResult> {
+		let mut args: Vec<OsString> = vec![
+			"am".into(),
+			"instrument".into(),
+		];
+		args.extend(options);
+		args.push(component.into());
+
+		let output = self.parent.exec(args, None, None)?;
+		let result = parse_instrument_output(rustix::path::Arg::as_str(&output.stdout)?);
+		if output.error() && result.code.is_none() && !output.kill() && !output.interrupt() {
+			Err(output.into())
+		} else {
+			Ok(result)
+		}
+	}
+
+	/// Change the night mode (`cmd uimode night`), triggering a configuration change in the running apps.
+	/// `cmd` is available since Android 7.0 (API 24), older devices return an error.
+	pub fn set_night_mode(&self, mode: NightMode) -> Result<()> {
+		let mode = mode.to_string();
+		let result = self.parent.exec(
+			[
+				"cmd",
+				"uimode",
+				"night",
+				mode.as_str(),
+			],
+			None,
+			None,
+		)?;
+		ActivityManager::handle_result(result)
+	}
+
+	/// Change the font scale (1.0 being the default size). The system observes the `font_scale` setting
+	/// and pushes the new configuration to the running apps.
+	pub fn set_font_scale(&self, scale: f32) -> Result<()> {
+		let value = make_font_scale_value(scale)?;
+		self.parent.put_setting(SettingsType::system, FONT_SCALE_SETTING, value)
+	}
+
+	/// Returns the state of the given service (`package/.Service`)
+	pub fn service_info(&self, component: &str) -> Result<ServiceInfo> {
+		let output = self.parent.exec(
+			[
+				"dumpsys",
+				"activity",
+				"services",
+				component,
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_service_info(rustix::path::Arg::as_str(&output.stdout)?))
+	}
+
+	/// Returns the activity currently in the foreground, reading the resumed activity from
+	/// `dumpsys activity activities`, falling back to the focused window of `dumpsys window`
+	pub fn current_focus(&self) -> Result<Option<ComponentName>> {
+		let output = self.parent.exec(
+			[
+				"dumpsys",
+				"activity",
+				"activities",
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		if let Some(component) = parse_focused_component(rustix::path::Arg::as_str(&output.stdout)?, &RESUMED_ACTIVITY_KEYS) {
+			return Ok(Some(component));
+		}
+
+		let output = self.parent.exec(
+			[
+				"dumpsys", "window",
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_focused_component(rustix::path::Arg::as_str(&output.stdout)?, &CURRENT_FOCUS_KEYS))
+	}
+
+	/// Returns at most `max` tasks of the recents stack, most recent first
+	pub fn recent_tasks(&self, max: usize) -> Result<Vec<RecentTask>> {
+		let output = self.parent.exec(
+			[
+				"dumpsys", "activity", "recents",
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_recent_tasks(rustix::path::Arg::as_str(&output.stdout)?, max))
+	}
+
 	/// Returns id of the current foreground user.
 	pub fn get_current_user(&self) -> Result<String> {
 		let result = self.parent.exec(
@@ -156,8 +357,160 @@ impl<'a> ActivityManager<'a> {
 
 #[cfg(test)]
 mod test {
+	use std::collections::HashMap;
+	use std::ffi::OsString;
+
+	use crate::am::{
+		CURRENT_FOCUS_KEYS, RESUMED_ACTIVITY_KEYS, make_font_scale_value, make_view_intent, parse_focused_component,
+		parse_instrument_output,
+	};
 	use crate::test::test::{connect_emulator, connect_tcp_ip_client, init_log, root_client};
-	use crate::types::{Intent, MemoryStatus, UserOption};
+	use crate::types::{ComponentName, InstrumentOptions, Intent, MemoryStatus, NightMode, SettingsType, UserOption};
+
+	#[test]
+	fn test_parse_focused_component() {
+		let activities = "ACTIVITY MANAGER ACTIVITIES (dumpsys activity activities)
+Display #0 (activities from top to bottom):
+  * Task{8f1c2d #12 type=standard A=1000:com.android.settings U=0 visible=true mode=fullscreen}
+    mResumedActivity: ActivityRecord{a1b2c3 u0 com.android.settings/.Settings t12}
+";
+		assert_eq!(
+			Some(ComponentName {
+				package: "com.android.settings".to_string(),
+				class: "com.android.settings.Settings".to_string(),
+			}),
+			parse_focused_component(activities, &RESUMED_ACTIVITY_KEYS)
+		);
+
+		let activities = "  ResumedActivity: ActivityRecord{d4e5f6 u0 com.example/com.example.ui.MainActivity t7}\n";
+		let component = parse_focused_component(activities, &RESUMED_ACTIVITY_KEYS).unwrap();
+		assert_eq!("com.example/com.example.ui.MainActivity", component.to_string());
+
+		let window = "  mCurrentFocus=Window{5d2a1b u0 com.android.launcher3/com.android.launcher3.uioverrides.QuickstepLauncher}\n  mFocusedApp=ActivityRecord{...}\n";
+		let component = parse_focused_component(window, &CURRENT_FOCUS_KEYS).unwrap();
+		assert_eq!("com.android.launcher3", component.package);
+		assert_eq!("com.android.launcher3.uioverrides.QuickstepLauncher", component.class);
+
+		assert_eq!(None, parse_focused_component("  mCurrentFocus=Window{7a8b9c u0 NotificationShade}\n", &CURRENT_FOCUS_KEYS));
+		assert_eq!(None, parse_focused_component("  mCurrentFocus=null\n", &CURRENT_FOCUS_KEYS));
+		assert_eq!(None, parse_focused_component("  mFocusedApp=null\n", &RESUMED_ACTIVITY_KEYS));
+	}
+
+	#[test]
+	fn test_current_focus() {
+		init_log();
+		let client = connect_emulator();
+		let component = client.shell().am().current_focus().expect("failed to get the current focus");
+		println!("current focus: {component:?}");
+	}
+
+	#[test]
+	fn test_recent_tasks() {
+		init_log();
+		let client = connect_emulator();
+		let tasks = client.shell().am().recent_tasks(5).expect("failed to get the recent tasks");
+		println!("recent tasks: {tasks:?}");
+		assert!(tasks.len() <= 5);
+	}
+
+	#[test]
+	fn test_configuration_args() {
+		assert_eq!("yes", NightMode::Yes.to_string());
+		assert_eq!("no", NightMode::No.to_string());
+		assert_eq!("auto", NightMode::Auto.to_string());
+
+		assert_eq!("1", make_font_scale_value(1.0).unwrap());
+		assert_eq!("1.3", make_font_scale_value(1.3).unwrap());
+		assert_eq!("0.85", make_font_scale_value(0.85).unwrap());
+		assert!(make_font_scale_value(0.0).is_err());
+		assert!(make_font_scale_value(-1.0).is_err());
+		assert!(make_font_scale_value(f32::NAN).is_err());
+	}
+
+	#[test]
+	fn test_configuration_change() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let am = shell.am();
+
+		am.set_night_mode(NightMode::Yes).expect("failed to set night mode");
+		let output = shell.exec(vec!["cmd", "uimode", "night"], None, None).unwrap();
+		assert!(String::from_utf8_lossy(&output.stdout).contains("yes"));
+		am.set_night_mode(NightMode::No).expect("failed to set night mode");
+
+		am.set_font_scale(1.3).expect("failed to set font scale");
+		assert_eq!(
+			Some("1.3".to_string()),
+			shell.get_setting(SettingsType::system, "font_scale").unwrap()
+		);
+		am.set_font_scale(1.0).expect("failed to set font scale");
+	}
+
+	#[test]
+	fn test_instrument_options() {
+		let options = InstrumentOptions {
+			raw: true,
+			wait: true,
+			class: Some("com.example.FooTest#testBar".to_string()),
+			extras: HashMap::from([
+				("size".to_string(), "small".to_string()),
+				("debug".to_string(), "false".to_string()),
+			]),
+		};
+		let args: Vec<OsString> = options.into_iter().collect();
+		assert_eq!(
+			vec![
+				"-r",
+				"-w",
+				"-e",
+				"class",
+				"'com.example.FooTest#testBar'",
+				"-e",
+				"debug",
+				"'false'",
+				"-e",
+				"size",
+				"'small'",
+			],
+			args
+		);
+		assert!(InstrumentOptions::default().into_iter().next().is_none());
+	}
+
+	#[test]
+	fn test_parse_instrument_output() {
+		let output = "INSTRUMENTATION_STATUS: class=com.example.FooTest\n\
+			INSTRUMENTATION_STATUS: test=testOne\n\
+			INSTRUMENTATION_STATUS_CODE: 1\n\
+			INSTRUMENTATION_STATUS: test=testOne\n\
+			INSTRUMENTATION_STATUS_CODE: 0\n\
+			INSTRUMENTATION_STATUS: test=testTwo\n\
+			INSTRUMENTATION_STATUS_CODE: 1\n\
+			INSTRUMENTATION_STATUS: stack=java.lang.AssertionError\n\
+			INSTRUMENTATION_STATUS_CODE: -2\n\
+			INSTRUMENTATION_STATUS: test=testThree\n\
+			INSTRUMENTATION_STATUS_CODE: 1\n\
+			INSTRUMENTATION_STATUS_CODE: -3\n\
+			INSTRUMENTATION_RESULT: stream=\n\
+			Tests run: 3,  Failures: 1\n\
+			INSTRUMENTATION_CODE: -1\n";
+		let result = parse_instrument_output(output);
+		assert_eq!(1, result.passed);
+		assert_eq!(1, result.failed);
+		assert_eq!(1, result.ignored);
+		assert_eq!(Some(-1), result.code);
+		assert_eq!(Some(&"".to_string()), result.results.get("stream"));
+		assert!(!result.success());
+
+		let output = "INSTRUMENTATION_RESULT: shortMsg=Process crashed.\n\
+			INSTRUMENTATION_CODE: 0\n";
+		let result = parse_instrument_output(output);
+		assert_eq!(0, result.passed);
+		assert_eq!(Some(0), result.code);
+		assert_eq!("Process crashed.", result.results["shortMsg"]);
+		assert!(!result.success());
+	}
 
 	#[test]
 	fn test_force_stop() {
@@ -180,6 +533,19 @@ mod test {
 		client.shell().am().start_service(&intent).expect("failed to start service");
 	}
 
+	#[test]
+	fn test_service_info() {
+		init_log();
+		let client = connect_tcp_ip_client();
+		let info = client
+			.shell()
+			.am()
+			.service_info("com.android.bluetooth/.btservice.AdapterService")
+			.expect("failed to get service info");
+		println!("service info: {:?}", info);
+		assert_eq!(info.running, info.pid.is_some());
+	}
+
 	#[test]
 	fn test_start_foreground_service() {
 		init_log();
@@ -195,6 +561,49 @@ mod test {
 			.expect("failed to start service");
 	}
 
+	#[test]
+	fn test_stop_service() {
+		init_log();
+		let client = connect_tcp_ip_client();
+		let intent = Intent {
+			component: Some(format!("{}/.service.SystemService", "com.swisscom.aot.library.standalone")),
+			..Default::default()
+		};
+
+		client.shell().am().stop_service(&intent).expect("failed to stop service");
+	}
+
+	#[test]
+	fn test_make_view_intent() {
+		let intent = make_view_intent("https://www.example.com/path?a=1&b=2", None);
+		assert_eq!(
+			"-a android.intent.action.VIEW -d 'https://www.example.com/path?a=1&b=2'",
+			format!("{intent}").trim()
+		);
+
+		let intent = make_view_intent("myapp://open/it's", Some("com.example.app"));
+		assert_eq!(
+			"-a android.intent.action.VIEW -d 'myapp://open/it'\\''s' -p com.example.app",
+			format!("{intent}").trim()
+		);
+	}
+
+	#[test]
+	fn test_open_url() {
+		init_log();
+		let client = connect_emulator();
+		client
+			.shell()
+			.am()
+			.open_url("https://www.google.com/search?q=android&hl=en")
+			.expect("failed to open url");
+		client
+			.shell()
+			.am()
+			.open_deeplink("https://www.google.com", Some("com.android.chrome"))
+			.expect("failed to open deeplink");
+	}
+
 	#[test]
 	fn test_broadcast() {
 		init_log();
@@ -1,39 +1,444 @@
 use std::borrow::Cow;
-use std::env::temp_dir;
-use std::ffi::OsString;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
 
 use std::fs::File;
-use std::process::{Output, Stdio};
+use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::path::Path;
+use std::process::{ChildStdout, ExitStatus, Output, Stdio};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use arboard::ImageData;
-use crossbeam_channel::Receiver;
+use chrono::{DateTime, Local, TimeZone};
+use crossbeam_channel::{Receiver, select};
+use image::ImageFormat;
+use itertools::Itertools;
+use lazy_static::lazy_static;
 use mac_address::MacAddress;
+use regex::Regex;
 use rustix::path::Arg;
-use simple_cmd::debug::CommandDebug;
 use simple_cmd::prelude::OutputExt;
-use simple_cmd::{Cmd, CommandBuilder};
+use simple_cmd::Cmd;
+use tracing::warn;
 use uuid::Uuid;
 
+use crate::dump_util::{parse_crash_reports, parse_logcat_merged};
 use crate::error::Error;
 use crate::prelude::*;
 use crate::result::Result;
-use crate::traits::AsArgs;
+use crate::shell::handle_timeout;
 use crate::types::{
-	Adb, AdbInstallOptions, Client, ConnectionType, LogcatOptions, RebootType, Reconnect, Shell, UninstallOptions, Wakefulness,
+	Adb, AdbInstallOptions, Client, ClientConfig, ConnectionType, CrashReport, DeviceState, ForwardEntry, KeyCode, LogcatEntry,
+	LogcatOptions, RebootType, Reconnect, RemoteStat, RetryPolicy, ScreenRecordOptions, Shell, SyncStats, TransferStats,
+	UninstallOptions, WaitState, WaitTransport, Wakefulness,
 };
 
-static GET_STATE_TIMEOUT: u64 = 200;
-static SLEEP_AFTER_ROOT: u64 = 1_000;
+static TCPIP_RESTART_TIMEOUT: u64 = 5_000;
+static WAKEFULNESS_TIMEOUT: u64 = 5_000;
+static FORWARD_AUTO_LOCAL: &str = "tcp:0";
+static FEATURE_STAT_V2: &str = "stat_v2";
+static FEATURE_SHELL_V2: &str = "shell_v2";
+static PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+static TRANSFER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+static REMOTE_TRANSFER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Device directory where the crate writes its temporary files
+pub(crate) static TEMP_DIR: &str = "/data/local/tmp";
+/// Prefix of all the temporary files created by the crate, see [`Client::cleanup_temp`]
+pub(crate) static TEMP_PREFIX: &str = "radb_tmp_";
+
+lazy_static! {
+	static ref RE_LOGCAT_EPOCH: Regex = Regex::new(r"^\s*(?P<secs>\d+)\.(?P<millis>\d{3})\s").unwrap();
+	static ref RE_SYNC_SUMMARY: Regex =
+		Regex::new(r"(\d+) files? pushed(?:, (\d+) skipped)?\.(?:[^(\n]*\((\d+) bytes in)?").unwrap();
+	static ref RE_TRANSFER_SUMMARY: Regex =
+		Regex::new(r"([\d.]+) (B|KB|MB|GB)/s \((\d+) bytes in ([\d.]+)s\)").unwrap();
+	static ref RE_TRANSPORT_ID: Regex = Regex::new(r"\stransport_id:(\d+)").unwrap();
+}
+
+/// Parse the `btime` line of /proc/stat
+pub(crate) fn parse_boot_time(stat: &str) -> Result<DateTime<Local>> {
+	let btime = stat
+		.lines()
+		.find_map(|line| line.strip_prefix("btime "))
+		.ok_or(Error::ParseInputError)?
+		.trim()
+		.parse::<i64>()?;
+	Local.timestamp_opt(btime, 0).single().ok_or(Error::ParseInputError)
+}
+
+/// Parse the timestamp of a logcat line printed using the `epoch` format
+pub(crate) fn parse_logcat_epoch(line: &str) -> Option<DateTime<Local>> {
+	let captures = RE_LOGCAT_EPOCH.captures(line)?;
+	let secs = captures.name("secs")?.as_str().parse::<i64>().ok()?;
+	let millis = captures.name("millis")?.as_str().parse::<u32>().ok()?;
+	Local.timestamp_opt(secs, millis * 1_000_000).single()
+}
+
+/// Set the `since` option to the boot time, clamped to the oldest entry of the log buffer
+pub(crate) fn logcat_options_since_boot(
+	mut options: LogcatOptions,
+	boot_time: DateTime<Local>,
+	earliest: Option<DateTime<Local>>,
+) -> LogcatOptions {
+	let since = match earliest {
+		Some(earliest) if earliest > boot_time => earliest,
+		_ => boot_time,
+	};
+	options.since = Some(since);
+	options
+}
+
+/// Parse the output of `adb get-state`. Devices not ready are reported as errors on stderr
+/// (eg. `error: device unauthorized.`)
+pub(crate) fn parse_device_state(stdout: &str, stderr: &str) -> Result<DeviceState> {
+	stdout
+		.lines()
+		.chain(stderr.lines())
+		.find_map(|line| DeviceState::try_from(line).ok())
+		.ok_or(Error::from(std::io::ErrorKind::NotConnected))
+}
+
+/// Parse the output of `adb connect`. adb exits successfully even when the connection fails,
+/// so the outcome is detected from the message printed on stdout or stderr
+pub(crate) fn parse_connect_output(stdout: &str, stderr: &str, success: bool) -> Result<()> {
+	let message = format!("{stdout}\n{stderr}").to_lowercase();
+	if message.contains("already connected") {
+		Err(Error::AlreadyConnected)
+	} else if message.contains("connection refused") || message.contains("actively refused") {
+		Err(Error::ConnectionRefused)
+	} else if !success || message.contains("failed to connect") || message.contains("cannot connect") {
+		Err(Error::from(std::io::ErrorKind::NotConnected))
+	} else {
+		Ok(())
+	}
+}
+
+/// Parse the output of `adb features`, one feature per line (or comma separated)
+pub(crate) fn parse_features(output: &str) -> Vec<String> {
+	output
+		.split(['\n', ','])
+		.map(|feature| feature.trim())
+		.filter(|feature| !feature.is_empty())
+		.map(|feature| feature.to_string())
+		.collect()
+}
+
+/// Parse the output of `stat -c '%f %u %g %s %X %Y %Z'`
+pub(crate) fn parse_toybox_stat(output: &str) -> Option<RemoteStat> {
+	let fields: Vec<&str> = output.split_whitespace().collect();
+	if fields.len() != 7 {
+		return None;
+	}
+	Some(RemoteStat {
+		mode: u32::from_str_radix(fields[0], 16).ok()?,
+		uid: fields[1].parse().ok()?,
+		gid: fields[2].parse().ok()?,
+		size: fields[3].parse().ok()?,
+		atime: fields[4].parse().ok()?,
+		mtime: fields[5].parse().ok()?,
+		ctime: fields[6].parse().ok()?,
+	})
+}
+
+/// Map the offline and unauthorized states to their errors
+pub(crate) fn check_device_state(state: DeviceState) -> Result<DeviceState> {
+	match state {
+		DeviceState::Offline => Err(Error::DeviceOffline),
+		DeviceState::Unauthorized => Err(Error::DeviceUnauthorized),
+		state => Ok(state),
+	}
+}
+
+/// Parse the `N files pushed, M skipped. X MB/s (B bytes in T)` summary lines of `adb push --sync`
+pub(crate) fn parse_sync_stats(output: &str) -> SyncStats {
+	RE_SYNC_SUMMARY
+		.captures_iter(output)
+		.fold(SyncStats::default(), |mut stats, captures| {
+			let value = |index: usize| captures.get(index).and_then(|m| m.as_str().parse::<u64>().ok()).unwrap_or(0);
+			stats.pushed += value(1) as usize;
+			stats.skipped += value(2) as usize;
+			stats.bytes += value(3);
+			stats
+		})
+}
+
+/// Parse the final `X MB/s (B bytes in T s)` summary line of `adb push` or `adb pull`.
+/// The rate is computed from the transferred bytes, falling back to the printed one for instant transfers.
+pub(crate) fn parse_transfer_stats(output: &str) -> Option<TransferStats> {
+	let captures = RE_TRANSFER_SUMMARY.captures_iter(output).last()?;
+	let bytes = captures[3].parse::<u64>().ok()?;
+	let duration = Duration::try_from_secs_f64(captures[4].parse().ok()?).ok()?;
+	let rate_bytes_per_sec = if duration.is_zero() {
+		let unit = match &captures[2] {
+			"KB" => 1024f64,
+			"MB" => 1024f64 * 1024f64,
+			"GB" => 1024f64 * 1024f64 * 1024f64,
+			_ => 1f64,
+		};
+		captures[1].parse::<f64>().ok()? * unit
+	} else {
+		bytes as f64 / duration.as_secs_f64()
+	};
+	Some(TransferStats {
+		bytes,
+		duration,
+		rate_bytes_per_sec,
+	})
+}
+
+/// Download `url` into the local `dest` file. `file://` urls are copied from the local filesystem
+#[cfg(feature = "http")]
+pub(crate) fn download(url: &str, dest: &Path) -> Result<()> {
+	let error = |message: String| Error::DownloadError {
+		url: url.to_string(),
+		message,
+	};
+	if let Some(path) = url.strip_prefix("file://") {
+		std::fs::copy(path, dest).map_err(|e| error(e.to_string()))?;
+		return Ok(());
+	}
+
+	let mut response = ureq::get(url).call().map_err(|e| error(e.to_string()))?;
+	let mut file = File::create(dest)?;
+	std::io::copy(&mut response.body_mut().as_reader(), &mut file).map_err(|e| error(e.to_string()))?;
+	Ok(())
+}
+
+/// List the files under `root`, relative to it and using `/` as separator
+fn list_local_files(root: &Path) -> Result<HashSet<String>> {
+	let mut files = HashSet::new();
+	let mut dirs = vec![root.to_path_buf()];
+	while let Some(dir) = dirs.pop() {
+		for entry in std::fs::read_dir(&dir)? {
+			let path = entry?.path();
+			if path.is_dir() {
+				dirs.push(path);
+			} else if let Ok(relative) = path.strip_prefix(root) {
+				files.insert(relative.components().map(|c| c.as_os_str().to_string_lossy()).join("/"));
+			}
+		}
+	}
+	Ok(files)
+}
+
+/// Returns the remote files (as printed by `find <remote_dir> -type f`) which don't exist locally
+pub(crate) fn extra_remote_files(find_output: &str, remote_dir: &str, local_files: &HashSet<String>) -> Vec<String> {
+	let prefix = format!("{}/", remote_dir.trim_end_matches('/'));
+	find_output
+		.lines()
+		.filter_map(|line| {
+			let relative = line.strip_prefix(prefix.as_str())?;
+			(!local_files.contains(relative)).then(|| line.to_string())
+		})
+		.collect()
+}
+
+/// Convert the exit status of a process into an error, unless it succeeded or was killed by a signal
+pub(crate) fn handle_exit_status(status: ExitStatus) -> Result<()> {
+	crate::shell::handle_result(Output {
+		status,
+		stdout: vec![],
+		stderr: vec![],
+	})
+}
+
+/// Read the whole content of a child process pipe on a separate thread
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+	thread::spawn(move || {
+		let mut buffer = vec![];
+		if let Some(mut pipe) = pipe {
+			let _ = pipe.read_to_end(&mut buffer);
+		}
+		buffer
+	})
+}
+
+/// Arguments of `adb shell` running `command` in a pseudo-terminal.
+/// `-t` is passed twice to force the pty allocation even when stdin is not a terminal
+pub(crate) fn make_shell_tty_args(command: &str) -> Vec<&str> {
+	vec![
+		"shell", "-t", "-t", command,
+	]
+}
+
+/// Returns a new, unique, path for a temporary file on the device
+pub(crate) fn make_temp_path(suffix: &str) -> String {
+	format!("{TEMP_DIR}/{TEMP_PREFIX}{}{suffix}", Uuid::new_v4())
+}
+
+/// Shell script removing the crate temporary files, printing the path of each removed file
+pub(crate) fn make_cleanup_temp_script() -> String {
+	format!("for f in {TEMP_DIR}/{TEMP_PREFIX}*; do [ -e \"$f\" ] && rm -rf \"$f\" && echo \"$f\"; done; true")
+}
+
+/// Find the transport id of the given connection in the `adb devices -l` output.
+/// A [`ConnectionType::USB`] connection is resolved only when a single usb device is attached.
+pub(crate) fn parse_transport_id(output: &str, addr: &ConnectionType) -> Option<u32> {
+	let devices = output
+		.lines()
+		.filter_map(|line| {
+			let serial = line.split_whitespace().next()?;
+			let transport_id = RE_TRANSPORT_ID.captures(line)?.get(1)?.as_str().parse::<u32>().ok()?;
+			let usb = line.split_whitespace().any(|field| field.starts_with("usb:"));
+			Some((serial, usb, transport_id))
+		})
+		.collect::<Vec<_>>();
+
+	match addr {
+		ConnectionType::Transport(id) => Some(u32::from(*id)),
+		ConnectionType::TcpIp(_) => devices
+			.iter()
+			.find(|(serial, _, _)| {
+				ConnectionType::from_str(serial).is_ok_and(|conn| conn.normalized() == addr.normalized())
+			})
+			.map(|(_, _, id)| *id),
+		ConnectionType::USB => match devices.iter().filter(|(_, usb, _)| *usb).collect::<Vec<_>>().as_slice() {
+			[(_, _, id)] => Some(*id),
+			_ => None,
+		},
+	}
+}
+
+/// Returns true if the error is caused by the connection to the device, rather than by the command
+pub(crate) fn is_transient_error(err: &Error) -> bool {
+	let kind = match err {
+		Error::DeviceOffline => return true,
+		Error::IoError(err) => err.kind(),
+		Error::CommandError(simple_cmd::Error::IoError(err)) => err.kind(),
+		Error::CommandError(simple_cmd::Error::CommandError(err)) => {
+			return is_transient_stderr(&String::from_utf8_lossy(&err.stderr));
+		}
+		_ => return false,
+	};
+	matches!(kind, std::io::ErrorKind::NotConnected | std::io::ErrorKind::BrokenPipe)
+}
+
+/// Returns true if adb itself failed to reach the device, as opposed to the command exiting with an error
+pub(crate) fn is_transient_output(output: &Output) -> bool {
+	output.error() && is_transient_stderr(&String::from_utf8_lossy(&output.stderr))
+}
+
+/// Only the errors printed by adb itself are matched, since the commands run on the device may print anything
+fn is_transient_stderr(stderr: &str) -> bool {
+	stderr.lines().any(|line| {
+		let line = line.trim();
+		let line = line.strip_prefix("adb: ").unwrap_or(line);
+		line == "error: device offline"
+			|| line == "error: no devices/emulators found"
+			|| (line.starts_with("error: device '") && line.ends_with("' not found"))
+	})
+}
+
+pub(crate) fn make_wait_for_boot_script(wake_on_ready: bool) -> String {
+	let script = "while [[ -z $(getprop sys.boot_completed) ]]; do sleep 1; done";
+	if wake_on_ready {
+		format!("{script}; input keyevent 143")
+	} else {
+		script.to_string()
+	}
+}
+
+pub(crate) fn make_wait_for_arg(state: WaitState, transport: WaitTransport) -> String {
+	format!("wait-for-{transport}-{state}")
+}
+
+/// Arguments of the `adb forward` command
+pub(crate) fn make_forward_args<'a>(local: &'a str, remote: &'a str) -> Vec<&'a str> {
+	vec![
+		"forward", local, remote,
+	]
+}
+
+/// Parse the local port allocated by `adb forward tcp:0`
+pub(crate) fn parse_forward_port(output: &str) -> Result<u16> {
+	Ok(output.trim().parse::<u16>()?)
+}
+
+/// Parse the `serial local remote` lines printed by `adb forward --list` and `adb reverse --list`
+pub(crate) fn parse_forward_list(output: &str) -> Result<Vec<ForwardEntry>> {
+	output
+		.lines()
+		.filter(|line| !line.trim().is_empty())
+		.map(ForwardEntry::try_from)
+		.collect()
+}
+
+/// Returns the given bytes if they start with the png signature
+pub(crate) fn check_png(data: Vec<u8>) -> Result<Vec<u8>> {
+	if data.starts_with(PNG_SIGNATURE) { Ok(data) } else { Err(Error::ParseInputError) }
+}
+
+/// Decode and re-encode a png image. The encoder only writes the critical chunks,
+/// dropping every ancillary chunk of the source image.
+pub(crate) fn normalize_png(data: &[u8]) -> Result<Vec<u8>> {
+	let img = image::load_from_memory_with_format(data, ImageFormat::Png)?;
+	let mut buffer = Cursor::new(Vec::new());
+	img.write_to(&mut buffer, ImageFormat::Png)?;
+	Ok(buffer.into_inner())
+}
 
 impl Client {
 	pub fn new(adb: Adb, addr: ConnectionType, debug: bool) -> Self {
-		Client { adb, addr, debug }
+		Client {
+			adb,
+			addr,
+			debug,
+			retry: None,
+			auto_root: false,
+			config: ClientConfig::default(),
+		}
+	}
+
+	/// When auto root is enabled, restart adbd as root before running a method requiring it
+	pub(crate) fn ensure_root(&self) -> Result<()> {
+		if !self.auto_root {
+			return Ok(());
+		}
+		match self.root() {
+			Ok(true) => Ok(()),
+			_ => Err(Error::RootRequired),
+		}
+	}
+
+	/// Run the given command, retrying it according to the retry policy
+	pub(crate) fn run_with_retry<F: FnMut() -> Result<Output>>(&self, mut run: F) -> Result<Output> {
+		let policy = match self.retry {
+			Some(policy) => policy,
+			None => return run(),
+		};
+
+		let mut attempt = 0;
+		loop {
+			let result = run();
+			let transient = match &result {
+				Ok(output) => is_transient_output(output),
+				Err(err) => is_transient_error(err),
+			};
+			if !transient || attempt >= policy.attempts {
+				return result;
+			}
+
+			attempt += 1;
+			if self.debug {
+				warn!("[{}] transient failure, retrying ({attempt}/{})", self.addr, policy.attempts);
+			}
+			sleep(policy.backoff);
+			let _ = self.connect(Some(policy.backoff));
+		}
+	}
+
+	/// Build the adb command of this client with `command` and run it, retrying it according to the retry policy
+	pub(crate) fn run_command<F: Fn(AdbCommandBuilder) -> AdbCommandBuilder>(&self, command: F) -> Result<Output> {
+		self.run_with_retry(|| Ok(command(AdbCommandBuilder::from(self)).build().output()?))
 	}
 
 	/// Attempt to connect to a tcp/ip client, optionally waiting until the given
-	/// timeout expires.
+	/// timeout expires. When the device is reachable but can't be used, [`Error::DeviceOffline`]
+	/// or [`Error::DeviceUnauthorized`] is returned.
 	/// # Examples:
 	/// ```rust
 	/// use radb_client::types::ConnectionType;
@@ -50,8 +455,8 @@ impl Client {
 	/// }
 	/// ```
 	pub fn connect(&self, timeout: Option<Duration>) -> Result<()> {
-		if self.is_connected() {
-			return Ok(());
+		if let Ok(state) = self.query_state() {
+			return check_device_state(state).map(|_| ());
 		}
 
 		let addr = match self.addr {
@@ -59,26 +464,51 @@ impl Client {
 			_ => return Err(Error::InvalidConnectionTypeError),
 		};
 
-		let mut command = CommandBuilder::adb(&self.adb).with_debug(self.debug);
+		let mut command = AdbCommandBuilder::new(&self.adb).with_debug(self.debug);
 		command = command.arg("connect").arg(addr.to_string()).timeout(timeout);
 
-		let output = command.build().output()?;
+		let started = Instant::now();
+		let output = handle_timeout("connect", started, timeout, command.build().output()?)?;
+
+		match parse_connect_output(
+			&String::from_utf8_lossy(&output.stdout),
+			&String::from_utf8_lossy(&output.stderr),
+			output.success(),
+		) {
+			// the device is connected already, which is the state we wanted
+			Ok(()) | Err(Error::AlreadyConnected) => {}
+			Err(err) => return Err(err),
+		}
+		self.query_state().and_then(check_device_state).map(|_| ())
+	}
 
-		if output.error() {
-			return Err(Error::IoError(std::io::Error::from(std::io::ErrorKind::NotConnected)));
-		} else {
-			match self.is_connected() {
-				true => Ok(()),
-				false => Err(Error::IoError(std::io::Error::from(std::io::ErrorKind::NotConnected))),
+	/// Connect to the device, restart adb as root and remount the partitions read-write.
+	/// If any of the steps fails, an [`Error::SetupError`] is returned with the name
+	/// of the failing step.
+	pub fn connect_root_remount(&self, timeout: Option<Duration>) -> Result<()> {
+		let setup_error = |step: &'static str| {
+			move |err: Error| Error::SetupError {
+				step,
+				source: Box::new(err),
 			}
+		};
+
+		self.connect(timeout).map_err(setup_error("connect"))?;
+
+		if !self.root().map_err(setup_error("root"))? {
+			return Err(setup_error("root")(Error::IoError(std::io::Error::from(
+				std::io::ErrorKind::PermissionDenied,
+			))));
 		}
+
+		self.remount(false).map_err(setup_error("remount"))
 	}
 
 	/// Disconnect a device.
 	/// Note that if the connection type is not tcp/ip, all devices
 	/// will be disconnected
 	pub fn disconnect(&self) -> Result<bool> {
-		let mut command = CommandBuilder::adb(&self.adb).with_debug(self.debug);
+		let mut command = AdbCommandBuilder::new(&self.adb).with_debug(self.debug);
 		command = command.arg("disconnect");
 		command = match self.addr {
 			ConnectionType::TcpIp(ip) => command.arg(ip.to_string()),
@@ -92,7 +522,7 @@ impl Client {
 	}
 
 	pub fn try_disconnect(&self) -> Result<bool> {
-		let mut command = CommandBuilder::adb(&self.adb).with_debug(self.debug);
+		let mut command = AdbCommandBuilder::new(&self.adb).with_debug(self.debug);
 		command = command.arg("disconnect");
 		command = match self.addr {
 			ConnectionType::TcpIp(ip) => command.arg(ip.to_string()),
@@ -107,7 +537,7 @@ impl Client {
 	/// disconnect all connected devices
 	pub fn disconnect_all(&self) -> Result<()> {
 		super::shell::handle_result(
-			CommandBuilder::adb(&self.adb)
+			AdbCommandBuilder::new(&self.adb)
 				.with_debug(self.debug)
 				.arg("disconnect")
 				.build()
@@ -115,33 +545,208 @@ impl Client {
 		)
 	}
 
-	/// Checks if the client is already connected
+	/// Returns true if the device is connected and usable (neither offline nor unauthorized)
 	pub fn is_connected(&self) -> bool {
-		let mut command = CommandBuilder::from(self);
-		command = command
+		self.query_state().and_then(check_device_state).is_ok()
+	}
+
+	/// Returns the device state
+	pub fn get_state(&self) -> Result<DeviceState> {
+		let output = self.run_command(|command| command.arg("get-state").timeout(Some(self.config.get_state_timeout)))?;
+		parse_device_state(Arg::as_str(&output.stdout)?, Arg::as_str(&output.stderr)?)
+	}
+
+	/// Returns the device state without retrying, for the methods checking or restoring the connection
+	fn query_state(&self) -> Result<DeviceState> {
+		let output = AdbCommandBuilder::from(self)
 			.arg("get-state")
-			.timeout(Some(Duration::from_millis(GET_STATE_TIMEOUT)));
-		let output = command.build().output();
-		return if let Ok(output) = output { output.success() } else { false };
+			.timeout(Some(self.config.get_state_timeout))
+			.build()
+			.output()?;
+		parse_device_state(Arg::as_str(&output.stdout)?, Arg::as_str(&output.stderr)?)
+	}
+
+	/// When the device is offline or unauthorized, reconnect it and wait until its state is `device`.
+	/// Returns [`Error::DeviceUnauthorized`] if the device is still unauthorized when the timeout expires,
+	/// since the authorization must be accepted on the device.
+	pub fn recover_if_offline(&self, timeout: Duration) -> Result<()> {
+		match self.query_state()? {
+			DeviceState::Offline | DeviceState::Unauthorized => {}
+			_ => return Ok(()),
+		}
+
+		self.reconnect(Some(Reconnect::Offline))?;
+
+		let start = Instant::now();
+		loop {
+			let state = self.query_state().ok();
+			if state == Some(DeviceState::Device) {
+				return Ok(());
+			}
+
+			let elapsed = start.elapsed();
+			if elapsed > timeout {
+				return match state {
+					Some(DeviceState::Unauthorized) => Err(Error::DeviceUnauthorized),
+					Some(DeviceState::Offline) => Err(Error::DeviceOffline),
+					_ => Err(Error::Timeout {
+						command: "recover offline device".to_string(),
+						elapsed,
+					}),
+				};
+			}
+			sleep(self.config.get_state_timeout);
+		}
 	}
 
-	/// Wait for device to be available with an optional timeout
-	pub fn wait_for_device(&self, timeout: Option<Duration>) -> Result<()> {
-		CommandBuilder::from(self)
+	/// Returns the adb transport id of this client's device, as listed by `adb devices -l`
+	pub fn resolve_transport_id(&self) -> Result<u32> {
+		let output = AdbCommandBuilder::new(&self.adb)
+			.with_debug(self.debug)
 			.args([
-				"wait-for-device",
-				"shell",
-				"while [[ -z $(getprop sys.boot_completed) ]]; do sleep 1; done; input keyevent 143",
+				"devices", "-l",
 			])
-			.timeout(timeout)
 			.build()
 			.output()?;
+		if output.error() {
+			return Err(output.into());
+		}
+		parse_transport_id(Arg::as_str(&output.stdout)?, &self.addr).ok_or(Error::NameNotFoundError(self.addr.to_string()))
+	}
+
+	/// Returns a new client targeting the transport id of this client's device, so that it keeps
+	/// addressing the same device even if its serial or ip address changes.
+	/// Fails with [`Error::TransportIdOutOfRange`] when the id can't be addressed by [`ConnectionType::Transport`]
+	pub fn pin_transport(&self) -> Result<Client> {
+		let id = self.resolve_transport_id()?;
+		let id = u8::try_from(id).map_err(|_| Error::TransportIdOutOfRange(id))?;
+		Ok(Client {
+			addr: ConnectionType::Transport(id),
+			..self.clone()
+		})
+	}
+
+	/// Returns the features supported by both the device and the adb host
+	pub fn features(&self) -> Result<Vec<String>> {
+		let output = self.run_command(|command| command.arg("features"))?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_features(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Returns true if the given feature is supported by both the device and the adb host
+	pub fn has_feature(&self, feature: &str) -> Result<bool> {
+		Ok(self.features()?.iter().any(|f| f == feature))
+	}
+
+	/// Returns the metadata of the given remote path.
+	/// When the device supports `stat_v2` the adb sync protocol is used, which reports 64-bit sizes and times,
+	/// otherwise falls back to `stat` on the device.
+	pub fn remote_stat<T: Arg>(&self, path: T) -> Result<RemoteStat> {
+		let path = path.as_str()?;
+		if self.has_feature(FEATURE_STAT_V2)? {
+			return crate::sync::stat_v2(&self.addr, path, self.config.default_command_timeout);
+		}
+
+		let output = self.shell().exec(
+			vec![
+				"stat",
+				"-c",
+				"'%f %u %g %s %X %Y %Z'",
+				path,
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		parse_toybox_stat(Arg::as_str(&output.stdout)?).ok_or(Error::ParseInputError)
+	}
+
+	/// Restart the adb daemon of the device listening on tcp/ip at the given port,
+	/// waiting briefly for the device to come back online.
+	pub fn tcpip(&self, port: u16) -> Result<()> {
+		let output = AdbCommandBuilder::from(self).arg("tcpip").arg(port.to_string()).build().output()?;
+
+		if output.error() {
+			return Err(output.into());
+		}
+
+		sleep(self.config.sleep_after_tcpip);
+		self.wait_for_state(Duration::from_millis(TCPIP_RESTART_TIMEOUT))
+	}
+
+	/// Restart the adb daemon of the device listening on usb
+	pub fn usb(&self) -> Result<()> {
+		let output = AdbCommandBuilder::from(self).arg("usb").build().output()?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+
+	/// Poll the device state until it's connected or the timeout expires
+	fn wait_for_state(&self, timeout: Duration) -> Result<()> {
+		let start = Instant::now();
+		while !self.is_connected() {
+			let elapsed = start.elapsed();
+			if elapsed > timeout {
+				return Err(Error::Timeout {
+					command: "wait for device state".to_string(),
+					elapsed,
+				});
+			}
+			sleep(self.config.get_state_timeout);
+		}
+		Ok(())
+	}
+
+	/// Wait for device to be available and booted, with an optional timeout.
+	/// When `wake_on_ready` is true the device is woken up (`input keyevent 143`) once booted,
+	/// which was the behavior before this option was added.
+	/// Returns [`Error::Timeout`] if the device isn't ready when the timeout expires.
+	pub fn wait_for_device(&self, timeout: Option<Duration>, wake_on_ready: bool) -> Result<()> {
+		let script = make_wait_for_boot_script(wake_on_ready);
+		self.wait_for_command(
+			WaitState::Device,
+			WaitTransport::Any,
+			timeout,
+			&[
+				"shell",
+				script.as_str(),
+			],
+		)?;
 		Ok(())
 	}
 
+	/// Wait for the device to be in the given state (`adb wait-for-<transport>-<state>`), with an optional timeout.
+	/// Returns [`Error::Timeout`] if the timeout expires first.
+	pub fn wait_for(&self, state: WaitState, transport: WaitTransport, timeout: Option<Duration>) -> Result<()> {
+		let output = self.wait_for_command(state, transport, timeout, &[])?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+
+	fn wait_for_command(
+		&self,
+		state: WaitState,
+		transport: WaitTransport,
+		timeout: Option<Duration>,
+		args: &[&str],
+	) -> Result<Output> {
+		let wait_for = make_wait_for_arg(state, transport);
+		let timeout = timeout.or(self.config.default_command_timeout);
+		let started = Instant::now();
+		let output = AdbCommandBuilder::from(self)
+			.arg(wait_for.as_str())
+			.args(args)
+			.timeout(timeout)
+			.build()
+			.output()?;
+		handle_timeout(wait_for.as_str(), started, timeout, output)
+	}
+
 	/// Get the current awake status
 	pub fn get_wakefulness(&self) -> Result<Wakefulness> {
-		let command1 = CommandBuilder::from(self)
+		let command1 = AdbCommandBuilder::from(self)
 			.args(vec![
 				"shell", "dumpsys", "power",
 			])
@@ -163,6 +768,40 @@ impl Client {
 		Ok(self.get_wakefulness()? != Wakefulness::Asleep)
 	}
 
+	/// Wake up the device with `KEYCODE_WAKEUP`, waiting until it's awake.
+	/// Returns [`Error::Timeout`] if the device doesn't wake up
+	pub fn wake(&self) -> Result<()> {
+		self.set_wakefulness(KeyCode::KEYCODE_WAKEUP, Wakefulness::Awake)
+	}
+
+	/// Put the device to sleep with `KEYCODE_SLEEP`, waiting until it's asleep.
+	/// Returns [`Error::Timeout`] if the device doesn't go to sleep
+	pub fn sleep(&self) -> Result<()> {
+		self.set_wakefulness(KeyCode::KEYCODE_SLEEP, Wakefulness::Asleep)
+	}
+
+	/// Send the keycode unless the device is already in the `target` state, then poll its wakefulness
+	fn set_wakefulness(&self, keycode: KeyCode, target: Wakefulness) -> Result<()> {
+		if self.get_wakefulness()? == target {
+			return Ok(());
+		}
+		self.shell().send_keyevent(keycode, None, None)?;
+
+		let start = Instant::now();
+		loop {
+			if self.get_wakefulness().is_ok_and(|wakefulness| wakefulness == target) {
+				return Ok(());
+			}
+			if start.elapsed() > Duration::from_millis(WAKEFULNESS_TIMEOUT) {
+				return Err(Error::Timeout {
+					command: format!("wait for {target}"),
+					elapsed: start.elapsed(),
+				});
+			}
+			sleep(self.config.get_state_timeout);
+		}
+	}
+
 	/// return the adb root status for the current connection
 	pub fn is_root(&self) -> Result<bool> {
 		self.shell().is_root()
@@ -174,10 +813,10 @@ impl Client {
 			return Ok(true);
 		}
 
-		let output = CommandBuilder::from(self).arg("root").build().output()?;
+		let output = AdbCommandBuilder::from(self).arg("root").build().output()?;
 
 		if output.success() {
-			sleep(Duration::from_millis(SLEEP_AFTER_ROOT));
+			sleep(self.config.sleep_after_root);
 			Ok(self.is_root()?)
 		} else {
 			Err(Error::CommandError(simple_cmd::Error::from(output)))
@@ -186,7 +825,7 @@ impl Client {
 
 	/// unroot the adb connection
 	pub fn unroot(&self) -> Result<()> {
-		super::shell::handle_result(CommandBuilder::from(self).arg("unroot").build().output()?)
+		super::shell::handle_result(AdbCommandBuilder::from(self).arg("unroot").build().output()?)
 	}
 
 	/// Save screencap to local file.
@@ -210,39 +849,70 @@ impl Client {
 	///    }
 	/// ```
 	pub fn save_screencap(&self, output: File) -> Result<()> {
+		self.screenshot_to(output)
+	}
+
+	/// Take a png screenshot, writing it to the given writer
+	pub fn screenshot_to<W: Write>(&self, mut out: W) -> Result<()> {
 		let args = vec![
-			"exec-out",
-			"screencap",
-			"-p",
+			"exec-out".into(),
+			"screencap".into(),
+			"-p".into(),
 		];
-		let pipe_out = Stdio::from(output);
-		let mut cmd = std::process::Command::new(self.adb.as_os_str());
+		let (result, status) = self.pipe_stdout(args, None, None, false, |mut stdout| std::io::copy(&mut stdout, &mut out))?;
+		result?;
+		handle_exit_status(status)
+	}
 
-		cmd.args(self.addr.as_args())
-			.args(args)
-			.stdout(pipe_out)
-			.stderr(Stdio::piped());
+	/// Take a png screenshot, returning its bytes
+	pub fn screenshot(&self) -> Result<Vec<u8>> {
+		self.screencap_bytes()
+	}
+
+	/// Take a png screenshot with `exec-out screencap -p`, returning its bytes without touching the filesystem.
+	/// Returns [`Error::ParseInputError`] if the device output is not a png image.
+	pub fn screencap_bytes(&self) -> Result<Vec<u8>> {
+		let mut buffer = vec![];
+		self.screenshot_to(&mut buffer)?;
+		check_png(buffer)
+	}
 
-		if self.debug {
-			cmd.debug();
+	/// Record the screen as a raw h264 stream, writing it to the given writer until the
+	/// time limit of the options is reached or the `cancel` channel fires
+	pub fn screen_record_to<W: Write>(
+		&self,
+		options: Option<ScreenRecordOptions>,
+		mut out: W,
+		cancel: Option<Receiver<()>>,
+	) -> Result<()> {
+		let mut args = vec![
+			"exec-out".into(),
+			"screenrecord".into(),
+			"--output-format=h264".into(),
+		];
+		if let Some(options) = options {
+			if options.audio {
+				args.push(self.shell().screenrecord_audio_flag()?.into());
+			}
+			args.extend(options);
 		}
+		args.push("-".into());
 
-		cmd.output()?;
+		let (result, status) = self.pipe_stdout(args, cancel, None, false, |mut stdout| std::io::copy(&mut stdout, &mut out))?;
+		result?;
+		handle_exit_status(status)
+	}
 
-		Ok(())
+	/// Take a screenshot and return the png re-encoded without any ancillary chunk
+	/// (timestamps, exif, text...), so that the same pixels always produce the same bytes.
+	/// Useful for hashing screenshots in visual regression tests.
+	pub fn screenshot_png_normalized(&self) -> Result<Vec<u8>> {
+		normalize_png(&self.screencap_bytes()?)
 	}
 
 	/// copy the device screenshot to clipboard
 	pub fn copy_screencap(&self) -> Result<()> {
-		let mut dir = temp_dir();
-		let file_name = format!("{}.png", Uuid::new_v4());
-		dir.push(file_name);
-
-		let path = dir.as_path().to_owned();
-		let file = File::create(path.as_path())?;
-		self.save_screencap(file)?;
-
-		let img = image::open(path.as_path())?;
+		let img = image::load_from_memory_with_format(&self.screencap_bytes()?, ImageFormat::Png)?;
 		let width = img.width();
 		let height = img.height();
 
@@ -269,14 +939,14 @@ impl Client {
 			args.push(s.to_owned());
 		}
 
-		CommandBuilder::from(self).args(args).build().output()?;
+		AdbCommandBuilder::from(self).args(args).build().output()?;
 		Ok(())
 	}
 
 	/// remount partitions read-write. if a reboot is required, `reboot_if_required` will
 	/// will automatically reboot the device.
 	pub fn remount(&self, reboot_if_required: bool) -> Result<()> {
-		let mut cmd = CommandBuilder::from(self).arg("remount");
+		let mut cmd = AdbCommandBuilder::from(self).arg("remount");
 		if reboot_if_required {
 			cmd = cmd.arg("-R");
 		}
@@ -292,7 +962,7 @@ impl Client {
 
 	/// print serial-number
 	pub fn get_seriano(&self) -> Result<String> {
-		let output = CommandBuilder::from(self).arg("get-serialno").build().output()?;
+		let output = self.run_command(|command| command.arg("get-serialno"))?;
 		Ok(Arg::as_str(&output.stdout)?.trim().to_string())
 	}
 
@@ -300,7 +970,7 @@ impl Client {
 	/// reconnect device         kick connection from device side to force reconnect
 	/// reconnect offline        reset offline/unauthorized devices to force reconnect
 	pub fn reconnect(&self, r#type: Option<Reconnect>) -> Result<String> {
-		let mut cmd = CommandBuilder::from(self).arg("reconnect".to_string());
+		let mut cmd = AdbCommandBuilder::from(self).arg("reconnect".to_string());
 		if let Some(reconnect_type) = r#type {
 			cmd = cmd.arg(reconnect_type.to_string());
 		}
@@ -308,6 +978,28 @@ impl Client {
 		Ok(Arg::as_str(&output.stdout)?.trim().to_owned())
 	}
 
+	/// Runs the given command with a pseudo-terminal allocated (`adb shell -t`), for those commands
+	/// which behave differently without a tty.
+	/// The output may include terminal control sequences, see [`crate::utils::strip_terminal_sequences`].
+	/// Returns [`Error::UnsupportedOnThisApi`] if the device doesn't support `shell_v2`
+	pub fn shell_tty(&self, command: &str) -> Result<Output> {
+		if !self.has_feature(FEATURE_SHELL_V2)? {
+			return Err(Error::UnsupportedOnThisApi);
+		}
+		let output = self.run_command(|builder| builder.args(make_shell_tty_args(command)))?;
+		if output.error() { Err(output.into()) } else { Ok(output) }
+	}
+
+	/// Remove the temporary files left on the device by the crate (e.g. by an interrupted [`crate::types::PackageManager::deploy`]),
+	/// returning the number of removed files
+	pub fn cleanup_temp(&self) -> Result<u32> {
+		let output = self.shell().exec(vec![make_cleanup_temp_script()], None, None)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(Arg::as_str(&output.stdout)?.lines().filter(|line| !line.trim().is_empty()).count() as u32)
+	}
+
 	///  bugreport PATH
 	///     write bugreport to given PATH (default=bugreport.zip);
 	///     if PATH is a directory, the bug report is saved in that directory.
@@ -320,38 +1012,200 @@ impl Client {
 			],
 			None => vec!["bugreport"],
 		};
-		CommandBuilder::from(self).args(args).build().output().map_err(|e| e.into())
+		self.run_command(|command| command.args(&args))
 	}
 
 	pub fn clear_logcat(&self) -> Result<()> {
-		let output = CommandBuilder::from(self)
-			.args([
+		let output = self.run_command(|command| {
+			command.args([
 				"logcat", "-b", "all", "-c",
 			])
-			.build()
-			.output()?;
+		})?;
 
 		if output.error() { Err(output.into()) } else { Ok(()) }
 	}
 
 	pub fn logcat(&self, options: LogcatOptions, cancel: Option<Receiver<()>>) -> Result<Output> {
-		let mut command = CommandBuilder::from(self);
-		let mut args = vec!["logcat".into()];
+		let mut args: Vec<OsString> = vec!["logcat".into()];
 		args.extend(options.clone());
 
-		if let Some(timeout) = options.timeout {
-			command = command.with_timeout(timeout);
+		self.run_command(|mut command| {
+			if let Some(timeout) = options.timeout {
+				command = command.with_timeout(timeout);
+			}
+
+			if let Some(signal) = cancel.clone() {
+				command = command.with_signal(signal);
+			}
+
+			command.args(&args)
+		})
+	}
+
+	/// Dump the crash and system logcat buffers and extract the crashes and ANRs they contain.
+	/// The `dump` and `format` of the options are overridden.
+	pub fn extract_crashes(&self, options: LogcatOptions) -> Result<Vec<CrashReport>> {
+		let mut args: Vec<OsString> = vec![
+			"logcat".into(),
+			"-b".into(),
+			"crash".into(),
+			"-b".into(),
+			"system".into(),
+		];
+		let timeout = options.timeout;
+		args.extend(LogcatOptions {
+			dump: true,
+			format: Some("year".to_string()),
+			..options
+		});
+
+		let output = AdbCommandBuilder::from(self)
+			.with_args(args)
+			.timeout(timeout.or(self.config.default_command_timeout))
+			.build()
+			.output()?;
+		if output.error() && !output.kill() {
+			return Err(output.into());
+		}
+		Ok(parse_crash_reports(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Dump the selected buffers and return their entries sorted by timestamp, so that the entries of
+	/// the different buffers are merged in a single chronological sequence.
+	/// The `dump` and `format` of the options are overridden.
+	pub fn logcat_merged_sorted(&self, options: LogcatOptions, cancel: Option<Receiver<()>>) -> Result<Vec<LogcatEntry>> {
+		let options = LogcatOptions {
+			dump: true,
+			format: Some("year".to_string()),
+			..options
+		};
+		let output = self.logcat(options, cancel)?;
+		if output.error() && !output.kill() && !output.interrupt() {
+			return Err(output.into());
 		}
+		Ok(parse_logcat_merged(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Run logcat invoking `on_line` for each line, as soon as it's printed.
+	/// The logcat process is terminated when `on_line` returns false, when the `cancel` channel fires
+	/// or when the `timeout` of the options expires.
+	/// When adb exits by itself with a failure (eg. the device is not connected) an error is returned.
+	pub fn logcat_stream<F: FnMut(String) -> bool>(
+		&self,
+		options: LogcatOptions,
+		cancel: Option<Receiver<()>>,
+		mut on_line: F,
+	) -> Result<()> {
+		let timeout = options.timeout;
+		let mut args = vec!["logcat".into()];
+		args.extend(options);
+
+		let (result, status) = self.pipe_stdout(args, cancel, timeout, true, |stdout| {
+			BufReader::new(stdout)
+				.split(b'\n')
+				.map(|line| line.map(|line| String::from_utf8_lossy(&line).trim_end().to_string()))
+				.try_for_each(|line| match on_line(line?) {
+					true => Ok(()),
+					false => Err(None),
+				})
+		})?;
+
+		match result {
+			Err(Some(err)) => Err(Error::IoError(err)),
+			// stopped by `on_line`
+			Err(None) => Ok(()),
+			// end of stream: adb exited by itself (e.g. device not found) or was killed by cancel/timeout
+			Ok(()) => handle_exit_status(status),
+		}
+	}
+
+	/// Spawn adb with the given arguments, passing its stdout to `read`.
+	/// The process is killed when the `cancel` channel fires or the `timeout` expires, which unblocks
+	/// the reader, or after `read` returns when `kill_after_read` is true.
+	pub(crate) fn pipe_stdout<R, F>(
+		&self,
+		args: Vec<OsString>,
+		cancel: Option<Receiver<()>>,
+		timeout: Option<Duration>,
+		kill_after_read: bool,
+		read: F,
+	) -> Result<(R, ExitStatus)>
+	where
+		F: FnOnce(ChildStdout) -> R,
+	{
+		let mut child = AdbCommandBuilder::from(self)
+			.with_args(args)
+			.stdout(Some(Stdio::piped()))
+			.stderr(Some(Stdio::null()))
+			.build()
+			.command()
+			.spawn()?;
+		let stdout = child.stdout.take().ok_or(Error::from(std::io::ErrorKind::BrokenPipe))?;
+		let child = Arc::new(Mutex::new(child));
+
+		let (done_sender, done_receiver) = crossbeam_channel::bounded::<()>(0);
+		let watcher = {
+			let child = Arc::clone(&child);
+			let mut cancel = cancel.unwrap_or(crossbeam_channel::never());
+			let timeout = timeout.map(crossbeam_channel::after).unwrap_or(crossbeam_channel::never());
+			thread::spawn(move || loop {
+				select! {
+					recv(cancel) -> msg => match msg {
+						Ok(_) => {
+							let _ = child.lock().unwrap().kill();
+							break;
+						}
+						// the sender was dropped, keep enforcing the timeout
+						Err(_) => cancel = crossbeam_channel::never(),
+					},
+					recv(timeout) -> _ => {
+						let _ = child.lock().unwrap().kill();
+						break;
+					},
+					recv(done_receiver) -> _ => break,
+				}
+			})
+		};
+
+		let result = read(stdout);
+
+		drop(done_sender);
+		let _ = watcher.join();
 
-		if let Some(signal) = cancel {
-			command = command.with_signal(signal);
+		let mut child = child.lock().unwrap();
+		if kill_after_read {
+			let _ = child.kill();
 		}
+		let status = child.wait()?;
+		Ok((result, status))
+	}
+
+	/// Same as [`Client::logcat`] but only returns the entries logged since the device
+	/// boot. The `since` option is overridden with the device boot time, or with the time
+	/// of the oldest entry still available in the log buffer, whichever comes later.
+	pub fn logcat_since_boot(&self, options: LogcatOptions, cancel: Option<Receiver<()>>) -> Result<Output> {
+		let boot_time = self.boot_time()?;
+		let earliest = self.logcat_earliest_entry()?;
+		self.logcat(logcat_options_since_boot(options, boot_time, earliest), cancel)
+	}
+
+	/// Returns the time of the oldest entry available in the logcat buffer, if any
+	pub fn logcat_earliest_entry(&self) -> Result<Option<DateTime<Local>>> {
+		let output = self.run_command(|command| {
+			command.args([
+				"logcat", "-d", "-m", "1", "-v", "epoch",
+			])
+		})?;
 
-		command.with_args(args).build().output().map_err(|e| e.into())
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(Arg::as_str(&output.stdout)?.lines().find_map(parse_logcat_epoch))
 	}
 
 	/// Returns the device mac-address
 	pub fn get_mac_address(&self) -> Result<MacAddress> {
+		self.ensure_root()?;
 		let output = self.shell().cat("/sys/class/net/eth0/address")?;
 		let mac_address_str = Arg::as_str(&output)?.trim_end();
 		let mac_address = MacAddress::try_from(mac_address_str)?;
@@ -360,6 +1214,7 @@ impl Client {
 
 	/// Returns the wlan mac-address
 	pub fn get_wlan_address(&self) -> Result<MacAddress> {
+		self.ensure_root()?;
 		let output = self.shell().cat("/sys/class/net/wlan0/address")?;
 		let mac_address_str = Arg::as_str(&output)?.trim_end();
 		let mac_address = MacAddress::try_from(mac_address_str)?;
@@ -374,44 +1229,328 @@ impl Client {
 		Ok(boot_id)
 	}
 
+	/// Returns the device boot time (the `btime` entry of /proc/stat)
+	pub fn boot_time(&self) -> Result<DateTime<Local>> {
+		let output = self.shell().cat("/proc/stat")?;
+		parse_boot_time(Arg::as_str(&output)?)
+	}
+
 	/// Disable verity
 	pub fn disable_verity(&self) -> Result<()> {
-		let output = CommandBuilder::from(self).arg("disable-verity").build().output()?;
+		let output = AdbCommandBuilder::from(self).arg("disable-verity").build().output()?;
 
 		if !output.success() { Err(output.into()) } else { Ok(()) }
 	}
 
 	/// Enable verity
 	pub fn enable_verity(&self) -> Result<()> {
-		let output = CommandBuilder::from(self).arg("enable-verity").build().output()?;
+		let output = AdbCommandBuilder::from(self).arg("enable-verity").build().output()?;
 		println!("output: {output:?}");
 
 		if !output.success() { Err(output.into()) } else { Ok(()) }
 	}
 
-	pub fn pull<S, T>(&self, src: S, dst: T) -> Result<Output>
-	where
-		S: Arg,
-		T: Arg,
-	{
-		let mut command = CommandBuilder::from(self);
-		command = command.arg("pull").arg(src.as_str()?).arg(dst.as_str()?);
-		command.build().output().map_err(|e| e.into())
+	/// Forward socket connections from the `local` spec to the `remote` spec (eg. `tcp:8080`).
+	/// When `local` is `tcp:0` adb picks a free local port, which is returned.
+	pub fn forward(&self, local: &str, remote: &str) -> Result<Option<u16>> {
+		let output = self.port_forward(make_forward_args(local, remote))?;
+		if local == FORWARD_AUTO_LOCAL {
+			Ok(Some(parse_forward_port(Arg::as_str(&output.stdout)?)?))
+		} else {
+			Ok(None)
+		}
 	}
 
-	pub fn push<S, T>(&self, src: S, dst: T) -> Result<Output>
-	where
-		S: Arg,
-		T: Arg,
-	{
-		let mut command = CommandBuilder::from(self);
-		command = command.arg("push").arg(src.as_str()?).arg(dst.as_str()?);
-		command.build().output().map_err(|e| e.into())
+	/// Forward a free local tcp port, picked by adb, to the given remote socket.
+	/// Returns the allocated local port.
+	pub fn forward_auto(&self, remote: &str) -> Result<u16> {
+		self.forward(FORWARD_AUTO_LOCAL, remote)?.ok_or(Error::ParseInputError)
 	}
 
-	pub fn install<T>(&self, path: T, install_options: Option<AdbInstallOptions>) -> Result<()>
-	where
-		T: Arg,
+	/// List all the forward socket connections
+	pub fn forward_list(&self) -> Result<Vec<ForwardEntry>> {
+		let output = self.port_forward(["forward", "--list"])?;
+		parse_forward_list(Arg::as_str(&output.stdout)?)
+	}
+
+	/// Remove the forward socket connection of the given `local` spec
+	pub fn forward_remove(&self, local: &str) -> Result<()> {
+		self.port_forward(["forward", "--remove", local]).map(|_| ())
+	}
+
+	/// Remove all the forward socket connections
+	pub fn forward_remove_all(&self) -> Result<()> {
+		self.port_forward(["forward", "--remove-all"]).map(|_| ())
+	}
+
+	/// Reverse socket connections from the device `remote` spec to the host `local` spec.
+	/// When `remote` is `tcp:0` the device picks a free port, which is returned.
+	pub fn reverse(&self, remote: &str, local: &str) -> Result<Option<u16>> {
+		let output = self.port_forward(["reverse", remote, local])?;
+		if remote == FORWARD_AUTO_LOCAL {
+			Ok(Some(parse_forward_port(Arg::as_str(&output.stdout)?)?))
+		} else {
+			Ok(None)
+		}
+	}
+
+	/// List all the reverse socket connections of the device
+	pub fn reverse_list(&self) -> Result<Vec<ForwardEntry>> {
+		let output = self.port_forward(["reverse", "--list"])?;
+		parse_forward_list(Arg::as_str(&output.stdout)?)
+	}
+
+	/// Remove the reverse socket connection of the given `remote` spec
+	pub fn reverse_remove(&self, remote: &str) -> Result<()> {
+		self.port_forward(["reverse", "--remove", remote]).map(|_| ())
+	}
+
+	fn port_forward<I, S>(&self, args: I) -> Result<Output>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
+	{
+		let args = args.into_iter().map(|arg| arg.as_ref().to_os_string()).collect::<Vec<_>>();
+		let output = self.run_command(|command| command.args(&args))?;
+		if output.error() { Err(output.into()) } else { Ok(output) }
+	}
+
+	pub fn pull<S, T>(&self, src: S, dst: T) -> Result<Output>
+	where
+		S: Arg,
+		T: Arg,
+	{
+		let (src, dst) = (src.as_str()?, dst.as_str()?);
+		self.run_command(|command| command.arg("pull").arg(src).arg(dst))
+	}
+
+	pub fn push<S, T>(&self, src: S, dst: T) -> Result<Output>
+	where
+		S: Arg,
+		T: Arg,
+	{
+		let (src, dst) = (src.as_str()?, dst.as_str()?);
+		self.run_command(|command| command.arg("push").arg(src).arg(dst))
+	}
+
+	/// Same as [`Client::pull`], returning the transfer statistics printed by adb
+	pub fn pull_stats<S, T>(&self, src: S, dst: T) -> Result<TransferStats>
+	where
+		S: Arg,
+		T: Arg,
+	{
+		let output = self.pull(src, dst)?;
+		Self::transfer_stats(output)
+	}
+
+	/// Same as [`Client::push`], returning the transfer statistics printed by adb
+	pub fn push_stats<S, T>(&self, src: S, dst: T) -> Result<TransferStats>
+	where
+		S: Arg,
+		T: Arg,
+	{
+		let output = self.push(src, dst)?;
+		Self::transfer_stats(output)
+	}
+
+	fn transfer_stats(output: Output) -> Result<TransferStats> {
+		if output.error() {
+			return Err(output.into());
+		}
+		parse_transfer_stats(Arg::as_str(&output.stdout)?).ok_or(Error::ParseInputError)
+	}
+
+	/// Same as [`Client::pull`], invoking `on_progress(transferred, total)` as the transfer progresses.
+	/// The progress is computed by polling the size of the local file, since adb only prints it to a terminal.
+	/// When the size of the remote file cannot be determined, or the source is a directory, `on_progress` is only
+	/// invoked with `(0, 100)` at the start and `(100, 100)` at the end of the transfer.
+	pub fn pull_with_progress<S, T, F>(&self, src: S, dst: T, on_progress: F) -> Result<()>
+	where
+		S: Arg,
+		T: Arg,
+		F: FnMut(u64, u64),
+	{
+		let src = src.as_str()?;
+		let dst = dst.as_str()?;
+		let total = self.remote_file_size(src).unwrap_or(0);
+
+		// pulling into a directory creates the file with the same name of the source
+		let target = Path::new(dst);
+		let target = match Path::new(src).file_name() {
+			Some(name) if target.is_dir() => target.join(name),
+			_ => target.to_path_buf(),
+		};
+
+		self.transfer_with_progress(
+			vec![
+				"pull", src, dst,
+			],
+			total,
+			TRANSFER_POLL_INTERVAL,
+			|| std::fs::metadata(&target).ok().map(|m| m.len()),
+			on_progress,
+		)
+	}
+
+	/// Same as [`Client::push`], invoking `on_progress(transferred, total)` as the transfer progresses.
+	/// The progress is computed by polling the size of the remote file, since adb only prints it to a terminal.
+	/// Each poll runs `stat` in a separate adb shell, so the remote file is only polled once per second.
+	/// When the size of the local file cannot be determined `on_progress` is only invoked with `(0, 100)`
+	/// at the start and `(100, 100)` at the end of the transfer.
+	pub fn push_with_progress<S, T, F>(&self, src: S, dst: T, on_progress: F) -> Result<()>
+	where
+		S: Arg,
+		T: Arg,
+		F: FnMut(u64, u64),
+	{
+		let src = src.as_str()?;
+		let dst = dst.as_str()?;
+		let total = std::fs::metadata(src).ok().filter(|m| m.is_file()).map(|m| m.len()).unwrap_or(0);
+
+		// pushing into a directory creates the file with the same name of the source
+		let target = match Path::new(src).file_name().and_then(|name| name.to_str()) {
+			Some(name) if dst.ends_with('/') || self.shell().is_dir(dst).unwrap_or(false) => {
+				format!("{}/{name}", dst.trim_end_matches('/'))
+			}
+			_ => dst.to_string(),
+		};
+
+		self.transfer_with_progress(
+			vec![
+				"push", src, dst,
+			],
+			total,
+			REMOTE_TRANSFER_POLL_INTERVAL,
+			|| self.remote_file_size(&target),
+			on_progress,
+		)
+	}
+
+	/// The size of the given remote path, only if it's a regular file
+	fn remote_file_size(&self, path: &str) -> Option<u64> {
+		let output = self
+			.shell()
+			.exec(
+				vec![
+					"stat", "-c", "'%F %s'", path,
+				],
+				None,
+				None,
+			)
+			.ok()
+			.filter(|output| output.success())?;
+		match Arg::as_str(&output.stdout).ok()?.trim().rsplit_once(' ')? {
+			("regular file", size) => size.parse().ok(),
+			_ => None,
+		}
+	}
+
+	/// Run a push/pull, polling the size of the destination file with `transferred` every `poll_interval` until
+	/// adb exits. stdout and stderr are drained on separate threads, so that a verbose adb can't block on a full pipe.
+	fn transfer_with_progress<P, F>(
+		&self,
+		args: Vec<&str>,
+		total: u64,
+		poll_interval: Duration,
+		transferred: P,
+		mut on_progress: F,
+	) -> Result<()>
+	where
+		P: Fn() -> Option<u64>,
+		F: FnMut(u64, u64),
+	{
+		let mut child = AdbCommandBuilder::from(self)
+			.args(args)
+			.stdout(Some(Stdio::piped()))
+			.stderr(Some(Stdio::piped()))
+			.build()
+			.command()
+			.spawn()?;
+		let stdout = drain(child.stdout.take());
+		let stderr = drain(child.stderr.take());
+
+		let (total, polling) = if total == 0 { (100, false) } else { (total, true) };
+		on_progress(0, total);
+		let mut last = 0;
+		let mut polled = Instant::now();
+		let status = loop {
+			if let Some(status) = child.try_wait()? {
+				break status;
+			}
+			if polling && polled.elapsed() >= poll_interval {
+				polled = Instant::now();
+				if let Some(size) = transferred().map(|size| size.min(total)) {
+					if size != last {
+						last = size;
+						on_progress(size, total);
+					}
+				}
+			}
+			sleep(TRANSFER_POLL_INTERVAL);
+		};
+
+		let output = Output {
+			status,
+			stdout: stdout.join().unwrap_or_default(),
+			stderr: stderr.join().unwrap_or_default(),
+		};
+		if !output.status.success() {
+			return Err(output.into());
+		}
+		if last != total {
+			on_progress(total, total);
+		}
+		Ok(())
+	}
+
+	/// Synchronize the content of `local_dir` into `remote_dir`, only pushing the files which changed.
+	/// When `delete_extra` is true the remote files not present in `local_dir` are removed.
+	pub fn sync<S, D>(&self, local_dir: S, remote_dir: D, delete_extra: bool) -> Result<SyncStats>
+	where
+		S: Arg,
+		D: Arg,
+	{
+		let local_dir = local_dir.as_str()?;
+		let remote_dir = remote_dir.as_str()?;
+
+		// trailing `/.` pushes the content of the directory instead of the directory itself
+		let local_content = format!("{}/.", local_dir.trim_end_matches('/'));
+		let output = self.run_command(|command| {
+			command.args([
+				"push",
+				"--sync",
+				local_content.as_str(),
+				remote_dir,
+			])
+		})?;
+		if output.error() {
+			return Err(output.into());
+		}
+		let stats = parse_sync_stats(Arg::as_str(&output.stdout)?);
+
+		if delete_extra {
+			let local_files = list_local_files(Path::new(local_dir))?;
+			let shell = self.shell();
+			let output = shell.exec(
+				vec![
+					"find",
+					remote_dir,
+					"-type",
+					"f",
+				],
+				None,
+				None,
+			)?;
+			for file in extra_remote_files(Arg::as_str(&output.stdout)?, remote_dir, &local_files) {
+				shell.rm(crate::shell::shell_quote(&file).as_str(), vec!["-f"])?;
+			}
+		}
+		Ok(stats)
+	}
+
+	pub fn install<T>(&self, path: T, install_options: Option<AdbInstallOptions>) -> Result<()>
+	where
+		T: Arg,
 	{
 		let mut args = vec!["install".into()];
 		match install_options {
@@ -419,7 +1558,17 @@ impl Client {
 			Some(options) => args.extend(options),
 		}
 		args.push(path.as_str()?.into());
-		super::shell::handle_result(self.adb.exec(self.addr, args, None, None, self.debug)?)
+		super::shell::handle_result(self.run_command(|command| command.args(&args))?)
+	}
+
+	/// Download the apk at `url` (http, https or file) to a local temporary file and install it
+	/// using [`PackageManager::deploy`]. The downloaded file is always removed afterwards.
+	#[cfg(feature = "http")]
+	pub fn install_from_url(&self, url: &str, options: Option<crate::types::InstallOptions>) -> Result<()> {
+		let local = std::env::temp_dir().join(format!("{TEMP_PREFIX}{}.apk", Uuid::new_v4()));
+		let result = download(url, &local).and_then(|_| self.shell().pm().deploy(&local, options));
+		let _ = std::fs::remove_file(&local);
+		result
 	}
 
 	pub fn uninstall(&self, package_name: &str, options: Option<UninstallOptions>) -> Result<()> {
@@ -429,7 +1578,7 @@ impl Client {
 			Some(options) => args.extend(options.into_iter()),
 		}
 		args.push(package_name.into());
-		super::shell::handle_result(self.adb.exec(self.addr, args, None, None, self.debug)?)
+		super::shell::handle_result(self.run_command(|command| command.args(&args))?)
 	}
 
 	/// return the client shell interface
@@ -437,28 +1586,76 @@ impl Client {
 		Shell { parent: self }
 	}
 
+	/// Run the given shell command lines in a single adb shell invocation, returning the output of each of them.
+	/// See [`Shell::exec_batch`]
+	pub fn shell_batch(&self, commands: &[&str]) -> Result<Vec<Output>> {
+		let commands: Vec<Vec<&str>> = commands.iter().map(|command| vec![*command]).collect();
+		self.shell().exec_batch(&commands)
+	}
+
 	/// Add debug tracing to connection
 	pub fn with_debug(mut self, debug: bool) -> Self {
 		self.debug = debug;
 		self
 	}
+
+	/// Retry the shell, transfer, install and query commands failing for a transient reason (device offline or not found)
+	/// up to `attempts` times, waiting `backoff` and reconnecting before each retry.
+	/// Commands which ran and exited with an error are never retried.
+	pub fn with_retry(mut self, attempts: u32, backoff: Duration) -> Self {
+		self.retry = Some(RetryPolicy { attempts, backoff });
+		self
+	}
+
+	/// Restart adbd as root before the methods requiring it ([`Client::get_mac_address`], [`Shell::list_settings`],
+	/// [`Shell::disable_verity`]...), which fail with [`Error::RootRequired`] when the elevation fails
+	pub fn with_auto_root(mut self) -> Self {
+		self.auto_root = true;
+		self
+	}
+
+	/// Use the given timeouts, e.g. longer ones for slow wireless connections
+	pub fn with_config(mut self, config: ClientConfig) -> Self {
+		self.config = config;
+		self
+	}
 }
 
 #[cfg(test)]
 mod test {
+	use std::collections::HashSet;
 	use std::fs::{File, remove_file};
-	use std::io::BufRead;
+	use std::ffi::OsString;
+	use std::hash::{DefaultHasher, Hash, Hasher};
+	use std::io::{BufRead, Cursor};
 	use std::net::SocketAddr;
-	use std::time::Duration;
+	use std::os::unix::fs::PermissionsExt;
+	use std::os::unix::process::ExitStatusExt;
+	use std::path::PathBuf;
+	use std::process::{ExitStatus, Output};
+	use std::str::FromStr;
+	use std::time::{Duration, Instant};
 
 	use chrono::Local;
 	use simple_cmd::prelude::OutputExt;
 
+	use crate::client::{
+		FEATURE_STAT_V2, FORWARD_AUTO_LOCAL, TEMP_DIR, TEMP_PREFIX, check_device_state, check_png, extra_remote_files,
+		is_transient_error, is_transient_output, logcat_options_since_boot, make_cleanup_temp_script, make_forward_args,
+		make_shell_tty_args, make_temp_path, make_wait_for_arg, make_wait_for_boot_script, normalize_png, parse_boot_time,
+		parse_connect_output, parse_device_state, parse_features, parse_forward_list, parse_forward_port, parse_logcat_epoch,
+		parse_sync_stats, parse_toybox_stat, parse_transfer_stats, parse_transport_id,
+	};
 	use crate::error::Error;
 	use crate::test::test::{
-		client_from, connect_client, connect_emulator, connect_tcp_ip_client, connection_from_tcpip, init_log, test_files_dir,
+		client_from, connect_client, connect_emulator, connect_tcp_ip_client, connection_from_tcpip, init_log, temp_dir,
+		test_files_dir,
 	};
-	use crate::types::{AdbInstallOptions, Client, ConnectionType, LogcatLevel, LogcatOptions, LogcatTag, Reconnect};
+	use crate::types::{
+		Adb, AdbInstallOptions, Client, ClientConfig, ConnectionType, DeviceState, ForwardEntry, LogcatLevel, LogcatOptions,
+		LogcatTag, Reconnect, RemoteStat, ScreenRecordOptions, SyncStats, TransferStats, WaitState, WaitTransport, Wakefulness,
+	};
+	use crate::utils::strip_terminal_sequences;
 
 	#[test]
 	fn test_new_client() {
@@ -481,6 +1678,28 @@ mod test {
 		let _ = client.connect(Some(Duration::from_secs(1))).expect("failed to connect");
 	}
 
+	#[test]
+	fn test_connect_root_remount() {
+		init_log();
+		let client = client_from(connection_from_tcpip());
+		client
+			.connect_root_remount(Some(Duration::from_secs(1)))
+			.expect("failed to connect, root and remount");
+		assert!(client.is_connected());
+		assert!(client.is_root().expect("failed to get root status"));
+
+		// emulator images allow adb root, only the user builds refuse it
+		let client = connect_emulator();
+		let user_build = client.shell().getprop("ro.build.type").expect("failed to get build type") == "user";
+		let result = client.connect_root_remount(None);
+		if user_build {
+			assert!(matches!(result, Err(Error::SetupError { step: "root", .. })), "unexpected result: {result:?}");
+		} else {
+			result.expect("failed to connect, root and remount the emulator");
+			assert!(client.is_root().expect("failed to get root status"));
+		}
+	}
+
 	#[test]
 	fn test_disconnect() {
 		init_log();
@@ -502,11 +1721,225 @@ mod test {
 		init_log();
 		let client = connect_client(connection_from_tcpip());
 		client
-			.wait_for_device(Some(Duration::from_secs(1)))
+			.wait_for_device(Some(Duration::from_secs(1)), false)
 			.expect("failed to wait for device");
 
 		let client = connect_emulator();
-		client.wait_for_device(None).expect("failed to wait for emulator");
+		client.wait_for_device(None, true).expect("failed to wait for emulator");
+	}
+
+	#[test]
+	fn test_is_transient() {
+		let output = |code: i32, stderr: &str| Output {
+			status: ExitStatus::from_raw(code << 8),
+			stdout: vec![],
+			stderr: stderr.as_bytes().to_vec(),
+		};
+		assert!(is_transient_output(&output(1, "error: device offline\n")));
+		assert!(is_transient_output(&output(1, "adb: error: no devices/emulators found\n")));
+		assert!(!is_transient_output(&output(1, "error: closed\n")));
+		assert!(!is_transient_output(&output(1, "curl: (56) Recv failure: Connection reset by peer\n")));
+		assert!(!is_transient_output(&output(1, "write error: Broken pipe\n")));
+		assert!(is_transient_output(&output(1, "adb: error: device '192.168.1.10:5555' not found\n")));
+		assert!(!is_transient_output(&output(1, "ls: /data/foo: No such file or directory\n")));
+		assert!(!is_transient_output(&output(0, "error: device offline\n")));
+
+		assert!(is_transient_error(&Error::DeviceOffline));
+		assert!(is_transient_error(&std::io::Error::from(std::io::ErrorKind::BrokenPipe).into()));
+		assert!(is_transient_error(&std::io::Error::from(std::io::ErrorKind::NotConnected).into()));
+		assert!(!is_transient_error(&std::io::Error::from(std::io::ErrorKind::NotFound).into()));
+		assert!(!is_transient_error(&std::io::Error::from(std::io::ErrorKind::ConnectionReset).into()));
+		assert!(!is_transient_error(&Error::ParseInputError));
+	}
+
+	#[test]
+	fn test_run_with_retry() {
+		let client = Client::new(
+			Adb::from(PathBuf::from("true")),
+			ConnectionType::from_str("127.0.0.1:5555").unwrap(),
+			false,
+		);
+
+		let mut calls = 0;
+		let result = client.run_with_retry(|| {
+			calls += 1;
+			Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe).into())
+		});
+		assert!(result.is_err());
+		assert_eq!(1, calls);
+
+		let client = client.with_retry(2, Duration::ZERO);
+		let mut calls = 0;
+		let result = client.run_with_retry(|| {
+			calls += 1;
+			Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe).into())
+		});
+		assert!(result.is_err());
+		assert_eq!(3, calls);
+
+		let mut calls = 0;
+		let result = client.run_with_retry(|| {
+			calls += 1;
+			Err(Error::ParseInputError)
+		});
+		assert!(result.is_err());
+		assert_eq!(1, calls);
+	}
+
+	#[test]
+	fn test_run_command_retry() {
+		let dir = temp_dir();
+		let log = dir.join("offline_adb.log");
+		let _ = remove_file(&log);
+		let adb = dir.join("offline_adb.sh");
+		std::fs::write(
+			&adb,
+			format!("#!/bin/sh\necho \"$@\" >> {}\necho 'error: device offline' >&2\nexit 1\n", log.display()),
+		)
+		.expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("127.0.0.1:5555").unwrap(), false)
+			.with_retry(2, Duration::ZERO);
+		assert!(client.features().is_err());
+		let calls = std::fs::read_to_string(&log).unwrap().lines().filter(|line| line.ends_with("features")).count();
+		assert_eq!(3, calls);
+	}
+
+	#[test]
+	fn test_pull_with_progress() {
+		let dir = temp_dir();
+		let dst = dir.join("pull_progress.bin");
+		let _ = remove_file(&dst);
+		// the fake adb writes the file in 3 chunks, then floods stderr to check it doesn't block on the pipe
+		let adb = dir.join("pull_progress_adb.sh");
+		std::fs::write(
+			&adb,
+			"#!/bin/sh\n\
+			case \"$*\" in\n\
+			*\" stat \"*/sdcard/dir*) echo 'directory 4096' ;;\n\
+			*\" stat \"*) echo 'regular file 3000' ;;\n\
+			*\" pull \"*) eval dst=\\${$#}\n\
+			for i in 1 2 3; do head -c 1000 /dev/zero >> \"$dst\"; sleep 0.5; done\n\
+			head -c 200000 /dev/zero >&2 ;;\n\
+			esac\n",
+		)
+		.expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("127.0.0.1:5555").unwrap(), false);
+		let mut progress = vec![];
+		client
+			.pull_with_progress("/sdcard/file.bin", dst.as_path(), |transferred, total| progress.push((transferred, total)))
+			.expect("failed to pull");
+
+		assert_eq!(Some(&(0, 3000)), progress.first());
+		assert_eq!(Some(&(3000, 3000)), progress.last());
+		assert!(progress.len() > 2, "no intermediate progress: {progress:?}");
+		assert!(progress.windows(2).all(|w| w[0].0 < w[1].0));
+
+		// the size of a directory is not the size of its content
+		let dst = dir.join("pull_progress_dir");
+		let _ = remove_file(&dst);
+		let mut progress = vec![];
+		client
+			.pull_with_progress("/sdcard/dir", dst.as_path(), |transferred, total| progress.push((transferred, total)))
+			.expect("failed to pull");
+		assert_eq!(
+			vec![
+				(0, 100),
+				(100, 100),
+			],
+			progress
+		);
+	}
+
+	#[cfg(feature = "http")]
+	#[test]
+	fn test_download() {
+		let temp_dir = crate::test::test::temp_dir();
+		let src = temp_dir.join("download_src.apk");
+		std::fs::write(&src, b"PK\x03\x04apk").unwrap();
+		let dest = temp_dir.join("download_dest.apk");
+
+		crate::client::download(format!("file://{}", src.display()).as_str(), &dest).expect("failed to download");
+		assert_eq!(std::fs::read(&src).unwrap(), std::fs::read(&dest).unwrap());
+
+		let missing = format!("file://{}", temp_dir.join("missing.apk").display());
+		let err = crate::client::download(missing.as_str(), &dest).expect_err("missing file should fail");
+		assert!(matches!(err, Error::DownloadError { url, .. } if url == missing));
+	}
+
+	#[cfg(feature = "http")]
+	#[test]
+	fn test_install_from_url() {
+		init_log();
+		let client = connect_emulator();
+		let url = format!("file://{}", test_files_dir().join("app-debug.apk").display());
+		client.install_from_url(url.as_str(), None).expect("failed to install from url");
+	}
+
+	#[test]
+	fn test_ensure_root() {
+		let client = Client::new(
+			Adb::from(PathBuf::from("true")),
+			ConnectionType::from_str("127.0.0.1:5555").unwrap(),
+			false,
+		);
+		assert!(!client.auto_root);
+		client.ensure_root().expect("auto root disabled should never fail");
+
+		// the fake adb never reports the root user
+		let client = client.with_auto_root();
+		assert!(matches!(client.ensure_root(), Err(Error::RootRequired)));
+	}
+
+	#[test]
+	fn test_client_config() {
+		let adb = temp_dir().join("slow_adb.sh");
+		std::fs::write(&adb, "#!/bin/sh\nexec sleep 5\n").expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("127.0.0.1:5555").unwrap(), false);
+		assert_eq!(ClientConfig::default(), client.config);
+		assert_eq!(Duration::from_millis(200), client.config.get_state_timeout);
+
+		let config = ClientConfig {
+			get_state_timeout: Duration::from_millis(100),
+			..ClientConfig::default()
+		};
+		let client = client.with_config(config);
+		assert_eq!(config, client.config);
+
+		let start = Instant::now();
+		assert!(client.get_state().is_err());
+		assert!(start.elapsed() < Duration::from_secs(2));
+	}
+
+	#[test]
+	fn test_make_wait_for_boot_script() {
+		let script = make_wait_for_boot_script(false);
+		assert!(script.contains("sys.boot_completed"));
+		assert!(!script.contains("keyevent"));
+		assert!(make_wait_for_boot_script(true).ends_with("; input keyevent 143"));
+	}
+
+	#[test]
+	fn test_make_wait_for_arg() {
+		assert_eq!("wait-for-any-device", make_wait_for_arg(WaitState::Device, WaitTransport::Any));
+		assert_eq!("wait-for-usb-recovery", make_wait_for_arg(WaitState::Recovery, WaitTransport::Usb));
+		assert_eq!("wait-for-local-bootloader", make_wait_for_arg(WaitState::Bootloader, WaitTransport::Local));
+		assert_eq!("wait-for-any-sideload", make_wait_for_arg(WaitState::Sideload, WaitTransport::Any));
+		assert_eq!("wait-for-any-disconnect", make_wait_for_arg(WaitState::Disconnect, WaitTransport::Any));
+	}
+
+	#[test]
+	fn test_wait_for() {
+		init_log();
+		let client = connect_emulator();
+		client
+			.wait_for(WaitState::Device, WaitTransport::Any, Some(Duration::from_secs(5)))
+			.expect("failed to wait for device");
 	}
 
 	#[test]
@@ -521,6 +1954,18 @@ mod test {
 		println!("awake status: {awake}");
 	}
 
+	#[test]
+	fn test_wake_and_sleep() {
+		init_log();
+		let client = connect_emulator();
+		client.sleep().expect("failed to put the device to sleep");
+		assert_eq!(Wakefulness::Asleep, client.get_wakefulness().unwrap());
+
+		client.wake().expect("failed to wake up the device");
+		assert_eq!(Wakefulness::Awake, client.get_wakefulness().unwrap());
+		assert!(client.is_awake().unwrap());
+	}
+
 	#[test]
 	fn test_is_root() {
 		init_log();
@@ -586,6 +2031,232 @@ mod test {
 		remove_file(output_path).unwrap();
 	}
 
+	#[test]
+	fn test_normalize_png() {
+		let mut img = image::RgbImage::new(4, 4);
+		img.put_pixel(1, 2, image::Rgb([255, 0, 0]));
+		let mut encoded = Cursor::new(Vec::new());
+		img.write_to(&mut encoded, image::ImageFormat::Png).unwrap();
+
+		// append an ancillary tEXt chunk right after IHDR
+		let source = encoded.into_inner();
+		let ihdr_end = 8 + 4 + 4 + 13 + 4;
+		let text_data = b"Creation Time\0now";
+		let mut text_chunk = (text_data.len() as u32).to_be_bytes().to_vec();
+		text_chunk.extend_from_slice(b"tEXt");
+		text_chunk.extend_from_slice(text_data);
+		text_chunk.extend_from_slice(&[0, 0, 0, 0]);
+
+		let mut with_text = source[..ihdr_end].to_vec();
+		with_text.extend_from_slice(&text_chunk);
+		with_text.extend_from_slice(&source[ihdr_end..]);
+
+		let normalized1 = normalize_png(&source).expect("failed to normalize png");
+		let normalized2 = normalize_png(&with_text).expect("failed to normalize png");
+		assert_eq!(normalized1, normalized2);
+		assert!(!normalized2.windows(4).any(|w| w == b"tEXt"));
+	}
+
+	#[test]
+	fn test_check_png() {
+		let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+		assert_eq!(png, check_png(png.clone()).unwrap());
+		assert!(check_png(b"/system/bin/sh: screencap: not found".to_vec()).is_err());
+		assert!(check_png(vec![]).is_err());
+	}
+
+	#[test]
+	fn test_screencap_bytes() {
+		init_log();
+		let client = connect_emulator();
+		let bytes = client.screencap_bytes().expect("failed to take screenshot");
+		let img = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).expect("invalid png");
+		assert!(img.width() > 0 && img.height() > 0);
+	}
+
+	#[test]
+	fn test_screenshot_png_normalized() {
+		init_log();
+		let client = connect_emulator();
+		let hash = |data: &Vec<u8>| {
+			let mut hasher = DefaultHasher::new();
+			data.hash(&mut hasher);
+			hasher.finish()
+		};
+
+		let first = client.screenshot_png_normalized().expect("failed to take screenshot");
+		let second = client.screenshot_png_normalized().expect("failed to take screenshot");
+		assert_eq!(hash(&first), hash(&second));
+	}
+
+	#[test]
+	fn test_parse_transfer_stats() {
+		let output = "[ 50%] /sdcard/file.bin\n/tmp/file.bin: 1 file pushed, 0 skipped. 12.5 MB/s (2097152 bytes in 0.160s)\n";
+		assert_eq!(
+			TransferStats {
+				bytes: 2097152,
+				duration: Duration::from_millis(160),
+				rate_bytes_per_sec: 2097152f64 / 0.16,
+			},
+			parse_transfer_stats(output).unwrap()
+		);
+
+		let output = "1953 KB/s (1000000 bytes in 0.500s)\n";
+		let stats = parse_transfer_stats(output).unwrap();
+		assert_eq!(1000000, stats.bytes);
+		assert_eq!(Duration::from_millis(500), stats.duration);
+		assert_eq!(2000000f64, stats.rate_bytes_per_sec);
+
+		let output = "/sdcard/a.txt: 1 file pulled, 0 skipped. 0.2 MB/s (42 bytes in 0.000s)\n";
+		let stats = parse_transfer_stats(output).unwrap();
+		assert_eq!(42, stats.bytes);
+		assert!(stats.duration.is_zero());
+		assert_eq!(0.2 * 1024f64 * 1024f64, stats.rate_bytes_per_sec);
+
+		assert!(parse_transfer_stats("adb: error: remote object '/sdcard/none' does not exist\n").is_none());
+	}
+
+	#[test]
+	fn test_push_pull_stats() {
+		init_log();
+		let client = connect_emulator();
+		let local = test_files_dir().join("app-debug.apk");
+		let stats = client.push_stats(local.as_path(), "/sdcard/").expect("failed to push");
+		println!("push: {stats:?}");
+		assert!(stats.bytes > 0);
+
+		let stats = client
+			.pull_stats("/sdcard/app-debug.apk", std::env::temp_dir().as_path())
+			.expect("failed to pull");
+		println!("pull: {stats:?}");
+		assert!(stats.bytes > 0);
+	}
+
+	#[test]
+	fn test_parse_sync_stats() {
+		let output = "/tmp/assets/./: 3 files pushed, 2 skipped. 12.3 MB/s (123456 bytes in 0.010s)\n";
+		assert_eq!(
+			SyncStats {
+				pushed: 3,
+				skipped: 2,
+				bytes: 123456,
+			},
+			parse_sync_stats(output)
+		);
+
+		let output = "/tmp/assets/./: 0 files pushed, 5 skipped.\n";
+		assert_eq!(
+			SyncStats {
+				pushed: 0,
+				skipped: 5,
+				bytes: 0,
+			},
+			parse_sync_stats(output)
+		);
+
+		let output = "/tmp/a/./: 1 file pushed, 0 skipped. 1.2 MB/s (10 bytes in 0.001s)\n\
+			/tmp/b/./: 2 files pushed, 1 skipped. 1.2 MB/s (20 bytes in 0.001s)\n";
+		assert_eq!(
+			SyncStats {
+				pushed: 3,
+				skipped: 1,
+				bytes: 30,
+			},
+			parse_sync_stats(output)
+		);
+		assert_eq!(SyncStats::default(), parse_sync_stats(""));
+	}
+
+	#[test]
+	fn test_extra_remote_files() {
+		let local_files = HashSet::from([
+			"a.txt".to_string(),
+			"sub/b.txt".to_string(),
+		]);
+		let output = "/sdcard/assets/a.txt\n/sdcard/assets/sub/b.txt\n/sdcard/assets/sub/c.txt\n/sdcard/assets/d.txt\n";
+		assert_eq!(
+			vec![
+				"/sdcard/assets/sub/c.txt",
+				"/sdcard/assets/d.txt",
+			],
+			extra_remote_files(output, "/sdcard/assets/", &local_files)
+		);
+	}
+
+	#[test]
+	fn test_sync() {
+		init_log();
+		let client = connect_emulator();
+		let local_dir = std::env::temp_dir().join("radb_sync");
+		let _ = std::fs::remove_dir_all(&local_dir);
+		std::fs::create_dir_all(local_dir.join("sub")).unwrap();
+		std::fs::write(local_dir.join("a.txt"), b"a").unwrap();
+		std::fs::write(local_dir.join("sub").join("b.txt"), b"b").unwrap();
+		let remote_dir = "/data/local/tmp/radb_sync";
+		client.shell().exec(vec!["mkdir", "-p", remote_dir], None, None).unwrap();
+		client.shell().exec(vec!["touch", "/data/local/tmp/radb_sync/extra.txt"], None, None).unwrap();
+
+		let stats = client.sync(local_dir.as_path(), remote_dir, true).expect("failed to sync");
+		assert_eq!(2, stats.pushed);
+		assert!(!client.shell().exists("/data/local/tmp/radb_sync/extra.txt").unwrap());
+
+		let stats = client.sync(local_dir.as_path(), remote_dir, false).expect("failed to sync");
+		assert_eq!(0, stats.pushed);
+		assert_eq!(2, stats.skipped);
+		std::fs::remove_dir_all(local_dir).unwrap();
+	}
+
+	#[test]
+	fn test_push_pull_with_progress() {
+		init_log();
+		let client = connect_emulator();
+		let src = test_files_dir().join("app-debug.apk");
+		let total = std::fs::metadata(&src).unwrap().len();
+
+		let mut progress = vec![];
+		client
+			.push_with_progress(src.as_path(), "/data/local/tmp/app-debug.apk", |transferred, total| {
+				progress.push((transferred, total))
+			})
+			.expect("failed to push file");
+		assert_eq!(Some(&(0, total)), progress.first());
+		assert_eq!(Some(&(total, total)), progress.last());
+
+		let dst = std::env::temp_dir().join("app-debug.apk");
+		let mut progress = vec![];
+		client
+			.pull_with_progress("/data/local/tmp/app-debug.apk", dst.as_path(), |transferred, total| {
+				progress.push((transferred, total))
+			})
+			.expect("failed to pull file");
+		assert_eq!(Some(&(total, total)), progress.last());
+		remove_file(dst).unwrap();
+	}
+
+	#[test]
+	fn test_screenshot_to() {
+		init_log();
+		let client = connect_emulator();
+		let mut buffer: Vec<u8> = vec![];
+		client.screenshot_to(&mut buffer).expect("failed to take screenshot");
+		assert!(buffer.starts_with(b"\x89PNG\r\n\x1a\n"));
+		assert!(image::load_from_memory(&buffer).is_ok());
+	}
+
+	#[test]
+	fn test_screen_record_to() {
+		init_log();
+		let client = connect_emulator();
+		let mut options = ScreenRecordOptions::new();
+		options.timelimit = Some(Duration::from_secs(2));
+
+		let mut buffer: Vec<u8> = vec![];
+		client
+			.screen_record_to(Some(options), &mut buffer, None)
+			.expect("failed to record screen");
+		assert!(!buffer.is_empty());
+	}
+
 	#[test]
 	pub fn test_copy_screencap() {
 		init_log();
@@ -625,6 +2296,151 @@ mod test {
 		println!("serial: {ip_addr}");
 	}
 
+	#[test]
+	fn test_parse_device_state() {
+		assert_eq!(DeviceState::Device, parse_device_state("device\n", "").unwrap());
+		assert_eq!(DeviceState::Bootloader, parse_device_state("bootloader\n", "").unwrap());
+		assert_eq!(
+			DeviceState::Unauthorized,
+			parse_device_state("", "error: device unauthorized.\nThis adb server's $ADB_VENDOR_KEYS is not set\n").unwrap()
+		);
+		assert_eq!(DeviceState::Offline, parse_device_state("", "error: device offline\n").unwrap());
+		assert!(parse_device_state("", "error: device '192.168.1.42:5555' not found\n").is_err());
+	}
+
+	#[test]
+	fn test_parse_connect_output() {
+		assert!(parse_connect_output("connected to 192.168.1.42:5555\n", "", true).is_ok());
+		assert!(matches!(
+			parse_connect_output("already connected to 192.168.1.42:5555\n", "", true),
+			Err(Error::AlreadyConnected)
+		));
+		assert!(matches!(
+			parse_connect_output(
+				"failed to connect to '192.168.1.42:5555': Connection refused\n",
+				"",
+				true
+			),
+			Err(Error::ConnectionRefused)
+		));
+		assert!(matches!(
+			parse_connect_output(
+				"",
+				"cannot connect to 192.168.1.42:5555: No connection could be made because the target machine actively refused it. (10061)\n",
+				false
+			),
+			Err(Error::ConnectionRefused)
+		));
+		assert!(matches!(
+			parse_connect_output("failed to connect to '192.168.1.42:5555': No route to host\n", "", true),
+			Err(Error::IoError(_))
+		));
+		assert!(matches!(parse_connect_output("", "", false), Err(Error::IoError(_))));
+	}
+
+	#[test]
+	fn test_check_device_state() {
+		assert_eq!(DeviceState::Device, check_device_state(DeviceState::Device).unwrap());
+		assert_eq!(DeviceState::Recovery, check_device_state(DeviceState::Recovery).unwrap());
+		assert!(matches!(check_device_state(DeviceState::Offline), Err(Error::DeviceOffline)));
+		assert!(matches!(
+			check_device_state(DeviceState::Unauthorized),
+			Err(Error::DeviceUnauthorized)
+		));
+	}
+
+	#[test]
+	fn test_extract_crashes() {
+		init_log();
+		let client = connect_emulator();
+		let options = LogcatOptions {
+			dump: true,
+			timeout: Some(Duration::from_secs(5)),
+			..Default::default()
+		};
+		let crashes = client.extract_crashes(options).expect("failed to extract crashes");
+		for crash in crashes {
+			println!("{:?} {:?} {:?}", crash.kind, crash.package, crash.timestamp);
+		}
+	}
+
+	#[test]
+	fn test_logcat_merged_sorted() {
+		init_log();
+		let client = connect_emulator();
+		let options = LogcatOptions {
+			dump: true,
+			buffers: vec![
+				"main".to_string(),
+				"system".to_string(),
+			],
+			timeout: Some(Duration::from_secs(10)),
+			..Default::default()
+		};
+		let entries = client.logcat_merged_sorted(options, None).expect("failed to read logcat");
+		assert!(!entries.is_empty());
+		assert!(entries.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+	}
+
+	#[test]
+	fn test_parse_features() {
+		let features = parse_features("shell_v2\ncmd\nstat_v2\nls_v2\n");
+		assert_eq!(vec!["shell_v2", "cmd", "stat_v2", "ls_v2"], features);
+		assert_eq!(vec!["shell_v2", "cmd"], parse_features("shell_v2,cmd\n"));
+		assert!(parse_features("").is_empty());
+	}
+
+	#[test]
+	fn test_parse_toybox_stat() {
+		assert_eq!(
+			Some(RemoteStat {
+				mode: 0o100660,
+				uid: 0,
+				gid: 1015,
+				size: 5_000_000_000,
+				atime: 1_700_000_000,
+				mtime: 1_700_000_001,
+				ctime: 1_700_000_002,
+			}),
+			parse_toybox_stat("81b0 0 1015 5000000000 1700000000 1700000001 1700000002\n")
+		);
+		assert_eq!(None, parse_toybox_stat("stat: '/sdcard/missing': No such file or directory\n"));
+	}
+
+	#[test]
+	fn test_remote_stat() {
+		init_log();
+		let client = connect_emulator();
+		if !client.has_feature(FEATURE_STAT_V2).expect("failed to get features") {
+			println!("stat_v2 not supported");
+			return;
+		}
+
+		let stat = client.remote_stat("/system/build.prop").expect("failed to stat file");
+		assert!(stat.size > 0);
+		assert_eq!(0o100000, stat.mode & 0o170000);
+		assert!(client.remote_stat("/system/missing_file").is_err());
+	}
+
+	#[test]
+	fn test_recover_if_offline() {
+		init_log();
+		let client = connect_emulator();
+		client
+			.recover_if_offline(Duration::from_secs(5))
+			.expect("failed to recover device");
+		assert_eq!(DeviceState::Device, client.get_state().expect("failed to get state"));
+	}
+
+	#[test]
+	fn test_tcpip() {
+		init_log();
+		let client = connect_emulator();
+		client.tcpip(5555).expect("failed to restart in tcpip mode");
+		assert!(client.is_connected());
+		client.usb().expect("failed to restart in usb mode");
+	}
+
 	#[test]
 	fn test_reconnect() {
 		init_log();
@@ -716,19 +2532,15 @@ mod test {
 		let since = Some(Local::now() - chrono::Duration::seconds(600));
 
 		let options = LogcatOptions {
-			expr: None,
-			dump: false,
-			filename: None,
 			tags: Some(vec![
 				LogcatTag {
 					name: "tl.RestClient".to_string(),
 					level: LogcatLevel::Debug,
 				},
 			]),
-			format: None,
 			since,
-			pid: None,
 			timeout,
+			..Default::default()
 		};
 
 		let output = client.logcat(options, None);
@@ -758,6 +2570,329 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn test_logcat_options_pids() {
+		let mut options = LogcatOptions {
+			dump: true,
+			pid: Some(100),
+			pids: vec![
+				200, 300,
+			],
+			..Default::default()
+		};
+		let args: Vec<OsString> = options.clone().into_iter().collect();
+		assert_eq!(
+			vec![
+				"-d", "--pid", "100", "--pid", "200", "--pid", "300",
+			],
+			args
+		);
+
+		options.pid = None;
+		options.pids.clear();
+		let args: Vec<OsString> = options.into_iter().collect();
+		assert_eq!(vec!["-d"], args);
+	}
+
+	#[test]
+	fn test_logcat_pids() {
+		init_log();
+		let client = connect_emulator();
+		let pids = client
+			.shell()
+			.pid_of_package("com.android.systemui")
+			.expect("failed to get pids");
+		assert!(!pids.is_empty());
+
+		let options = LogcatOptions {
+			dump: true,
+			pids,
+			..Default::default()
+		};
+		let output = client.logcat(options, None).expect("failed to read logcat");
+		assert!(output.success());
+	}
+
+	#[test]
+	fn test_logcat_stream() {
+		init_log();
+		let client = connect_emulator();
+		let options = LogcatOptions {
+			timeout: Some(Duration::from_secs(10)),
+			..Default::default()
+		};
+
+		let mut lines = vec![];
+		client
+			.logcat_stream(options, None, |line| {
+				lines.push(line);
+				lines.len() < 10
+			})
+			.expect("failed to stream logcat");
+		assert_eq!(10, lines.len());
+		println!("lines: {lines:#?}");
+	}
+
+	#[test]
+	fn test_logcat_stream_exit_status() {
+		let options = || LogcatOptions {
+			timeout: Some(Duration::from_millis(500)),
+			..Default::default()
+		};
+
+		// adb exits at once without printing anything
+		let client = Client::new(Adb::from(PathBuf::from("false")), ConnectionType::USB, false);
+		assert!(client.logcat_stream(options(), None, |_| true).is_err());
+
+		// dropping the cancel sender must not disable the timeout
+		let adb = temp_dir().join("silent_logcat_adb.sh");
+		std::fs::write(&adb, "#!/bin/sh\nexec sleep 5\n").expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+		let client = Client::new(Adb::from(adb), ConnectionType::USB, false);
+		let (sender, receiver) = crossbeam_channel::bounded::<()>(1);
+		drop(sender);
+		let start = Instant::now();
+		client.logcat_stream(options(), Some(receiver), |_| true).expect("killed by the timeout");
+		assert!(start.elapsed() < Duration::from_secs(5));
+	}
+
+	#[test]
+	fn test_logcat_options_since_boot() {
+		let stat = "cpu  1 2 3 4\nintr 1234\nctxt 5678\nbtime 1700000000\nprocesses 42\n";
+		let boot_time = parse_boot_time(stat).expect("failed to parse btime");
+		assert_eq!(1700000000, boot_time.timestamp());
+
+		let options = LogcatOptions {
+			dump: true,
+			..Default::default()
+		};
+
+		let args = logcat_options_since_boot(options.clone(), boot_time, None)
+			.into_iter()
+			.collect::<Vec<_>>();
+		let index = args.iter().position(|arg| arg == "-T").expect("missing -T argument");
+		assert_eq!(boot_time.format("%m-%d %H:%M:%S.%3f").to_string(), args[index + 1].to_str().unwrap());
+
+		// the oldest entry of the buffer comes after the boot time
+		let earliest = parse_logcat_epoch("  1700000600.250  1234  1234 I tag: message").expect("failed to parse line");
+		assert_eq!(1700000600250, earliest.timestamp_millis());
+
+		let args = logcat_options_since_boot(options.clone(), boot_time, Some(earliest))
+			.into_iter()
+			.collect::<Vec<_>>();
+		let index = args.iter().position(|arg| arg == "-T").expect("missing -T argument");
+		assert_eq!(earliest.format("%m-%d %H:%M:%S.%3f").to_string(), args[index + 1].to_str().unwrap());
+
+		assert!(parse_logcat_epoch("--------- beginning of main").is_none());
+	}
+
+	#[test]
+	fn test_logcat_since_boot() {
+		init_log();
+		let client = connect_emulator();
+		let boot_time = client.boot_time().expect("failed to get boot time");
+		println!("boot time: {boot_time}");
+
+		let options = LogcatOptions {
+			dump: true,
+			timeout: Some(Duration::from_secs(5)),
+			..Default::default()
+		};
+
+		let output = client.logcat_since_boot(options, None).expect("failed to read logcat");
+		assert!(!output.stdout.is_empty());
+	}
+
+	#[test]
+	fn test_parse_transport_id() {
+		let output = "List of devices attached\n\
+			192.168.1.10:5555      device product:IP2300 model:IP2300 device:IP2300 transport_id:5\n\
+			emulator-5554          device product:sdk_gphone64_arm64 model:sdk_gphone64_arm64 device:emu64a transport_id:2\n\
+			0A041FDD4003F7         device usb:1-1 product:oriole model:Pixel_6 device:oriole transport_id:7\n";
+
+		let tcp = ConnectionType::from_str("192.168.1.10:5555").unwrap();
+		assert_eq!(Some(5), parse_transport_id(output, &tcp));
+		let mapped = ConnectionType::from_str("[::ffff:192.168.1.10]:5555").unwrap();
+		assert_eq!(Some(5), parse_transport_id(output, &mapped));
+		let missing = ConnectionType::from_str("192.168.1.11:5555").unwrap();
+		assert_eq!(None, parse_transport_id(output, &missing));
+
+		assert_eq!(Some(7), parse_transport_id(output, &ConnectionType::USB));
+		assert_eq!(Some(3), parse_transport_id(output, &ConnectionType::Transport(3)));
+		assert_eq!(None, parse_transport_id("List of devices attached\n", &ConnectionType::USB));
+
+		let two_usb = format!("{output}0B0000000000001 device usb:1-2 product:a model:b device:c transport_id:8\n");
+		assert_eq!(None, parse_transport_id(&two_usb, &ConnectionType::USB));
+
+		let reconnected = "192.168.1.10:5555      device product:IP2300 model:IP2300 device:IP2300 transport_id:300\n";
+		assert_eq!(Some(300), parse_transport_id(reconnected, &tcp));
+	}
+
+	#[test]
+	fn test_pin_transport_out_of_range() {
+		let adb = temp_dir().join("reconnected_adb.sh");
+		std::fs::write(
+			&adb,
+			"#!/bin/sh\necho '192.168.1.10:5555 device product:a model:b device:c transport_id:300'\n",
+		)
+		.expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("192.168.1.10:5555").unwrap(), false);
+
+		assert_eq!(300, client.resolve_transport_id().expect("failed to resolve transport id"));
+		assert!(matches!(client.pin_transport(), Err(Error::TransportIdOutOfRange(300))));
+	}
+
+	#[test]
+	fn test_pin_transport() {
+		init_log();
+		let client = connect_emulator();
+		let pinned = client.pin_transport().expect("failed to pin transport");
+		assert!(matches!(pinned.addr, ConnectionType::Transport(_)));
+		assert_eq!(
+			client.shell().getprop("ro.serialno").unwrap(),
+			pinned.shell().getprop("ro.serialno").unwrap()
+		);
+	}
+
+	#[test]
+	fn test_make_temp_path() {
+		let path = make_temp_path(".png");
+		assert!(path.starts_with(&format!("{TEMP_DIR}/{TEMP_PREFIX}")));
+		assert!(path.ends_with(".png"));
+		assert_ne!(path, make_temp_path(".png"));
+
+		let script = make_cleanup_temp_script();
+		assert!(script.contains(&format!("{TEMP_DIR}/{TEMP_PREFIX}*")));
+	}
+
+	#[test]
+	fn test_cleanup_temp() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let files = [
+			make_temp_path(".txt"),
+			make_temp_path(""),
+		];
+		for file in files.iter() {
+			shell.exec(vec!["touch", file.as_str()], None, None).expect("failed to create file");
+		}
+		shell.exec(vec!["touch", "/data/local/tmp/radb_unmarked.txt"], None, None).unwrap();
+
+		let removed = client.cleanup_temp().expect("failed to cleanup");
+		assert!(removed >= 2);
+		for file in files.iter() {
+			assert!(!shell.exists(file.as_str()).unwrap());
+		}
+		assert!(shell.exists("/data/local/tmp/radb_unmarked.txt").unwrap());
+		shell.rm("/data/local/tmp/radb_unmarked.txt", vec!["-f"]).unwrap();
+		assert_eq!(0, client.cleanup_temp().expect("failed to cleanup"));
+	}
+
+	#[test]
+	fn test_make_shell_tty_args() {
+		let args = make_shell_tty_args("top -n 1");
+		assert_eq!("shell", args[0]);
+		assert!(args.contains(&"-t"));
+		assert_eq!(Some(&"top -n 1"), args.last());
+
+		assert_eq!(
+			"Tasks: 1 total\nMem: 2G\n",
+			strip_terminal_sequences("\x1b[H\x1b[2J\x1b[1mTasks:\x1b[0m 1 total\r\n\x1b]0;title\x07Mem: 2G\r\n")
+		);
+	}
+
+	#[test]
+	fn test_shell_tty() {
+		init_log();
+		let client = connect_emulator();
+		match client.shell_tty("tty") {
+			Ok(output) => {
+				let stdout = String::from_utf8_lossy(&output.stdout);
+				assert!(strip_terminal_sequences(&stdout).starts_with("/dev/pts/"));
+			}
+			Err(err) => assert!(matches!(err, Error::UnsupportedOnThisApi)),
+		}
+	}
+
+	#[test]
+	fn test_make_forward_args() {
+		assert_eq!(
+			vec![
+				"forward", "tcp:0", "tcp:8080",
+			],
+			make_forward_args(FORWARD_AUTO_LOCAL, "tcp:8080")
+		);
+		assert_eq!(38471, parse_forward_port("38471\n").unwrap());
+		assert!(parse_forward_port("error: cannot bind listener").is_err());
+		assert!(parse_forward_port("").is_err());
+	}
+
+	#[test]
+	fn test_forward_auto() {
+		init_log();
+		let client = connect_emulator();
+		let port = client.forward_auto("tcp:8080").expect("failed to forward");
+		println!("allocated port: {port}");
+		assert!(port > 0);
+	}
+
+	#[test]
+	fn test_parse_forward_list() {
+		let output = "emulator-5554 tcp:8080 tcp:8080\n192.168.1.42:5555 tcp:38471 localabstract:chrome_devtools_remote\n\n";
+		let entries = parse_forward_list(output).expect("failed to parse forward list");
+		assert_eq!(
+			vec![
+				ForwardEntry {
+					serial: "emulator-5554".to_string(),
+					local: "tcp:8080".to_string(),
+					remote: "tcp:8080".to_string(),
+				},
+				ForwardEntry {
+					serial: "192.168.1.42:5555".to_string(),
+					local: "tcp:38471".to_string(),
+					remote: "localabstract:chrome_devtools_remote".to_string(),
+				},
+			],
+			entries
+		);
+		assert!(parse_forward_list("").unwrap().is_empty());
+		assert!(parse_forward_list("emulator-5554 tcp:8080").is_err());
+	}
+
+	#[test]
+	fn test_forward() {
+		init_log();
+		let client = connect_emulator();
+		client.forward_remove_all().expect("failed to remove forwards");
+		assert_eq!(None, client.forward("tcp:18080", "tcp:8080").expect("failed to forward"));
+		let port = client.forward("tcp:0", "tcp:8081").expect("failed to forward");
+		assert!(port.is_some());
+
+		let list = client.forward_list().expect("failed to list forwards");
+		println!("forward list: {list:?}");
+		assert!(list.iter().any(|entry| entry.local == "tcp:18080" && entry.remote == "tcp:8080"));
+
+		client.forward_remove("tcp:18080").expect("failed to remove forward");
+		let list = client.forward_list().expect("failed to list forwards");
+		assert!(!list.iter().any(|entry| entry.local == "tcp:18080"));
+		client.forward_remove_all().expect("failed to remove forwards");
+	}
+
+	#[test]
+	fn test_reverse() {
+		init_log();
+		let client = connect_emulator();
+		assert_eq!(None, client.reverse("tcp:18080", "tcp:8080").expect("failed to reverse"));
+
+		let list = client.reverse_list().expect("failed to list reverse");
+		println!("reverse list: {list:?}");
+		assert!(list.iter().any(|entry| entry.local == "tcp:18080" && entry.remote == "tcp:8080"));
+
+		client.reverse_remove("tcp:18080").expect("failed to remove reverse");
+	}
+
 	#[test]
 	fn test_install() {
 		init_log();
@@ -801,4 +2936,18 @@ mod test {
 				.expect("failed to check if package is installed")
 		);
 	}
+
+	#[test]
+	fn test_shell_batch() {
+		init_log();
+		let client = connect_emulator();
+		let outputs = client
+			.shell_batch(&["getprop ro.build.version.sdk", "ls /missing_dir", "echo hello world"])
+			.expect("failed to exec shell batch");
+		assert_eq!(3, outputs.len());
+		assert!(outputs[0].success());
+		assert!(!outputs[0].stdout.is_empty());
+		assert!(outputs[1].error());
+		assert_eq!(b"hello world\n".to_vec(), outputs[2].stdout);
+	}
 }
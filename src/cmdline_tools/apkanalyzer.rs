@@ -21,6 +21,21 @@ impl Display for ApkSummary {
 	}
 }
 
+/// Parse a size in bytes, as printed by `apk file-size` and `apk download-size`
+fn parse_size(output: &str) -> anyhow::Result<u64> {
+	output.trim().parse::<u64>().map_err(|e| e.into())
+}
+
+/// Returns the non empty lines of the output, trimmed
+fn parse_lines(output: &str) -> Vec<String> {
+	output
+		.lines()
+		.map(|line| line.trim())
+		.filter(|line| !line.is_empty())
+		.map(|line| line.to_string())
+		.collect()
+}
+
 impl ApkAnalyzer {
 	pub fn new() -> anyhow::Result<Self> {
 		crate::utils::apk_analyzer().map(|path| ApkAnalyzer { path, debug: false })
@@ -95,6 +110,11 @@ impl ApkAnalyzer {
 
 	/// Returns the manifest of the given APK file
 	pub fn manifest_code<P: AsRef<Path>>(&self, apk_path: P) -> anyhow::Result<String> {
+		self.manifest_print(apk_path)
+	}
+
+	/// Returns the manifest of the given APK file, as printed by `manifest print`
+	pub fn manifest_print<P: AsRef<Path>>(&self, apk_path: P) -> anyhow::Result<String> {
 		let output = simple_cmd::Cmd::builder(&self.path)
 			.arg("manifest")
 			.arg("print")
@@ -105,6 +125,30 @@ impl ApkAnalyzer {
 		output.stdout.as_str().map(|s| s.trim().to_string()).map_err(|e| e.into())
 	}
 
+	/// Returns the raw file size of the given APK file, in bytes
+	pub fn file_size<P: AsRef<Path>>(&self, apk_path: P) -> anyhow::Result<u64> {
+		let output = simple_cmd::Cmd::builder(&self.path)
+			.arg("apk")
+			.arg("file-size")
+			.arg(apk_path.as_ref())
+			.with_debug(self.debug)
+			.build()
+			.output()?;
+		parse_size(output.stdout.as_str()?)
+	}
+
+	/// Returns the estimated download size of the given APK file, in bytes
+	pub fn download_size<P: AsRef<Path>>(&self, apk_path: P) -> anyhow::Result<u64> {
+		let output = simple_cmd::Cmd::builder(&self.path)
+			.arg("apk")
+			.arg("download-size")
+			.arg(apk_path.as_ref())
+			.with_debug(self.debug)
+			.build()
+			.output()?;
+		parse_size(output.stdout.as_str()?)
+	}
+
 	/// Returns if the given APK file is debuggable
 	pub fn debuggable<P: AsRef<Path>>(&self, apk_path: P) -> anyhow::Result<bool> {
 		let output = simple_cmd::Cmd::builder(&self.path)
@@ -126,13 +170,7 @@ impl ApkAnalyzer {
 			.with_debug(self.debug)
 			.build()
 			.output()?;
-		let result = output
-			.stdout
-			.lines()
-			.into_iter()
-			.map(|l| l.map_err(|e| e.into()))
-			.collect::<Result<Vec<_>, anyhow::Error>>()?;
-		Ok(result)
+		Ok(parse_lines(output.stdout.as_str()?))
 	}
 
 	/// Returns the files list of the given APK file
@@ -278,6 +316,22 @@ pub(crate) mod test {
 
 	static APK_PATH: &str = "/Users/alessandro/Documents/git/swisscom/aot-lib/standalone/build/outputs/apk/ip2300/release/standalone-ip2300-release.apk";
 
+	#[test]
+	fn test_parse_output() {
+		assert_eq!(3471212, parse_size("3471212\n").unwrap());
+		assert!(parse_size("3.3 MB").is_err());
+		assert!(parse_size("").is_err());
+
+		assert_eq!(
+			vec![
+				"android.permission.INTERNET".to_string(),
+				"android.permission.WAKE_LOCK".to_string(),
+			],
+			parse_lines("android.permission.INTERNET\n\nandroid.permission.WAKE_LOCK\n")
+		);
+		assert!(parse_lines("").is_empty());
+	}
+
 	#[test]
 	fn test_new() {
 		init_log();
@@ -326,7 +380,7 @@ pub(crate) mod test {
 		init_log();
 		let apk_path = PathBuf::from(APK_PATH);
 		let apkanalyzer = ApkAnalyzer::new().expect("Failed to create ApkAnalyzer");
-		let result = apkanalyzer.manifest_code(&apk_path).expect("Failed to get manifest");
+		let result = apkanalyzer.manifest_print(&apk_path).expect("Failed to get manifest");
 		assert!(result.len() > 0);
 		trace!("manifest: {}", result);
 	}
@@ -375,6 +429,18 @@ pub(crate) mod test {
 		trace!("target-sdk: {}", result);
 	}
 
+	#[test]
+	fn test_apk_sizes() {
+		init_log();
+		let apk_path = PathBuf::from(APK_PATH);
+		let apkanalyzer = ApkAnalyzer::new().expect("Failed to create ApkAnalyzer");
+		let file_size = apkanalyzer.file_size(&apk_path).expect("Failed to get file size");
+		let download_size = apkanalyzer.download_size(&apk_path).expect("Failed to get download size");
+		assert!(file_size > 0);
+		assert!(download_size > 0);
+		trace!("file-size: {}, download-size: {}", file_size, download_size);
+	}
+
 	#[test]
 	fn test_manifest_debuggable() {
 		init_log();
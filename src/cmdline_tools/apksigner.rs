@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use rustix::path::Arg;
+use simple_cmd::prelude::OutputExt;
+
+use crate::cmdline_tools::{ApkSigner, CertInfo};
+
+lazy_static! {
+	static ref RE_SCHEME: Regex = Regex::new(r"^Verified using v(\d+) scheme .*: true$").unwrap();
+	static ref RE_SIGNER: Regex = Regex::new(r"^Signer #(\d+) certificate (DN|SHA-256 digest|SHA-1 digest): (.*)$").unwrap();
+}
+
+/// Parse the output of `apksigner verify --print-certs --verbose`
+pub(crate) fn parse_signer_certs(output: &str) -> Vec<CertInfo> {
+	let mut schemes = vec![];
+	let mut signers: BTreeMap<u32, CertInfo> = BTreeMap::new();
+
+	for line in output.lines().map(|line| line.trim()) {
+		if let Some(captures) = RE_SCHEME.captures(line) {
+			if let Ok(version) = captures[1].parse::<u8>() {
+				schemes.push(version);
+			}
+		} else if let Some(captures) = RE_SIGNER.captures(line) {
+			let Ok(index) = captures[1].parse::<u32>() else {
+				continue;
+			};
+			let signer = signers.entry(index).or_default();
+			let value = captures[3].trim().to_string();
+			match &captures[2] {
+				"DN" => signer.subject = value,
+				"SHA-256 digest" => signer.sha256 = value,
+				_ => signer.sha1 = Some(value),
+			}
+		}
+	}
+
+	signers
+		.into_values()
+		.map(|signer| CertInfo {
+			schemes: schemes.clone(),
+			..signer
+		})
+		.collect()
+}
+
+impl ApkSigner {
+	pub fn new() -> anyhow::Result<Self> {
+		crate::utils::apk_signer().map(|path| ApkSigner { path, debug: false })
+	}
+
+	pub fn from<P: AsRef<Path>>(path: P, debug: bool) -> Self {
+		ApkSigner {
+			path: path.as_ref().to_path_buf(),
+			debug,
+		}
+	}
+
+	/// Verify the signature of the given APK file, returning its signer certificates
+	pub fn signer_certs<P: AsRef<Path>>(&self, apk_path: P) -> anyhow::Result<Vec<CertInfo>> {
+		let output = simple_cmd::Cmd::builder(&self.path)
+			.arg("verify")
+			.arg("--print-certs")
+			.arg("--verbose")
+			.arg(apk_path.as_ref())
+			.with_debug(self.debug)
+			.build()
+			.output()?;
+		if output.error() {
+			return Err(anyhow::Error::msg(output.stderr.as_str()?.trim().to_string()));
+		}
+		Ok(parse_signer_certs(output.stdout.as_str()?))
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+	use super::*;
+
+	static VERIFY_OUTPUT: &str = r"Verifies
+Verified using v1 scheme (JAR signing): true
+Verified using v2 scheme (APK Signature Scheme v2): true
+Verified using v3 scheme (APK Signature Scheme v3): true
+Verified using v3.1 scheme (APK Signature Scheme v3.1): false
+Verified using v4 scheme (APK Signature Scheme v4): false
+Verified for SourceStamp: false
+Number of signers: 1
+Signer #1 certificate DN: C=US, O=Android, CN=Android Debug
+Signer #1 certificate SHA-256 digest: 8c2d5a0b1f6f1e0c4bd9a3b04e8b2f8e5d14d6c0c9a6a3e2b7c8d9e0f1a2b3c4
+Signer #1 certificate SHA-1 digest: 5e8f16062ea3cd2c4a0d547876baa6f38cabf625
+Signer #1 certificate MD5 digest: 9b7a3e6f2a1c0d5e4f3a2b1c0d9e8f7a
+Signer #1 key algorithm: RSA
+Signer #1 key size (bits): 2048
+";
+
+	#[test]
+	fn test_parse_signer_certs() {
+		let certs = parse_signer_certs(VERIFY_OUTPUT);
+		assert_eq!(
+			vec![CertInfo {
+				subject: "C=US, O=Android, CN=Android Debug".to_string(),
+				sha256: "8c2d5a0b1f6f1e0c4bd9a3b04e8b2f8e5d14d6c0c9a6a3e2b7c8d9e0f1a2b3c4".to_string(),
+				sha1: Some("5e8f16062ea3cd2c4a0d547876baa6f38cabf625".to_string()),
+				schemes: vec![1, 2, 3],
+			}],
+			certs
+		);
+
+		assert!(parse_signer_certs("").is_empty());
+		assert!(parse_signer_certs("DOES NOT VERIFY\nERROR: Missing META-INF/MANIFEST.MF").is_empty());
+	}
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 mod apkanalyzer;
+mod apksigner;
 
 /// See <a href='https://developer.android.com/tools/apkanalyzer'>Android Apk Analyzer</a>
 /// for more information.
@@ -16,3 +17,22 @@ pub struct ApkSummary {
 	pub version_code: String,
 	pub version_name: String,
 }
+
+/// See <a href='https://developer.android.com/tools/apksigner'>Android Apk Signer</a>
+/// for more information.
+#[derive(Debug, Clone)]
+pub struct ApkSigner {
+	pub(crate) path: PathBuf,
+	pub(crate) debug: bool,
+}
+
+/// A signer certificate, as printed by `apksigner verify --print-certs`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertInfo {
+	/// the certificate distinguished name, e.g. `CN=Android Debug, O=Android, C=US`
+	pub subject: String,
+	pub sha256: String,
+	pub sha1: Option<String>,
+	/// the signature scheme versions verified for the apk, e.g. [1, 2, 3]
+	pub schemes: Vec<u8>,
+}
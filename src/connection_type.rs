@@ -1,12 +1,13 @@
 use std::ffi::OsString;
 use std::fmt::{Debug, Display, Formatter};
-use std::net::{AddrParseError, SocketAddr};
+use std::net::{AddrParseError, IpAddr, Ipv4Addr, SocketAddr};
 use std::str::FromStr;
 use std::vec::IntoIter;
 
+use crate::adb::connected_devices;
 use crate::error::Error;
 use crate::traits::AsArgs;
-use crate::types::ConnectionType;
+use crate::types::{Adb, AdbDevice, ConnectionType};
 
 #[allow(dead_code)]
 impl ConnectionType {
@@ -31,6 +32,70 @@ impl ConnectionType {
 	pub fn try_from_ip(value: &str) -> crate::result::Result<ConnectionType> {
 		Ok(ConnectionType::TcpIp(value.parse()?))
 	}
+
+	/// Returns the connection of the only device connected to `adb`.
+	/// Fails with [`Error::NoDevices`] or [`Error::MultipleDevices`] when there isn't exactly one online device.
+	///
+	/// # Examples
+	/// ```rust
+	/// use radb_client::types::{Adb, ConnectionType};
+	///
+	/// let adb = Adb::new().expect("failed to find adb");
+	/// if let Ok(addr) = ConnectionType::single_device(&adb) {
+	///     println!("connecting to {addr}");
+	/// }
+	/// ```
+	pub fn single_device(adb: &Adb) -> crate::result::Result<ConnectionType> {
+		single_connection(adb.list_devices(false)?)
+	}
+
+	/// Encode the connection as `tcp:<ip>:<port>`, `transport_id:<id>` or `usb`
+	pub(crate) fn encode(&self) -> String {
+		match self {
+			ConnectionType::TcpIp(sock) => format!("tcp:{sock}"),
+			ConnectionType::Transport(id) => format!("transport_id:{id}"),
+			ConnectionType::USB => "usb".to_string(),
+		}
+	}
+
+	/// Decode a connection encoded with [`ConnectionType::encode`]
+	pub(crate) fn decode(value: &str) -> crate::result::Result<ConnectionType> {
+		if value == "usb" {
+			Ok(ConnectionType::USB)
+		} else if let Some(id) = value.strip_prefix("transport_id:") {
+			Ok(ConnectionType::Transport(id.parse()?))
+		} else if let Some(sock) = value.strip_prefix("tcp:") {
+			Ok(ConnectionType::TcpIp(sock.parse()?))
+		} else {
+			Err(Error::AddressParseError)
+		}
+	}
+
+	/// Returns the canonical form of this connection, so that the same device reached through
+	/// different addresses compares equal: ipv4-mapped ipv6 addresses are converted to ipv4
+	/// and any loopback address becomes `127.0.0.1`
+	pub fn normalized(&self) -> ConnectionType {
+		match self {
+			ConnectionType::TcpIp(sock) => {
+				let ip = match sock.ip() {
+					ip if ip.is_loopback() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+					IpAddr::V6(ip) => ip.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(ip)),
+					ip => ip,
+				};
+				ConnectionType::TcpIp(SocketAddr::new(ip, sock.port()))
+			}
+			other => *other,
+		}
+	}
+}
+
+fn single_connection(devices: Vec<AdbDevice>) -> crate::result::Result<ConnectionType> {
+	let mut devices = connected_devices(devices).into_iter();
+	match (devices.next(), devices.next()) {
+		(Some(device), None) => Ok(device.addr),
+		(Some(_), Some(_)) => Err(Error::MultipleDevices),
+		(None, _) => Err(Error::NoDevices),
+	}
 }
 
 impl AsArgs<OsString> for ConnectionType {
@@ -70,7 +135,20 @@ impl From<SocketAddr> for ConnectionType {
 impl FromStr for ConnectionType {
 	type Err = Error;
 
+	/// Parses `ip:port` (optionally prefixed by `ip:`), `localhost:port`, `transport:<id>`
+	/// (or `transport_id:<id>`) and `usb`, so that the [`Display`] output parses back
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s == "usb" {
+			return Ok(ConnectionType::USB);
+		}
+		if let Some(id) = s.strip_prefix("transport_id:").or_else(|| s.strip_prefix("transport:")) {
+			return id.parse().map(ConnectionType::Transport).map_err(|_| Error::AddressParseError);
+		}
+		let s = s.strip_prefix("ip:").unwrap_or(s);
+		if let Some(port) = s.strip_prefix("localhost:") {
+			let port = port.parse().map_err(|_| Error::AddressParseError)?;
+			return Ok(ConnectionType::TcpIp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)));
+		}
 		let addr: Result<SocketAddr, AddrParseError> = s.parse();
 		match addr {
 			Ok(addr) => Ok(ConnectionType::TcpIp(addr)),
@@ -96,15 +174,33 @@ impl IntoIterator for ConnectionType {
 	}
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for ConnectionType {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&self.encode())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ConnectionType {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let value = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+		ConnectionType::decode(&value).map_err(serde::de::Error::custom)
+	}
+}
+
 #[cfg(test)]
 mod test {
+	use std::collections::HashSet;
 	use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 	use std::str::FromStr;
 
 	use crate::test::test::init_log;
 	use simple_cmd::debug::CommandDebug;
 
-	use crate::types::ConnectionType;
+	use crate::connection_type::single_connection;
+	use crate::error::Error;
+	use crate::types::{AdbDevice, ConnectionType};
 
 	#[test]
 	fn test_parse_address() {
@@ -117,6 +213,112 @@ mod test {
 		let _address = ConnectionType::from_str(ip);
 
 		ConnectionType::from_str("invalid").expect_err("Expected error");
+		ConnectionType::from_str("transport:x").expect_err("Expected error");
+	}
+
+	#[test]
+	fn test_from_str() {
+		assert_eq!(
+			ConnectionType::TcpIp("192.168.1.6:5555".parse().unwrap()),
+			"192.168.1.6:5555".parse().unwrap()
+		);
+		assert_eq!(ConnectionType::Transport(3), "transport:3".parse().unwrap());
+		assert_eq!(ConnectionType::Transport(3), "transport_id:3".parse().unwrap());
+		assert_eq!(ConnectionType::USB, "usb".parse().unwrap());
+
+		for addr in [
+			ConnectionType::TcpIp("192.168.1.6:5555".parse().unwrap()),
+			ConnectionType::TcpIp("[::1]:5555".parse().unwrap()),
+			ConnectionType::Transport(3),
+			ConnectionType::USB,
+		] {
+			assert_eq!(addr, addr.to_string().parse().unwrap());
+		}
+	}
+
+	#[test]
+	fn test_normalized() {
+		let addr = ConnectionType::from_str("127.0.0.1:5555").unwrap();
+		assert_eq!(addr, ConnectionType::from_str("localhost:5555").unwrap());
+		assert_eq!(addr, ConnectionType::from_str("[::1]:5555").unwrap().normalized());
+		assert_eq!(
+			ConnectionType::from_str("192.168.1.1:5555").unwrap(),
+			ConnectionType::from_str("[::ffff:192.168.1.1]:5555").unwrap().normalized()
+		);
+		assert_ne!(addr, ConnectionType::from_str("127.0.0.1:5556").unwrap().normalized());
+		assert_eq!(ConnectionType::USB, ConnectionType::USB.normalized());
+
+		let addresses: HashSet<ConnectionType> = ["127.0.0.1:5555", "localhost:5555", "[::1]:5555", "192.168.1.1:5555"]
+			.iter()
+			.map(|addr| ConnectionType::from_str(addr).unwrap().normalized())
+			.collect();
+		assert_eq!(2, addresses.len());
+
+		ConnectionType::from_str("localhost:invalid").expect_err("Expected error");
+	}
+
+	#[test]
+	fn test_encode() {
+		for addr in [
+			ConnectionType::USB,
+			ConnectionType::Transport(4),
+			ConnectionType::from_str("192.168.1.6:5555").unwrap(),
+		] {
+			assert_eq!(addr, ConnectionType::decode(&addr.encode()).unwrap());
+		}
+		assert_eq!("tcp:192.168.1.6:5555", ConnectionType::from_str("192.168.1.6:5555").unwrap().encode());
+		assert!(ConnectionType::decode("192.168.1.6:5555").is_err());
+		assert!(ConnectionType::decode("transport_id:x").is_err());
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn test_deserialize() {
+		use serde::Deserialize;
+		use serde::de::IntoDeserializer;
+		use serde::de::value::{Error, StrDeserializer};
+
+		let deserializer: StrDeserializer<Error> = "tcp:192.168.1.6:5555".into_deserializer();
+		assert_eq!(
+			ConnectionType::from_str("192.168.1.6:5555").unwrap(),
+			ConnectionType::deserialize(deserializer).unwrap()
+		);
+
+		let deserializer: StrDeserializer<Error> = "invalid".into_deserializer();
+		assert!(ConnectionType::deserialize(deserializer).is_err());
+	}
+
+	#[test]
+	fn test_single_connection() {
+		let device = |addr: ConnectionType, connected: bool| AdbDevice {
+			name: addr.to_string(),
+			product: "sdk_gphone64_x86_64".to_string(),
+			model: "sdk_gphone64_x86_64".to_string(),
+			device: "emu64xa".to_string(),
+			connected,
+			addr,
+		};
+
+		assert!(matches!(single_connection(vec![]), Err(Error::NoDevices)));
+		assert!(matches!(
+			single_connection(vec![device(ConnectionType::Transport(1), false)]),
+			Err(Error::NoDevices)
+		));
+		assert_eq!(
+			ConnectionType::Transport(2),
+			single_connection(vec![
+				device(ConnectionType::Transport(1), false),
+				device(ConnectionType::Transport(2), true),
+			])
+			.unwrap()
+		);
+		assert!(matches!(
+			single_connection(vec![
+				device(ConnectionType::Transport(1), true),
+				device(ConnectionType::from_str("192.168.1.6:5555").unwrap(), true),
+			]),
+			Err(Error::MultipleDevices)
+		));
 	}
 
 	#[test]
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use rustix::path::Arg;
+use simple_cmd::prelude::OutputExt;
+
+use crate::result::Result;
+use crate::shell::shell_quote;
+use crate::types::{ContentProvider, ContentValue};
+
+lazy_static! {
+	static ref RE_ROW: Regex = Regex::new("^Row: \\d+ (.*)$").unwrap();
+	static ref RE_ROW_COLUMN: Regex = Regex::new("(?:^|, )([\\w.\\-]+)=").unwrap();
+}
+
+/// Parse the `Row: N key=value, key=value` lines printed by `content query`
+pub(crate) fn parse_content_rows(output: &str) -> Vec<HashMap<String, String>> {
+	output
+		.lines()
+		.filter_map(|line| RE_ROW.captures(line)?.get(1))
+		.map(|row| {
+			let row = row.as_str();
+			let columns: Vec<_> = RE_ROW_COLUMN.captures_iter(row).collect();
+			columns
+				.iter()
+				.enumerate()
+				.filter_map(|(index, captures)| {
+					let key = captures.get(1)?;
+					let end = columns.get(index + 1).and_then(|next| next.get(0)).map(|m| m.start()).unwrap_or(row.len());
+					Some((key.as_str().to_string(), row[key.end() + 1..end].to_string()))
+				})
+				.collect()
+		})
+		.collect()
+}
+
+/// Build the `--bind column:type:value` arguments
+fn make_bindings(values: &[(&str, ContentValue)]) -> Vec<String> {
+	values
+		.iter()
+		.flat_map(|(column, value)| {
+			[
+				"--bind".to_string(),
+				shell_quote(format!("{column}:{}:{value}", value.type_code()).as_str()),
+			]
+		})
+		.collect()
+}
+
+/// Build the `content query` arguments
+pub(crate) fn make_query_args(uri: &str, projection: Option<&[&str]>, where_clause: Option<&str>) -> Vec<String> {
+	let mut args = vec![
+		"content".to_string(),
+		"query".to_string(),
+		"--uri".to_string(),
+		shell_quote(uri),
+	];
+
+	if let Some(projection) = projection {
+		args.push("--projection".to_string());
+		args.push(shell_quote(projection.join(":").as_str()));
+	}
+
+	if let Some(where_clause) = where_clause {
+		args.push("--where".to_string());
+		args.push(shell_quote(where_clause));
+	}
+	args
+}
+
+impl<'a> ContentProvider<'a> {
+	/// Query the content provider, returning the rows as column to value maps
+	pub fn query(
+		&self,
+		uri: &str,
+		projection: Option<&[&str]>,
+		where_clause: Option<&str>,
+	) -> Result<Vec<HashMap<String, String>>> {
+		let output = self.parent.exec(make_query_args(uri, projection, where_clause), None, None)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_content_rows(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Insert a row with the given values
+	pub fn insert(&self, uri: &str, values: &[(&str, ContentValue)]) -> Result<()> {
+		let mut args = vec![
+			"content".to_string(),
+			"insert".to_string(),
+			"--uri".to_string(),
+			shell_quote(uri),
+		];
+		args.extend(make_bindings(values));
+		self.handle_result(args)
+	}
+
+	/// Update the rows matching the where clause with the given values
+	pub fn update(&self, uri: &str, values: &[(&str, ContentValue)], where_clause: Option<&str>) -> Result<()> {
+		let mut args = vec![
+			"content".to_string(),
+			"update".to_string(),
+			"--uri".to_string(),
+			shell_quote(uri),
+		];
+		args.extend(make_bindings(values));
+		if let Some(where_clause) = where_clause {
+			args.push("--where".to_string());
+			args.push(shell_quote(where_clause));
+		}
+		self.handle_result(args)
+	}
+
+	/// Delete the rows matching the where clause
+	pub fn delete(&self, uri: &str, where_clause: Option<&str>) -> Result<()> {
+		let mut args = vec![
+			"content".to_string(),
+			"delete".to_string(),
+			"--uri".to_string(),
+			shell_quote(uri),
+		];
+		if let Some(where_clause) = where_clause {
+			args.push("--where".to_string());
+			args.push(shell_quote(where_clause));
+		}
+		self.handle_result(args)
+	}
+
+	#[inline]
+	fn handle_result(&self, args: Vec<String>) -> Result<()> {
+		let output = self.parent.exec(args, None, None)?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::content::{make_bindings, make_query_args, parse_content_rows};
+	use crate::test::test::{connect_emulator, init_log, root_client};
+	use crate::types::ContentValue;
+
+	#[test]
+	fn test_parse_content_rows() {
+		let output = "Row: 0 _id=1, name=volume_music, value=5\n\
+			Row: 1 _id=2, name=device_name, value=My phone, with a comma\n";
+		let rows = parse_content_rows(output);
+		assert_eq!(2, rows.len());
+		assert_eq!("1", rows[0]["_id"]);
+		assert_eq!("volume_music", rows[0]["name"]);
+		assert_eq!("5", rows[0]["value"]);
+		assert_eq!("My phone, with a comma", rows[1]["value"]);
+
+		assert!(parse_content_rows("No result found.\n").is_empty());
+	}
+
+	#[test]
+	fn test_make_args() {
+		assert_eq!(
+			vec![
+				"content",
+				"query",
+				"--uri",
+				"'content://settings/system'",
+				"--projection",
+				"'name:value'",
+				"--where",
+				"'name='\\''volume_music'\\'''",
+			],
+			make_query_args(
+				"content://settings/system",
+				Some(&["name", "value"]),
+				Some("name='volume_music'")
+			)
+		);
+
+		assert_eq!(
+			vec![
+				"--bind",
+				"'name:s:my value'",
+				"--bind",
+				"'count:l:42'",
+				"--bind",
+				"'enabled:b:true'",
+				"--bind",
+				"'ratio:d:0.5'",
+			],
+			make_bindings(&[
+				("name", ContentValue::S("my value".to_string())),
+				("count", ContentValue::I(42)),
+				("enabled", ContentValue::B(true)),
+				("ratio", ContentValue::D(0.5)),
+			])
+		);
+	}
+
+	#[test]
+	fn test_content() {
+		init_log();
+		let client = connect_emulator();
+		root_client(&client);
+		let shell = client.shell();
+		let content = shell.content();
+		let uri = "content://settings/system";
+
+		content
+			.insert(
+				uri,
+				&[
+					("name", ContentValue::S("radb_test".to_string())),
+					("value", ContentValue::I(1)),
+				],
+			)
+			.expect("failed to insert");
+
+		let rows = content
+			.query(uri, Some(&["name", "value"]), Some("name='radb_test'"))
+			.expect("failed to query");
+		assert_eq!(1, rows.len());
+		assert_eq!("1", rows[0]["value"]);
+
+		content
+			.update(uri, &[("value", ContentValue::I(2))], Some("name='radb_test'"))
+			.expect("failed to update");
+		let rows = content.query(uri, None, Some("name='radb_test'")).expect("failed to query");
+		assert_eq!("2", rows[0]["value"]);
+
+		content.delete(uri, Some("name='radb_test'")).expect("failed to delete");
+		let rows = content.query(uri, None, Some("name='radb_test'")).expect("failed to query");
+		assert!(rows.is_empty());
+	}
+}
@@ -1,9 +1,15 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, Local, NaiveDateTime};
 use lazy_static::lazy_static;
-use regex::{Regex, RegexBuilder};
+use regex::{Captures, Regex, RegexBuilder};
 
 use crate::error::Error;
 use crate::result::Result;
-use crate::types::{DexoptState, InstallPermission, PackageFlags, RuntimePermission, SimplePackageReader};
+use crate::types::{
+	ComponentName, CrashKind, CrashReport, DexoptState, InstallPermission, LogcatEntry, PackageFlags, RecentTask,
+	RuntimePermission, ServiceInfo, SimplePackageReader,
+};
 
 lazy_static! {
 	static ref RE_PACKAGES: &'static str = "(?m)^Packages:\\n";
@@ -26,6 +32,16 @@ lazy_static! {
 		Regex::new("(?m)^\\s{4,}(?P<name>[^\\:]+):\\s+granted=(?P<granted>true|false)$").unwrap();
 	static ref RE_DEXOPT_STATE: Regex = Regex::new("(?m)^Dexopt state:\\n").unwrap();
 	static ref RE_PACKAGE_NAME: Regex = Regex::new(r#"^\s+\[[\w.]+]$"#).unwrap();
+	static ref RE_THREADTIME_LINE: Regex = Regex::new(
+		r"^(?:(?P<year>\d{4})-)?(?P<time>\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d{3})\s+(?P<pid>\d+)\s+(?P<tid>\d+)\s+(?P<level>[VDIWEFA])\s+(?P<tag>.*?)\s*: ?(?P<message>.*)$"
+	)
+	.unwrap();
+	static ref RE_CRASH_PROCESS: Regex = Regex::new(r"^Process: ([^,\s]+)").unwrap();
+	static ref RE_ANR_PACKAGE: Regex = Regex::new(r"^ANR in ([^\s(]+)").unwrap();
+	static ref RE_SERVICE_APP: Regex = Regex::new(r"^app=ProcessRecord\{\w+ (\d+):").unwrap();
+	static ref RE_RECENT_TASK_ID: Regex = Regex::new(r"\{\w+ #(\d+) ").unwrap();
+	static ref RE_TARGET_SDK: Regex = Regex::new(r"\btargetSdk=(\d+)").unwrap();
+	static ref RE_MIN_SDK: Regex = Regex::new(r"\bminSdk=(\d+)").unwrap();
 	static ref RE_PACKAGE_PATH: Regex = Regex::new(r#"^\s+path:\s*(?<path>[^\n]+)$"#).unwrap();
 }
 
@@ -167,6 +183,16 @@ impl<'a> SimplePackageReader<'a> {
 		return None;
 	}
 
+	/// Returns the `targetSdk` of the package
+	pub fn target_sdk(&self) -> Option<u16> {
+		RE_TARGET_SDK.captures(self.data).and_then(|c| c[1].parse().ok())
+	}
+
+	/// Returns the `minSdk` of the package
+	pub fn min_sdk(&self) -> Option<u16> {
+		RE_MIN_SDK.captures(self.data).and_then(|c| c[1].parse().ok())
+	}
+
 	pub fn get_package_flags(&self) -> Option<Vec<PackageFlags>> {
 		package_flags(&self.data).ok()
 	}
@@ -281,6 +307,162 @@ pub(crate) fn package_flags(dump: &str) -> Result<Vec<PackageFlags>> {
 	}
 }
 
+/// The timestamp of a [`RE_THREADTIME_LINE`] match. Without the year the current one is assumed.
+fn threadtime_timestamp(captures: &Captures) -> Option<NaiveDateTime> {
+	let year = captures
+		.name("year")
+		.map(|year| year.as_str().to_string())
+		.unwrap_or_else(|| Local::now().year().to_string());
+	let time = captures.name("time")?.as_str();
+	NaiveDateTime::parse_from_str(&format!("{year}-{time}"), "%Y-%m-%d %H:%M:%S%.3f").ok()
+}
+
+/// Parse a logcat line in `threadtime` format (optionally with the `year` modifier),
+/// returning (timestamp, pid, tag, message). Without the year the current one is assumed.
+fn parse_threadtime_line(line: &str) -> Option<(Option<NaiveDateTime>, &str, &str, &str)> {
+	let captures = RE_THREADTIME_LINE.captures(line)?;
+	Some((
+		threadtime_timestamp(&captures),
+		captures.name("pid")?.as_str(),
+		captures.name("tag")?.as_str(),
+		captures.name("message")?.as_str(),
+	))
+}
+
+/// Parse the `threadtime` lines of a logcat dump, sorting them by timestamp.
+/// The sort is stable, so the entries logged at the same time keep their order.
+pub(crate) fn parse_logcat_merged(log: &str) -> Vec<LogcatEntry> {
+	let mut entries = log
+		.lines()
+		.filter_map(|line| {
+			let captures = RE_THREADTIME_LINE.captures(line)?;
+			Some(LogcatEntry {
+				timestamp: threadtime_timestamp(&captures),
+				pid: captures.name("pid")?.as_str().parse().ok()?,
+				tid: captures.name("tid")?.as_str().parse().ok()?,
+				level: captures.name("level")?.as_str().chars().next()?,
+				tag: captures.name("tag")?.as_str().to_string(),
+				message: captures.name("message")?.as_str().to_string(),
+			})
+		})
+		.collect::<Vec<_>>();
+	entries.sort_by_key(|entry| entry.timestamp);
+	entries
+}
+
+/// Extract the `FATAL EXCEPTION` and `ANR in` reports from a logcat dump in `threadtime` format.
+/// A report continues as long as the following lines are logged by the same pid with the same tag.
+pub(crate) fn parse_crash_reports(log: &str) -> Vec<CrashReport> {
+	let mut reports = vec![];
+	let mut current: Option<(CrashReport, &str, &str)> = None;
+
+	for (timestamp, pid, tag, message) in log.lines().filter_map(parse_threadtime_line) {
+		if let Some((report, report_pid, report_tag)) = current.as_mut() {
+			if *report_pid == pid && *report_tag == tag {
+				if report.package.is_none() {
+					let re = match report.kind {
+						CrashKind::Crash => &*RE_CRASH_PROCESS,
+						CrashKind::Anr => &*RE_ANR_PACKAGE,
+					};
+					report.package = re.captures(message).map(|c| c[1].to_string());
+				}
+				report.stack.push(message.to_string());
+				continue;
+			}
+			reports.extend(current.take().map(|(report, _, _)| report));
+		}
+
+		let (kind, package) = if message.starts_with("FATAL EXCEPTION") {
+			(CrashKind::Crash, None)
+		} else if let Some(captures) = RE_ANR_PACKAGE.captures(message) {
+			(CrashKind::Anr, Some(captures[1].to_string()))
+		} else {
+			continue;
+		};
+
+		current = Some((
+			CrashReport {
+				kind,
+				package,
+				timestamp,
+				stack: vec![],
+			},
+			pid,
+			tag,
+		));
+	}
+
+	reports.extend(current.map(|(report, _, _)| report));
+	reports
+}
+
+/// Parse the first `ServiceRecord` of the `dumpsys activity services <component>` output.
+/// When the service has no record it is not running, and the default [`ServiceInfo`] is returned.
+pub(crate) fn parse_service_info(dump: &str) -> ServiceInfo {
+	let mut info = ServiceInfo::default();
+	let mut lines = dump.lines().skip_while(|line| !line.trim_start().starts_with("* ServiceRecord{"));
+	let Some(header) = lines.next() else {
+		return info;
+	};
+	let indent = header.len() - header.trim_start().len();
+
+	for line in lines {
+		let trimmed = line.trim_start();
+		// the record ends with the next record, or with the next section
+		if !trimmed.is_empty() && line.len() - trimmed.len() <= indent {
+			break;
+		}
+
+		if let Some(captures) = RE_SERVICE_APP.captures(trimmed) {
+			info.pid = captures[1].parse().ok();
+		} else if trimmed.starts_with("startRequested=true") {
+			info.started = true;
+		} else if trimmed.starts_with("* Client AppBindRecord{") {
+			info.client_count += 1;
+		} else if trimmed.contains("hasBound=true") {
+			info.bound = true;
+		}
+	}
+
+	info.running = info.pid.is_some();
+	info.bound |= info.client_count > 0;
+	info
+}
+
+/// Parse the tasks of the `dumpsys activity recents` output, most recent first, returning at most `max` of them.
+/// The top activity is the last one of the `Activities=[...]` list, when present.
+pub(crate) fn parse_recent_tasks(dump: &str, max: usize) -> Vec<RecentTask> {
+	let mut tasks: Vec<RecentTask> = vec![];
+	for line in dump.lines().map(str::trim) {
+		if line.starts_with("* Recent #") {
+			if tasks.len() == max {
+				break;
+			}
+			if let Some(captures) = RE_RECENT_TASK_ID.captures(line) {
+				tasks.push(RecentTask {
+					task_id: captures[1].parse().unwrap_or_default(),
+					base_activity: None,
+					top_activity: None,
+					last_active_ms: None,
+				});
+			}
+			continue;
+		}
+
+		let Some(task) = tasks.last_mut() else {
+			continue;
+		};
+		if let Some(value) = line.strip_prefix("realActivity=") {
+			task.base_activity = ComponentName::from_str(value.trim_end_matches('}')).ok();
+		} else if let Some(value) = line.strip_prefix("lastActiveTime=") {
+			task.last_active_ms = value.split_whitespace().next().and_then(|value| value.parse().ok());
+		} else if let Some(value) = line.strip_prefix("Activities=[") {
+			task.top_activity = value.split_whitespace().rev().find_map(|token| ComponentName::from_str(token).ok());
+		}
+	}
+	tasks
+}
+
 #[allow(dead_code)]
 pub fn is_installed(data: &str, package_name: &str, sdk_int: u16) -> Option<String> {
 	match SimplePackageReader::new(data, sdk_int) {
@@ -299,6 +481,217 @@ mod test {
 
 	use super::*;
 
+	#[test]
+	fn test_parse_recent_tasks() {
+		let dump = "ACTIVITY MANAGER RECENT TASKS (dumpsys activity recents)
+  mRecentsUid=10088
+  mRecentsComponent=ComponentInfo{com.android.launcher3/com.android.quickstep.RecentsActivity}
+  mFreezeTaskListReordering=false
+
+  Recent tasks:
+  * Recent #0: Task{9b5a1c7 #14 type=standard A=10123:com.example.app U=0 visible=true mode=fullscreen translucent=false sz=2}
+    userId=0 effectiveUid=u0a123 mCallingUid=u0a88 mUserSetupComplete=true mCallingPackage=com.android.launcher3
+    affinity=10123:com.example.app
+    intent={act=android.intent.action.MAIN cat=[android.intent.category.LAUNCHER] flg=0x10200000 cmp=com.example.app/.MainActivity}
+    mActivityComponent=com.example.app/.MainActivity
+    realActivity=com.example.app/.MainActivity
+    Activities=[ActivityRecord{c1d2e3 u0 com.example.app/.MainActivity t14}, ActivityRecord{f4a5b6 u0 com.example.app/.DetailActivity t14}]
+    lastActiveTime=1823455 (inactive for 3s)
+  * Recent #1: Task{2c3d4e5 #1 type=home A=10088:com.android.launcher3 U=0 visible=false mode=fullscreen translucent=false sz=1}
+    realActivity=com.android.launcher3/.uioverrides.QuickstepLauncher
+    lastActiveTime=1812000 (inactive for 14s)
+  * Recent #2: TaskRecord{7f8e9d0 #9 A=com.android.settings U=0 StackId=3 sz=1}
+    realActivity=com.android.settings/.Settings
+    lastActiveTime=1500000 (inactive for 323s)
+";
+		let tasks = parse_recent_tasks(dump, 10);
+		assert_eq!(3, tasks.len());
+		assert_eq!(
+			RecentTask {
+				task_id: 14,
+				base_activity: Some(ComponentName::from_str("com.example.app/.MainActivity").unwrap()),
+				top_activity: Some(ComponentName::from_str("com.example.app/.DetailActivity").unwrap()),
+				last_active_ms: Some(1823455),
+			},
+			tasks[0]
+		);
+		assert_eq!(1, tasks[1].task_id);
+		assert_eq!(
+			"com.android.launcher3/com.android.launcher3.uioverrides.QuickstepLauncher",
+			tasks[1].base_activity.as_ref().unwrap().to_string()
+		);
+		assert_eq!(None, tasks[1].top_activity);
+		assert_eq!(9, tasks[2].task_id);
+		assert_eq!(Some(1500000), tasks[2].last_active_ms);
+
+		let tasks = parse_recent_tasks(dump, 1);
+		assert_eq!(1, tasks.len());
+		assert_eq!(14, tasks[0].task_id);
+
+		assert!(parse_recent_tasks(dump, 0).is_empty());
+		assert!(parse_recent_tasks("", 5).is_empty());
+	}
+
+	#[test]
+	fn test_sdk_levels() {
+		let dump = "Packages:
+  Package [com.example.app] (4f3c2a1):
+    userId=10123
+    pkg=Package{8b7d6e5 com.example.app}
+    codePath=/data/app/~~AbCdEf==/com.example.app-XyZ==
+    versionCode=42 minSdk=24 targetSdk=34
+    minExtensionVersions=[]
+    versionName=1.4.2
+
+Dexopt state:
+  [com.example.app]
+";
+		let reader = SimplePackageReader::new(dump, 34).unwrap();
+		assert_eq!(Some(34), reader.target_sdk());
+		assert_eq!(Some(24), reader.min_sdk());
+		assert_eq!(Some(42), reader.get_version_code());
+
+		let reader = SimplePackageReader::new("Packages:\n  Package [com.example.app] (4f3c2a1):\n\n", 34).unwrap();
+		assert_eq!(None, reader.target_sdk());
+		assert_eq!(None, reader.min_sdk());
+	}
+
+	#[test]
+	fn test_parse_service_info() {
+		let dump = "ACTIVITY MANAGER SERVICES (dumpsys activity services)
+  User 0 active services:
+  * ServiceRecord{c1a2b3 u0 com.example.app/.PlayerService}
+    intent={cmp=com.example.app/.PlayerService}
+    packageName=com.example.app
+    processName=com.example.app
+    app=ProcessRecord{d4e5f6 12345:com.example.app/u0a123}
+    isForeground=true foregroundId=1 foregroundNoti=Notification(channel=player)
+    createTime=-1m2s ago startingBgTimeout=--
+    startRequested=true delayedStop=false stopIfKilled=false callStart=true lastStartId=1
+    Bindings:
+    * IntentBindRecord{a1b2c3 CREATE}:
+      intent={cmp=com.example.app/.PlayerService}
+      binder=android.os.BinderProxy@7e8f9a
+      requested=true received=true hasBound=true doRebind=false
+      * Client AppBindRecord{11aa22 ProcessRecord{33bb44 23456:com.example.remote/u0a124}}
+        Per-process Connections:
+          ConnectionRecord{55cc66 u0 CR com.example.app/.PlayerService:@77dd88}
+      * Client AppBindRecord{99ee00 ProcessRecord{d4e5f6 12345:com.example.app/u0a123}}
+        Per-process Connections:
+          ConnectionRecord{aa11bb u0 CR com.example.app/.PlayerService:@cc22dd}
+    All Connections:
+      ConnectionRecord{55cc66 u0 CR com.example.app/.PlayerService:@77dd88}
+      ConnectionRecord{aa11bb u0 CR com.example.app/.PlayerService:@cc22dd}
+
+  * ServiceRecord{ff0011 u0 com.example.app/.OtherService}
+    app=ProcessRecord{d4e5f6 99999:com.example.app/u0a123}
+    startRequested=false delayedStop=false stopIfKilled=false callStart=false lastStartId=0
+
+  Connection bindings to services:
+  * ConnectionRecord{55cc66 u0 CR com.example.app/.PlayerService:@77dd88}
+";
+		assert_eq!(
+			ServiceInfo {
+				running: true,
+				pid: Some(12345),
+				started: true,
+				bound: true,
+				client_count: 2,
+			},
+			parse_service_info(dump)
+		);
+
+		let started_only = "  * ServiceRecord{c1a2b3 u0 com.example.app/.SyncService}
+    app=ProcessRecord{d4e5f6 4242:com.example.app/u0a123}
+    startRequested=true delayedStop=false stopIfKilled=false callStart=true lastStartId=3
+";
+		let info = parse_service_info(started_only);
+		assert!(info.running && info.started && !info.bound);
+		assert_eq!(Some(4242), info.pid);
+		assert_eq!(0, info.client_count);
+
+		let not_running = "ACTIVITY MANAGER SERVICES (dumpsys activity services)\n  (nothing)\n";
+		assert_eq!(ServiceInfo::default(), parse_service_info(not_running));
+	}
+
+	#[test]
+	fn test_parse_logcat_merged() {
+		let log = "--------- beginning of main
+2024-05-01 10:00:02.000  1200  1210 I ActivityManager: second
+--------- beginning of system
+2024-05-01 10:00:01.500   800   812 W PackageManager: first
+2024-05-01 10:00:03.250  1200  1201 E AndroidRuntime: FATAL EXCEPTION: main
+--------- beginning of crash
+2024-05-01 10:00:02.000  1300  1300 D Tag: same time as second
+2024-04-30 23:59:59.999   900   900 V Boot : previous day
+";
+		let entries = parse_logcat_merged(log);
+		assert_eq!(
+			vec!["previous day", "first", "second", "same time as second", "FATAL EXCEPTION: main"],
+			entries.iter().map(|entry| entry.message.as_str()).collect::<Vec<_>>()
+		);
+		assert!(entries.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+		assert_eq!(
+			LogcatEntry {
+				timestamp: NaiveDateTime::parse_from_str("2024-05-01 10:00:01.500", "%Y-%m-%d %H:%M:%S%.3f").ok(),
+				pid: 800,
+				tid: 812,
+				level: 'W',
+				tag: "PackageManager".to_string(),
+				message: "first".to_string(),
+			},
+			entries[1]
+		);
+		assert_eq!("Boot", entries[0].tag);
+		assert!(parse_logcat_merged("--------- beginning of main\n").is_empty());
+	}
+
+	#[test]
+	fn test_parse_crash_reports() {
+		let log = "2024-01-15 10:23:45.001  1234  1234 I ActivityThread: Init compatibility object
+2024-01-15 10:23:45.678  4321  4321 E AndroidRuntime: FATAL EXCEPTION: main
+2024-01-15 10:23:45.678  4321  4321 E AndroidRuntime: Process: com.example.app, PID: 4321
+2024-01-15 10:23:45.678  4321  4321 E AndroidRuntime: java.lang.RuntimeException: boom
+2024-01-15 10:23:45.678  4321  4321 E AndroidRuntime: \tat com.example.app.MainActivity.onCreate(MainActivity.kt:12)
+2024-01-15 10:23:45.678  4321  4321 E AndroidRuntime: \tat android.app.Activity.performCreate(Activity.java:8000)
+2024-01-15 10:23:45.700   567   600 I ActivityManager: Process com.example.app (pid 4321) has died
+2024-01-15 10:24:10.123   567   612 E ActivityManager: ANR in com.example.other (com.example.other/.MainActivity)
+2024-01-15 10:24:10.123   567   612 E ActivityManager: PID: 8765
+2024-01-15 10:24:10.123   567   612 E ActivityManager: Reason: Input dispatching timed out
+01-15 10:25:00.000  9999  9999 E AndroidRuntime: FATAL EXCEPTION: pool-1-thread-1
+";
+		let reports = parse_crash_reports(log);
+		assert_eq!(3, reports.len());
+
+		assert_eq!(CrashKind::Crash, reports[0].kind);
+		assert_eq!(Some("com.example.app".to_string()), reports[0].package);
+		assert_eq!(
+			NaiveDateTime::parse_from_str("2024-01-15 10:23:45.678", "%Y-%m-%d %H:%M:%S%.3f").ok(),
+			reports[0].timestamp
+		);
+		assert_eq!(
+			vec![
+				"Process: com.example.app, PID: 4321",
+				"java.lang.RuntimeException: boom",
+				"\tat com.example.app.MainActivity.onCreate(MainActivity.kt:12)",
+				"\tat android.app.Activity.performCreate(Activity.java:8000)",
+			],
+			reports[0].stack
+		);
+
+		assert_eq!(CrashKind::Anr, reports[1].kind);
+		assert_eq!(Some("com.example.other".to_string()), reports[1].package);
+		assert_eq!(2, reports[1].stack.len());
+
+		assert_eq!(CrashKind::Crash, reports[2].kind);
+		assert_eq!(None, reports[2].package);
+		assert!(reports[2].timestamp.is_some());
+		assert!(reports[2].stack.is_empty());
+
+		assert!(parse_crash_reports("").is_empty());
+	}
+
 	#[test]
 	fn test_package_flags() {
 		init_log();
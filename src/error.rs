@@ -2,6 +2,7 @@ use std::io::ErrorKind;
 use std::net::AddrParseError;
 use std::num::ParseIntError;
 use std::process::Output;
+use std::time::Duration;
 
 use image::ImageError;
 use java_properties::PropertiesError;
@@ -53,8 +54,60 @@ pub enum Error {
 	#[error("name not found {0}")]
 	NameNotFoundError(String),
 
+	#[error("service not found {0}")]
+	ServiceNotFound(String),
+
+	#[error("screenrecord does not support audio capture")]
+	AudioUnsupported,
+
+	#[error("dumpsys {service} timed out")]
+	DumpsysTimeout { service: String, partial: Vec<u8> },
+
+	#[error("device unauthorized, accept the debugging authorization on the device")]
+	DeviceUnauthorized,
+
+	#[error("device offline")]
+	DeviceOffline,
+
+	#[error("operation not supported by this device")]
+	UnsupportedOnThisApi,
+
+	#[error("root access required")]
+	RootRequired,
+
+	#[error("failed to download {url}: {message}")]
+	DownloadError { url: String, message: String },
+
 	#[error("avbctl not installed")]
 	AvbctlNotInstalled,
+
+	#[error("{command} timed out after {elapsed:?}")]
+	Timeout { command: String, elapsed: Duration },
+
+	#[error("ffplay not found, install ffmpeg or set FFPlayOptions::binary")]
+	FFPlayNotFound,
+
+	#[error("more than one device connected")]
+	MultipleDevices,
+
+	#[error("no devices connected")]
+	NoDevices,
+
+	#[error("device already connected")]
+	AlreadyConnected,
+
+	#[error("connection refused by the device")]
+	ConnectionRefused,
+
+	#[error("transport id {0} is out of the range addressable by ConnectionType::Transport")]
+	TransportIdOutOfRange(u32),
+
+	#[error("{step} failed: {source}")]
+	SetupError {
+		step: &'static str,
+		#[source]
+		source: Box<Error>,
+	},
 }
 
 impl From<AddrParseError> for Error {
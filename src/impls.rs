@@ -1,20 +1,25 @@
 use std::ffi::OsString;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 use std::vec::IntoIter;
 
 use cmd_lib::AsOsStr;
 use lazy_static::lazy_static;
 use regex::Regex;
-use simple_cmd::CommandBuilder;
+use strum::IntoEnumIterator;
 
 use crate::error::Error;
-use crate::prelude::CommandBuilderExt;
+use crate::shell::shell_quote;
 use crate::traits::{AsArg, AsArgs};
 use crate::types::{
-	Adb, AdbDevice, AdbInstallOptions, Client, ConnectionType, Extra, FFPlayOptions, InputSource, InstallLocationOption,
-	InstallOptions, InstallPermission, Intent, KeyCode, KeyEventType, ListPackageDisplayOptions, ListPackageFilter, LogcatLevel,
-	LogcatOptions, LogcatTag, MemoryStatus, MotionEvent, Package, PackageFlags, PropType, Property, RebootType, Reconnect,
-	RuntimePermission, SELinuxType, ScreenRecordOptions, UninstallOptions, UserOption, Wakefulness,
+	Adb, AdbDevice, AdbInstallOptions, BatteryHealth, BatteryStatus, Client, ClientConfig, ComponentName, ConnectionType,
+	ContentValue, DensityBucket, DeviceState, DirEntry, Extra, FFPlayOptions, ForwardEntry, InputSource, InstallLocationOption,
+	InstallOptions, InstrumentOptions, InstrumentResult, InstallPermission, Intent, KeyCode, KeyEventType,
+	ListPackageDisplayOptions, ListPackageFilter, LogcatLevel, LogcatOptions, LogcatTag, LsOptions, LsSort, MemoryStatus,
+	MotionEvent, NightMode, Package, PackageFlags, PropType, Property, RebootType, Reconnect, RuntimePermission, SELinuxType,
+	ScreenRecordOptions, ServiceState, StayAwakeMode, UninstallOptions, UserOption, WaitState, WaitTransport, Wakefulness,
 };
 
 lazy_static! {
@@ -53,6 +58,87 @@ impl Display for AdbDevice {
 
 // endregion AdbDevice
 
+// region BatteryStatus
+
+impl From<u8> for BatteryStatus {
+	fn from(value: u8) -> Self {
+		match value {
+			2 => BatteryStatus::Charging,
+			3 => BatteryStatus::Discharging,
+			4 => BatteryStatus::NotCharging,
+			5 => BatteryStatus::Full,
+			_ => BatteryStatus::Unknown,
+		}
+	}
+}
+
+// endregion BatteryStatus
+
+// region BatteryHealth
+
+impl From<u8> for BatteryHealth {
+	fn from(value: u8) -> Self {
+		match value {
+			2 => BatteryHealth::Good,
+			3 => BatteryHealth::Overheat,
+			4 => BatteryHealth::Dead,
+			5 => BatteryHealth::OverVoltage,
+			6 => BatteryHealth::UnspecifiedFailure,
+			7 => BatteryHealth::Cold,
+			_ => BatteryHealth::Unknown,
+		}
+	}
+}
+
+// endregion BatteryHealth
+
+// region ContentValue
+
+impl ContentValue {
+	/// Returns the `--bind` type of the value
+	pub(crate) fn type_code(&self) -> char {
+		match self {
+			ContentValue::S(_) => 's',
+			ContentValue::I(_) => 'l',
+			ContentValue::B(_) => 'b',
+			ContentValue::D(_) => 'd',
+		}
+	}
+}
+
+impl Display for ContentValue {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ContentValue::S(value) => write!(f, "{value}"),
+			ContentValue::I(value) => write!(f, "{value}"),
+			ContentValue::B(value) => write!(f, "{value}"),
+			ContentValue::D(value) => write!(f, "{value}"),
+		}
+	}
+}
+
+// endregion ContentValue
+
+// region DeviceState
+
+impl TryFrom<&str> for DeviceState {
+	type Error = Error;
+
+	fn try_from(value: &str) -> Result<Self, Self::Error> {
+		match value.trim().trim_start_matches("error: ").trim_end_matches('.') {
+			"device" => Ok(DeviceState::Device),
+			"offline" | "device offline" => Ok(DeviceState::Offline),
+			"unauthorized" | "device unauthorized" => Ok(DeviceState::Unauthorized),
+			"bootloader" => Ok(DeviceState::Bootloader),
+			"recovery" => Ok(DeviceState::Recovery),
+			"sideload" => Ok(DeviceState::Sideload),
+			_ => Err(Error::ParseInputError),
+		}
+	}
+}
+
+// endregion DeviceState
+
 // region Reconnect
 
 impl Display for Reconnect {
@@ -66,6 +152,52 @@ impl Display for Reconnect {
 
 // endregion Reconnect
 
+// region WaitState
+
+impl Display for WaitState {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			WaitState::Device => write!(f, "device"),
+			WaitState::Recovery => write!(f, "recovery"),
+			WaitState::Sideload => write!(f, "sideload"),
+			WaitState::Bootloader => write!(f, "bootloader"),
+			WaitState::Disconnect => write!(f, "disconnect"),
+		}
+	}
+}
+
+// endregion WaitState
+
+// region WaitTransport
+
+impl Display for WaitTransport {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			WaitTransport::Usb => write!(f, "usb"),
+			WaitTransport::Local => write!(f, "local"),
+			WaitTransport::Any => write!(f, "any"),
+		}
+	}
+}
+
+// endregion WaitTransport
+
+// region StayAwakeMode
+
+impl Display for StayAwakeMode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			StayAwakeMode::On => write!(f, "true"),
+			StayAwakeMode::Off => write!(f, "false"),
+			StayAwakeMode::Usb => write!(f, "usb"),
+			StayAwakeMode::Ac => write!(f, "ac"),
+			StayAwakeMode::Wireless => write!(f, "wireless"),
+		}
+	}
+}
+
+// endregion StayAwakeMode
+
 // region UserOption
 
 impl AsArgs<OsString> for UserOption {
@@ -124,6 +256,20 @@ impl AsArgs<OsString> for UninstallOptions {
 
 // endregion UninstallOptions
 
+// region NightMode
+
+impl Display for NightMode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			NightMode::Yes => write!(f, "yes"),
+			NightMode::No => write!(f, "no"),
+			NightMode::Auto => write!(f, "auto"),
+		}
+	}
+}
+
+// endregion NightMode
+
 // region MemoryStatus
 
 impl Display for MemoryStatus {
@@ -144,6 +290,22 @@ impl Display for MemoryStatus {
 
 // region InputSource
 
+impl InputSource {
+	/// All the sources accepted by `input <source>`
+	pub fn all() -> Vec<InputSource> {
+		InputSource::iter().collect()
+	}
+
+	/// Parse a source ignoring the case, the whitespaces, `-` and `_` (ie. "Touch-Screen")
+	pub fn from_str_lenient(value: &str) -> Option<InputSource> {
+		let value = value.chars().filter(|c| !c.is_whitespace() && *c != '-' && *c != '_').collect::<String>();
+		InputSource::iter().find(|source| {
+			let name: &str = source.into();
+			name.eq_ignore_ascii_case(&value)
+		})
+	}
+}
+
 impl Into<OsString> for InputSource {
 	fn into(self) -> OsString {
 		let string: &str = self.into();
@@ -185,6 +347,301 @@ impl AsArg<OsString> for KeyEventType {
 
 // region KeyCode
 
+/// The Android key codes, sorted by value
+static KEYCODES: [(KeyCode, u32); 290] = [
+	(KeyCode::KEYCODE_UNKNOWN, 0),
+	(KeyCode::KEYCODE_SOFT_LEFT, 1),
+	(KeyCode::KEYCODE_SOFT_RIGHT, 2),
+	(KeyCode::KEYCODE_HOME, 3),
+	(KeyCode::KEYCODE_BACK, 4),
+	(KeyCode::KEYCODE_CALL, 5),
+	(KeyCode::KEYCODE_ENDCALL, 6),
+	(KeyCode::KEYCODE_0, 7),
+	(KeyCode::KEYCODE_1, 8),
+	(KeyCode::KEYCODE_2, 9),
+	(KeyCode::KEYCODE_3, 10),
+	(KeyCode::KEYCODE_4, 11),
+	(KeyCode::KEYCODE_5, 12),
+	(KeyCode::KEYCODE_6, 13),
+	(KeyCode::KEYCODE_7, 14),
+	(KeyCode::KEYCODE_8, 15),
+	(KeyCode::KEYCODE_9, 16),
+	(KeyCode::KEYCODE_STAR, 17),
+	(KeyCode::KEYCODE_POUND, 18),
+	(KeyCode::KEYCODE_DPAD_UP, 19),
+	(KeyCode::KEYCODE_DPAD_DOWN, 20),
+	(KeyCode::KEYCODE_DPAD_LEFT, 21),
+	(KeyCode::KEYCODE_DPAD_RIGHT, 22),
+	(KeyCode::KEYCODE_DPAD_CENTER, 23),
+	(KeyCode::KEYCODE_VOLUME_UP, 24),
+	(KeyCode::KEYCODE_VOLUME_DOWN, 25),
+	(KeyCode::KEYCODE_POWER, 26),
+	(KeyCode::KEYCODE_CAMERA, 27),
+	(KeyCode::KEYCODE_CLEAR, 28),
+	(KeyCode::KEYCODE_A, 29),
+	(KeyCode::KEYCODE_B, 30),
+	(KeyCode::KEYCODE_C, 31),
+	(KeyCode::KEYCODE_D, 32),
+	(KeyCode::KEYCODE_E, 33),
+	(KeyCode::KEYCODE_F, 34),
+	(KeyCode::KEYCODE_G, 35),
+	(KeyCode::KEYCODE_H, 36),
+	(KeyCode::KEYCODE_I, 37),
+	(KeyCode::KEYCODE_J, 38),
+	(KeyCode::KEYCODE_K, 39),
+	(KeyCode::KEYCODE_L, 40),
+	(KeyCode::KEYCODE_M, 41),
+	(KeyCode::KEYCODE_N, 42),
+	(KeyCode::KEYCODE_O, 43),
+	(KeyCode::KEYCODE_P, 44),
+	(KeyCode::KEYCODE_Q, 45),
+	(KeyCode::KEYCODE_R, 46),
+	(KeyCode::KEYCODE_S, 47),
+	(KeyCode::KEYCODE_T, 48),
+	(KeyCode::KEYCODE_U, 49),
+	(KeyCode::KEYCODE_V, 50),
+	(KeyCode::KEYCODE_W, 51),
+	(KeyCode::KEYCODE_X, 52),
+	(KeyCode::KEYCODE_Y, 53),
+	(KeyCode::KEYCODE_Z, 54),
+	(KeyCode::KEYCODE_COMMA, 55),
+	(KeyCode::KEYCODE_PERIOD, 56),
+	(KeyCode::KEYCODE_ALT_LEFT, 57),
+	(KeyCode::KEYCODE_ALT_RIGHT, 58),
+	(KeyCode::KEYCODE_SHIFT_LEFT, 59),
+	(KeyCode::KEYCODE_SHIFT_RIGHT, 60),
+	(KeyCode::KEYCODE_TAB, 61),
+	(KeyCode::KEYCODE_SPACE, 62),
+	(KeyCode::KEYCODE_SYM, 63),
+	(KeyCode::KEYCODE_EXPLORER, 64),
+	(KeyCode::KEYCODE_ENVELOPE, 65),
+	(KeyCode::KEYCODE_ENTER, 66),
+	(KeyCode::KEYCODE_DEL, 67),
+	(KeyCode::KEYCODE_GRAVE, 68),
+	(KeyCode::KEYCODE_MINUS, 69),
+	(KeyCode::KEYCODE_EQUALS, 70),
+	(KeyCode::KEYCODE_LEFT_BRACKET, 71),
+	(KeyCode::KEYCODE_RIGHT_BRACKET, 72),
+	(KeyCode::KEYCODE_BACKSLASH, 73),
+	(KeyCode::KEYCODE_SEMICOLON, 74),
+	(KeyCode::KEYCODE_APOSTROPHE, 75),
+	(KeyCode::KEYCODE_SLASH, 76),
+	(KeyCode::KEYCODE_AT, 77),
+	(KeyCode::KEYCODE_NUM, 78),
+	(KeyCode::KEYCODE_HEADSETHOOK, 79),
+	(KeyCode::KEYCODE_FOCUS, 80),
+	(KeyCode::KEYCODE_PLUS, 81),
+	(KeyCode::KEYCODE_MENU, 82),
+	(KeyCode::KEYCODE_NOTIFICATION, 83),
+	(KeyCode::KEYCODE_SEARCH, 84),
+	(KeyCode::KEYCODE_MEDIA_PLAY_PAUSE, 85),
+	(KeyCode::KEYCODE_MEDIA_STOP, 86),
+	(KeyCode::KEYCODE_MEDIA_NEXT, 87),
+	(KeyCode::KEYCODE_MEDIA_PREVIOUS, 88),
+	(KeyCode::KEYCODE_MEDIA_REWIND, 89),
+	(KeyCode::KEYCODE_MEDIA_FAST_FORWARD, 90),
+	// alias of KEYCODE_MEDIA_FAST_FORWARD
+	(KeyCode::KEYCODE_FAST_FORWARD, 90),
+	(KeyCode::KEYCODE_MUTE, 91),
+	(KeyCode::KEYCODE_PAGE_UP, 92),
+	(KeyCode::KEYCODE_PAGE_DOWN, 93),
+	(KeyCode::KEYCODE_PICTSYMBOLS, 94),
+	(KeyCode::KEYCODE_SWITCH_CHARSET, 95),
+	(KeyCode::KEYCODE_BUTTON_A, 96),
+	(KeyCode::KEYCODE_BUTTON_B, 97),
+	(KeyCode::KEYCODE_BUTTON_C, 98),
+	(KeyCode::KEYCODE_BUTTON_X, 99),
+	(KeyCode::KEYCODE_BUTTON_Y, 100),
+	(KeyCode::KEYCODE_BUTTON_Z, 101),
+	(KeyCode::KEYCODE_BUTTON_L1, 102),
+	(KeyCode::KEYCODE_BUTTON_R1, 103),
+	(KeyCode::KEYCODE_BUTTON_L2, 104),
+	(KeyCode::KEYCODE_BUTTON_R2, 105),
+	(KeyCode::KEYCODE_BUTTON_THUMBL, 106),
+	(KeyCode::KEYCODE_BUTTON_THUMBR, 107),
+	(KeyCode::KEYCODE_BUTTON_START, 108),
+	(KeyCode::KEYCODE_BUTTON_SELECT, 109),
+	(KeyCode::KEYCODE_BUTTON_MODE, 110),
+	(KeyCode::KEYCODE_ESCAPE, 111),
+	(KeyCode::KEYCODE_FORWARD_DEL, 112),
+	(KeyCode::KEYCODE_CTRL_LEFT, 113),
+	(KeyCode::KEYCODE_CTRL_RIGHT, 114),
+	(KeyCode::KEYCODE_CAPS_LOCK, 115),
+	(KeyCode::KEYCODE_SCROLL_LOCK, 116),
+	(KeyCode::KEYCODE_META_LEFT, 117),
+	(KeyCode::KEYCODE_META_RIGHT, 118),
+	(KeyCode::KEYCODE_FUNCTION, 119),
+	(KeyCode::KEYCODE_SYSRQ, 120),
+	(KeyCode::KEYCODE_BREAK, 121),
+	(KeyCode::KEYCODE_MOVE_HOME, 122),
+	(KeyCode::KEYCODE_MOVE_END, 123),
+	(KeyCode::KEYCODE_INSERT, 124),
+	(KeyCode::KEYCODE_FORWARD, 125),
+	(KeyCode::KEYCODE_MEDIA_PLAY, 126),
+	(KeyCode::KEYCODE_MEDIA_PAUSE, 127),
+	(KeyCode::KEYCODE_MEDIA_CLOSE, 128),
+	(KeyCode::KEYCODE_MEDIA_EJECT, 129),
+	(KeyCode::KEYCODE_MEDIA_RECORD, 130),
+	(KeyCode::KEYCODE_F1, 131),
+	(KeyCode::KEYCODE_F2, 132),
+	(KeyCode::KEYCODE_F3, 133),
+	(KeyCode::KEYCODE_F4, 134),
+	(KeyCode::KEYCODE_F5, 135),
+	(KeyCode::KEYCODE_F6, 136),
+	(KeyCode::KEYCODE_F7, 137),
+	(KeyCode::KEYCODE_F8, 138),
+	(KeyCode::KEYCODE_F9, 139),
+	(KeyCode::KEYCODE_F10, 140),
+	(KeyCode::KEYCODE_F11, 141),
+	(KeyCode::KEYCODE_F12, 142),
+	(KeyCode::KEYCODE_NUM_LOCK, 143),
+	(KeyCode::KEYCODE_NUMPAD_0, 144),
+	(KeyCode::KEYCODE_NUMPAD_1, 145),
+	(KeyCode::KEYCODE_NUMPAD_2, 146),
+	(KeyCode::KEYCODE_NUMPAD_3, 147),
+	(KeyCode::KEYCODE_NUMPAD_4, 148),
+	(KeyCode::KEYCODE_NUMPAD_5, 149),
+	(KeyCode::KEYCODE_NUMPAD_6, 150),
+	(KeyCode::KEYCODE_NUMPAD_7, 151),
+	(KeyCode::KEYCODE_NUMPAD_8, 152),
+	(KeyCode::KEYCODE_NUMPAD_9, 153),
+	(KeyCode::KEYCODE_NUMPAD_DIVIDE, 154),
+	(KeyCode::KEYCODE_NUMPAD_MULTIPLY, 155),
+	(KeyCode::KEYCODE_NUMPAD_SUBTRACT, 156),
+	(KeyCode::KEYCODE_NUMPAD_ADD, 157),
+	(KeyCode::KEYCODE_NUMPAD_DOT, 158),
+	(KeyCode::KEYCODE_NUMPAD_COMMA, 159),
+	(KeyCode::KEYCODE_NUMPAD_ENTER, 160),
+	(KeyCode::KEYCODE_NUMPAD_EQUALS, 161),
+	(KeyCode::KEYCODE_NUMPAD_LEFT_PAREN, 162),
+	(KeyCode::KEYCODE_NUMPAD_RIGHT_PAREN, 163),
+	(KeyCode::KEYCODE_VOLUME_MUTE, 164),
+	(KeyCode::KEYCODE_INFO, 165),
+	(KeyCode::KEYCODE_CHANNEL_UP, 166),
+	(KeyCode::KEYCODE_CHANNEL_DOWN, 167),
+	(KeyCode::KEYCODE_ZOOM_IN, 168),
+	(KeyCode::KEYCODE_ZOOM_OUT, 169),
+	(KeyCode::KEYCODE_TV, 170),
+	(KeyCode::KEYCODE_WINDOW, 171),
+	(KeyCode::KEYCODE_GUIDE, 172),
+	(KeyCode::KEYCODE_DVR, 173),
+	(KeyCode::KEYCODE_BOOKMARK, 174),
+	(KeyCode::KEYCODE_CAPTIONS, 175),
+	(KeyCode::KEYCODE_SETTINGS, 176),
+	(KeyCode::KEYCODE_TV_POWER, 177),
+	(KeyCode::KEYCODE_TV_INPUT, 178),
+	(KeyCode::KEYCODE_STB_POWER, 179),
+	(KeyCode::KEYCODE_STB_INPUT, 180),
+	(KeyCode::KEYCODE_AVR_POWER, 181),
+	(KeyCode::KEYCODE_AVR_INPUT, 182),
+	(KeyCode::KEYCODE_PROG_RED, 183),
+	(KeyCode::KEYCODE_PROG_GREEN, 184),
+	(KeyCode::KEYCODE_PROG_YELLOW, 185),
+	(KeyCode::KEYCODE_PROG_BLUE, 186),
+	(KeyCode::KEYCODE_APP_SWITCH, 187),
+	(KeyCode::KEYCODE_BUTTON_1, 188),
+	(KeyCode::KEYCODE_BUTTON_2, 189),
+	(KeyCode::KEYCODE_BUTTON_3, 190),
+	(KeyCode::KEYCODE_BUTTON_4, 191),
+	(KeyCode::KEYCODE_BUTTON_5, 192),
+	(KeyCode::KEYCODE_BUTTON_6, 193),
+	(KeyCode::KEYCODE_BUTTON_7, 194),
+	(KeyCode::KEYCODE_BUTTON_8, 195),
+	(KeyCode::KEYCODE_BUTTON_9, 196),
+	(KeyCode::KEYCODE_BUTTON_10, 197),
+	(KeyCode::KEYCODE_BUTTON_11, 198),
+	(KeyCode::KEYCODE_BUTTON_12, 199),
+	(KeyCode::KEYCODE_BUTTON_13, 200),
+	(KeyCode::KEYCODE_BUTTON_14, 201),
+	(KeyCode::KEYCODE_BUTTON_15, 202),
+	(KeyCode::KEYCODE_BUTTON_16, 203),
+	(KeyCode::KEYCODE_LANGUAGE_SWITCH, 204),
+	(KeyCode::KEYCODE_MANNER_MODE, 205),
+	(KeyCode::KEYCODE_3D_MODE, 206),
+	(KeyCode::KEYCODE_CONTACTS, 207),
+	(KeyCode::KEYCODE_CALENDAR, 208),
+	(KeyCode::KEYCODE_MUSIC, 209),
+	(KeyCode::KEYCODE_CALCULATOR, 210),
+	(KeyCode::KEYCODE_ZENKAKU_HANKAKU, 211),
+	(KeyCode::KEYCODE_EISU, 212),
+	(KeyCode::KEYCODE_MUHENKAN, 213),
+	(KeyCode::KEYCODE_HENKAN, 214),
+	(KeyCode::KEYCODE_KATAKANA_HIRAGANA, 215),
+	(KeyCode::KEYCODE_YEN, 216),
+	(KeyCode::KEYCODE_RO, 217),
+	(KeyCode::KEYCODE_KANA, 218),
+	(KeyCode::KEYCODE_ASSIST, 219),
+	(KeyCode::KEYCODE_BRIGHTNESS_DOWN, 220),
+	(KeyCode::KEYCODE_BRIGHTNESS_UP, 221),
+	(KeyCode::KEYCODE_MEDIA_AUDIO_TRACK, 222),
+	(KeyCode::KEYCODE_SLEEP, 223),
+	(KeyCode::KEYCODE_WAKEUP, 224),
+	(KeyCode::KEYCODE_PAIRING, 225),
+	(KeyCode::KEYCODE_MEDIA_TOP_MENU, 226),
+	(KeyCode::KEYCODE_11, 227),
+	(KeyCode::KEYCODE_12, 228),
+	(KeyCode::KEYCODE_LAST_CHANNEL, 229),
+	(KeyCode::KEYCODE_TV_DATA_SERVICE, 230),
+	(KeyCode::KEYCODE_VOICE_ASSIST, 231),
+	(KeyCode::KEYCODE_TV_RADIO_SERVICE, 232),
+	(KeyCode::KEYCODE_TV_TELETEXT, 233),
+	(KeyCode::KEYCODE_TV_NUMBER_ENTRY, 234),
+	(KeyCode::KEYCODE_TV_TERRESTRIAL_ANALOG, 235),
+	(KeyCode::KEYCODE_TV_TERRESTRIAL_DIGITAL, 236),
+	(KeyCode::KEYCODE_TV_SATELLITE, 237),
+	(KeyCode::KEYCODE_TV_SATELLITE_BS, 238),
+	(KeyCode::KEYCODE_TV_SATELLITE_CS, 239),
+	(KeyCode::KEYCODE_TV_SATELLITE_SERVICE, 240),
+	(KeyCode::KEYCODE_TV_NETWORK, 241),
+	(KeyCode::KEYCODE_TV_ANTENNA_CABLE, 242),
+	(KeyCode::KEYCODE_TV_INPUT_HDMI_1, 243),
+	(KeyCode::KEYCODE_TV_INPUT_HDMI_2, 244),
+	(KeyCode::KEYCODE_TV_INPUT_HDMI_3, 245),
+	(KeyCode::KEYCODE_TV_INPUT_HDMI_4, 246),
+	(KeyCode::KEYCODE_TV_INPUT_COMPOSITE_1, 247),
+	(KeyCode::KEYCODE_TV_INPUT_COMPOSITE_2, 248),
+	(KeyCode::KEYCODE_TV_INPUT_COMPONENT_1, 249),
+	(KeyCode::KEYCODE_TV_INPUT_COMPONENT_2, 250),
+	(KeyCode::KEYCODE_TV_INPUT_VGA_1, 251),
+	(KeyCode::KEYCODE_TV_AUDIO_DESCRIPTION, 252),
+	(KeyCode::KEYCODE_TV_AUDIO_DESCRIPTION_MIX_UP, 253),
+	(KeyCode::KEYCODE_TV_AUDIO_DESCRIPTION_MIX_DOWN, 254),
+	(KeyCode::KEYCODE_TV_ZOOM_MODE, 255),
+	(KeyCode::KEYCODE_TV_CONTENTS_MENU, 256),
+	(KeyCode::KEYCODE_TV_MEDIA_CONTEXT_MENU, 257),
+	(KeyCode::KEYCODE_TV_TIMER_PROGRAMMING, 258),
+	(KeyCode::KEYCODE_HELP, 259),
+	(KeyCode::KEYCODE_NAVIGATE_PREVIOUS, 260),
+	(KeyCode::KEYCODE_NAVIGATE_NEXT, 261),
+	(KeyCode::KEYCODE_NAVIGATE_IN, 262),
+	(KeyCode::KEYCODE_NAVIGATE_OUT, 263),
+	(KeyCode::KEYCODE_STEM_PRIMARY, 264),
+	(KeyCode::KEYCODE_STEM_1, 265),
+	(KeyCode::KEYCODE_STEM_2, 266),
+	(KeyCode::KEYCODE_STEM_3, 267),
+	(KeyCode::KEYCODE_DPAD_UP_LEFT, 268),
+	(KeyCode::KEYCODE_DPAD_DOWN_LEFT, 269),
+	(KeyCode::KEYCODE_DPAD_UP_RIGHT, 270),
+	(KeyCode::KEYCODE_DPAD_DOWN_RIGHT, 271),
+	(KeyCode::KEYCODE_MEDIA_SKIP_FORWARD, 272),
+	(KeyCode::KEYCODE_MEDIA_SKIP_BACKWARD, 273),
+	(KeyCode::KEYCODE_MEDIA_STEP_FORWARD, 274),
+	(KeyCode::KEYCODE_MEDIA_STEP_BACKWARD, 275),
+	(KeyCode::KEYCODE_SOFT_SLEEP, 276),
+	(KeyCode::KEYCODE_CUT, 277),
+	(KeyCode::KEYCODE_COPY, 278),
+	(KeyCode::KEYCODE_PASTE, 279),
+	(KeyCode::KEYCODE_SYSTEM_NAVIGATION_UP, 280),
+	(KeyCode::KEYCODE_SYSTEM_NAVIGATION_DOWN, 281),
+	(KeyCode::KEYCODE_SYSTEM_NAVIGATION_LEFT, 282),
+	(KeyCode::KEYCODE_SYSTEM_NAVIGATION_RIGHT, 283),
+	(KeyCode::KEYCODE_ALL_APPS, 284),
+	(KeyCode::KEYCODE_REFRESH, 285),
+	(KeyCode::KEYCODE_THUMBS_UP, 286),
+	(KeyCode::KEYCODE_THUMBS_DOWN, 287),
+	(KeyCode::KEYCODE_PROFILE_SWITCH, 288),
+];
+
 impl Into<OsString> for KeyCode {
 	fn into(self) -> OsString {
 		let string: &str = self.into();
@@ -192,6 +649,39 @@ impl Into<OsString> for KeyCode {
 	}
 }
 
+impl From<KeyCode> for u32 {
+	fn from(value: KeyCode) -> Self {
+		KEYCODES.iter().find(|(keycode, _)| *keycode == value).map(|(_, code)| *code).unwrap_or(0)
+	}
+}
+
+impl TryFrom<u32> for KeyCode {
+	type Error = Error;
+
+	fn try_from(value: u32) -> Result<Self, Self::Error> {
+		KEYCODES
+			.iter()
+			.find(|(_, code)| *code == value)
+			.map(|(keycode, _)| *keycode)
+			.ok_or(Error::ParseInputError)
+	}
+}
+
+impl FromStr for KeyCode {
+	type Err = Error;
+
+	/// Accepts both the full name (`KEYCODE_HOME`) and the bare one (`HOME`), case insensitive
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let name = s.trim().to_uppercase();
+		let name = if name.starts_with("KEYCODE_") { name } else { format!("KEYCODE_{name}") };
+		KEYCODES
+			.iter()
+			.map(|(keycode, _)| *keycode)
+			.find(|keycode| <&str>::from(keycode) == name)
+			.ok_or(Error::ParseInputError)
+	}
+}
+
 // endregion KeyCode
 
 // region Package
@@ -212,6 +702,10 @@ impl Display for Package {
 			write!(f, " file_name:{}", file_name).unwrap();
 		}
 
+		if let Some(apk_size) = self.apk_size {
+			write!(f, " apk_size:{}", apk_size).unwrap();
+		}
+
 		Ok(())
 	}
 }
@@ -439,6 +933,8 @@ impl Display for RebootType {
 			RebootType::Sideload => write!(f, "sideload"),
 			RebootType::SideloadAutoReboot => write!(f, "sideload-auto-reboot"),
 			RebootType::Dra => write!(f, "dra"),
+			RebootType::Fastboot => write!(f, "fastboot"),
+			RebootType::Edl => write!(f, "edl"),
 		}
 	}
 }
@@ -464,6 +960,13 @@ impl IntoIterator for LogcatOptions {
 			args.push("-d".into());
 		}
 
+		for buffer in self.buffers {
+			args.extend([
+				"-b".into(),
+				buffer.into(),
+			]);
+		}
+
 		if let Some(filename) = self.filename {
 			args.extend([
 				"-f".into(),
@@ -478,7 +981,7 @@ impl IntoIterator for LogcatOptions {
 			]);
 		}
 
-		if let Some(pid) = self.pid {
+		for pid in self.pid.into_iter().chain(self.pids) {
 			args.extend([
 				"--pid".into(),
 				format!("{}", pid).into(),
@@ -532,6 +1035,71 @@ impl Display for LogcatTag {
 
 // endregion LogcatTag
 
+// region ClientConfig
+
+impl Default for ClientConfig {
+	fn default() -> Self {
+		ClientConfig {
+			get_state_timeout: Duration::from_millis(200),
+			sleep_after_root: Duration::from_millis(1_000),
+			sleep_after_tcpip: Duration::from_millis(1_000),
+			default_command_timeout: None,
+		}
+	}
+}
+
+// endregion ClientConfig
+
+// region DensityBucket
+
+impl DensityBucket {
+	/// Returns the nominal dpi of the bucket
+	pub fn dpi(&self) -> u32 {
+		match self {
+			DensityBucket::Ldpi => 120,
+			DensityBucket::Mdpi => 160,
+			DensityBucket::Tv => 213,
+			DensityBucket::Hdpi => 240,
+			DensityBucket::Xhdpi => 320,
+			DensityBucket::Xxhdpi => 480,
+			DensityBucket::Xxxhdpi => 640,
+		}
+	}
+}
+
+impl From<u32> for DensityBucket {
+	/// Maps the dpi to the closest bucket, as the resources are resolved by the framework
+	fn from(dpi: u32) -> Self {
+		match dpi {
+			0..=140 => DensityBucket::Ldpi,
+			141..=186 => DensityBucket::Mdpi,
+			187..=226 => DensityBucket::Tv,
+			227..=280 => DensityBucket::Hdpi,
+			281..=400 => DensityBucket::Xhdpi,
+			401..=560 => DensityBucket::Xxhdpi,
+			_ => DensityBucket::Xxxhdpi,
+		}
+	}
+}
+
+impl Display for DensityBucket {
+	/// The resource qualifier of the bucket (eg. `xhdpi`)
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		let qualifier = match self {
+			DensityBucket::Ldpi => "ldpi",
+			DensityBucket::Mdpi => "mdpi",
+			DensityBucket::Tv => "tvdpi",
+			DensityBucket::Hdpi => "hdpi",
+			DensityBucket::Xhdpi => "xhdpi",
+			DensityBucket::Xxhdpi => "xxhdpi",
+			DensityBucket::Xxxhdpi => "xxxhdpi",
+		};
+		write!(f, "{qualifier}")
+	}
+}
+
+// endregion DensityBucket
+
 // region FFPlayOptions
 
 impl Default for FFPlayOptions {
@@ -540,6 +1108,19 @@ impl Default for FFPlayOptions {
 			framerate: Some(30),
 			size: Some((1440, 800)),
 			probesize: Some(300),
+			binary: None,
+		}
+	}
+}
+
+impl FFPlayOptions {
+	/// Returns the path of the ffplay binary, either [`FFPlayOptions::binary`] or the one found in the PATH.
+	/// Fails with [`Error::FFPlayNotFound`] if it doesn't exist
+	pub fn check_available(&self) -> Result<PathBuf, Error> {
+		match &self.binary {
+			Some(binary) if binary.is_file() => Ok(binary.clone()),
+			Some(_) => Err(Error::FFPlayNotFound),
+			None => which::which("ffplay").map_err(|_| Error::FFPlayNotFound),
 		}
 	}
 }
@@ -580,6 +1161,32 @@ impl Display for Property {
 
 // endregion Property
 
+// region ForwardEntry
+
+impl Display for ForwardEntry {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {} {}", self.serial, self.local, self.remote)
+	}
+}
+
+impl TryFrom<&str> for ForwardEntry {
+	type Error = Error;
+
+	fn try_from(value: &str) -> Result<Self, Self::Error> {
+		let mut split = value.split_whitespace();
+		match (split.next(), split.next(), split.next(), split.next()) {
+			(Some(serial), Some(local), Some(remote), None) => Ok(ForwardEntry {
+				serial: serial.to_string(),
+				local: local.to_string(),
+				remote: remote.to_string(),
+			}),
+			_ => Err(Error::ParseInputError),
+		}
+	}
+}
+
+// endregion ForwardEntry
+
 // region PropType
 
 impl TryFrom<Vec<u8>> for PropType {
@@ -626,6 +1233,46 @@ impl ToString for PropType {
 
 // endregion PropType
 
+// region LsOptions
+
+impl IntoIterator for LsOptions {
+	type Item = OsString;
+	type IntoIter = IntoIter<Self::Item>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		let mut flags = String::new();
+		if self.all {
+			flags.push('A');
+		}
+		if self.long {
+			flags.push('l');
+		}
+		if self.classify {
+			flags.push_str("pF");
+		}
+		if self.recursive {
+			flags.push('R');
+		}
+		match self.sort {
+			LsSort::Name => {}
+			LsSort::Size => flags.push('S'),
+			LsSort::Time => flags.push('t'),
+			LsSort::None => flags.push('f'),
+		}
+		if self.reverse {
+			flags.push('r');
+		}
+
+		if flags.is_empty() {
+			vec![].into_iter()
+		} else {
+			vec![format!("-{flags}").into()].into_iter()
+		}
+	}
+}
+
+// endregion LsOptions
+
 // region ScreenRecordOptions
 
 impl Default for ScreenRecordOptions {
@@ -686,6 +1333,7 @@ impl ScreenRecordOptions {
 			bug_report: None,
 			size: None,
 			verbose: false,
+			audio: false,
 		}
 	}
 }
@@ -717,8 +1365,9 @@ impl TryFrom<&str> for SELinuxType {
 
 	fn try_from(value: &str) -> Result<Self, Self::Error> {
 		match value.trim() {
-			"Enforcing" => Ok(SELinuxType::Enforcing),
-			"Permissive" => Ok(SELinuxType::Permissive),
+			// `getenforce` output or the content of /sys/fs/selinux/enforce
+			"Enforcing" | "1" => Ok(SELinuxType::Enforcing),
+			"Permissive" | "0" => Ok(SELinuxType::Permissive),
 			_ => Err(Error::ParseInputError),
 		}
 	}
@@ -819,6 +1468,27 @@ impl Extra {
 		self
 	}
 
+	pub fn put_float_extra(&mut self, name: &str, value: f32) -> &mut Self {
+		self.ef.insert(name.to_string(), value);
+		self
+	}
+
+	pub fn put_uri_extra(&mut self, name: &str, value: &str) -> &mut Self {
+		self.eu.insert(name.to_string(), value.to_string());
+		self
+	}
+
+	/// `value` is a flattened component name (`package/.Class`)
+	pub fn put_component_extra(&mut self, name: &str, value: &str) -> &mut Self {
+		self.ecn.insert(name.to_string(), value.to_string());
+		self
+	}
+
+	pub fn put_float_array_extra(&mut self, name: &str, value: Vec<f32>) -> &mut Self {
+		self.efa.insert(name.to_string(), value);
+		self
+	}
+
 	pub fn put_string_array_extra(&mut self, name: &str, value: Vec<String>) -> &mut Self {
 		self.esa.insert(name.to_string(), value);
 		self
@@ -903,7 +1573,7 @@ impl Display for Extra {
 
 		if !self.esa.is_empty() {
 			self.esa.iter().for_each(|entry| {
-				output.push(format!("--efa {:} {:}", entry.0, entry.1.join(",")));
+				output.push(format!("--esa {:} {:}", entry.0, entry.1.join(",")));
 			});
 		}
 
@@ -1005,12 +1675,6 @@ impl TryFrom<&AdbDevice> for Client {
 	}
 }
 
-impl From<&Client> for CommandBuilder {
-	fn from(value: &Client) -> Self {
-		CommandBuilder::adb(&value.adb).addr(value.addr).with_debug(value.debug)
-	}
-}
-
 impl Display for Client {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		self.addr.fmt(f)
@@ -1018,3 +1682,281 @@ impl Display for Client {
 }
 
 // endregion Client
+
+// region InstrumentOptions
+
+impl IntoIterator for InstrumentOptions {
+	type Item = OsString;
+	type IntoIter = IntoIter<Self::Item>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		let mut args: Vec<OsString> = vec![];
+		if self.raw {
+			args.push("-r".into());
+		}
+		if self.wait {
+			args.push("-w".into());
+		}
+		if let Some(class) = self.class {
+			args.push("-e".into());
+			args.push("class".into());
+			args.push(shell_quote(&class).into());
+		}
+
+		let mut extras: Vec<_> = self.extras.into_iter().collect();
+		extras.sort();
+		for (key, value) in extras {
+			args.push("-e".into());
+			args.push(key.into());
+			args.push(shell_quote(&value).into());
+		}
+		args.into_iter()
+	}
+}
+
+// endregion InstrumentOptions
+
+// region InstrumentResult
+
+impl InstrumentResult {
+	/// The instrumentation completed and no test failed
+	pub fn success(&self) -> bool {
+		self.code == Some(-1) && self.failed == 0
+	}
+}
+
+// endregion InstrumentResult
+
+// region ComponentName
+
+impl Display for ComponentName {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}/{}", self.package, self.class)
+	}
+}
+
+impl FromStr for ComponentName {
+	type Err = Error;
+
+	/// Parse a `package/class` component. A class starting with `.` is relative to the package.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.split_once('/') {
+			Some((package, class)) if !package.is_empty() && !class.is_empty() => Ok(ComponentName {
+				package: package.to_string(),
+				class: match class.strip_prefix('.') {
+					Some(_) => format!("{package}{class}"),
+					None => class.to_string(),
+				},
+			}),
+			_ => Err(Error::ParseInputError),
+		}
+	}
+}
+
+// endregion ComponentName
+
+// region ServiceState
+
+impl From<&str> for ServiceState {
+	fn from(value: &str) -> Self {
+		match value.trim() {
+			"running" => ServiceState::Running,
+			"stopped" => ServiceState::Stopped,
+			"restarting" => ServiceState::Restarting,
+			_ => ServiceState::Unknown,
+		}
+	}
+}
+
+// endregion ServiceState
+
+// region DirEntry
+
+impl DirEntry {
+	/// Always false when the entry was not listed using the long listing format
+	pub fn is_dir(&self) -> bool {
+		self.mode.as_deref().is_some_and(|mode| mode.starts_with('d'))
+	}
+
+	pub fn is_file(&self) -> bool {
+		self.mode.as_deref().is_some_and(|mode| mode.starts_with('-'))
+	}
+
+	pub fn is_symlink(&self) -> bool {
+		self.mode.as_deref().is_some_and(|mode| mode.starts_with('l'))
+	}
+}
+
+// endregion DirEntry
+
+#[cfg(test)]
+mod test {
+	use std::str::FromStr;
+
+	use strum::IntoEnumIterator;
+
+	use crate::error::Error;
+	use crate::traits::AsArg;
+	use crate::types::{DensityBucket, Extra, FFPlayOptions, InputSource, KeyCode, RebootType, SELinuxType, ServiceState};
+
+	#[test]
+	fn test_keycode_round_trip() {
+		for keycode in KeyCode::iter() {
+			let name: &str = keycode.into();
+			assert_eq!(keycode, KeyCode::from_str(name).unwrap());
+			assert_eq!(keycode, KeyCode::from_str(name.trim_start_matches("KEYCODE_")).unwrap());
+
+			let code = u32::from(keycode);
+			if keycode != KeyCode::KEYCODE_FAST_FORWARD {
+				assert_eq!(keycode, KeyCode::try_from(code).unwrap(), "{name} = {code}");
+			}
+		}
+	}
+
+	#[test]
+	fn test_input_source() {
+		assert_eq!(10, InputSource::all().len());
+		assert_eq!(Some(InputSource::touchscreen), InputSource::from_str_lenient("touchscreen"));
+		assert_eq!(Some(InputSource::touchscreen), InputSource::from_str_lenient(" Touch-Screen "));
+		assert_eq!(Some(InputSource::touchnavigation), InputSource::from_str_lenient("TOUCH_NAVIGATION"));
+		assert_eq!(Some(InputSource::dpad), InputSource::from_str_lenient("DPad"));
+		assert_eq!(None, InputSource::from_str_lenient("touch"));
+		assert_eq!(None, InputSource::from_str_lenient(""));
+	}
+
+	#[test]
+	fn test_extra_display() {
+		let mut extra = Extra::default();
+		extra
+			.put_string_extra("name", "value")
+			.put_bool_extra("enabled", true)
+			.put_int_extra("count", 3)
+			.put_long_extra("id", 9000000000)
+			.put_float_extra("ratio", 1.5)
+			.put_uri_extra("uri", "content://settings/system")
+			.put_component_extra("component", "com.example/.MainActivity")
+			.put_float_array_extra("ratios", vec![0.5, 2.0])
+			.put_string_array_extra("names", vec!["a".to_string(), "b".to_string()]);
+		extra.eia.insert("ints".to_string(), vec![1, 2]);
+		extra.ela.insert("longs".to_string(), vec![3, 4]);
+		extra.grant_read_uri_permission = true;
+
+		let display = extra.to_string();
+		for expected in [
+			"--es name value",
+			"--ez enabled true",
+			"--ei count 3",
+			"--el id 9000000000",
+			"--ef ratio 1.5",
+			"--eu uri content://settings/system",
+			"--ecn component com.example/.MainActivity",
+			"--eia ints 1,2",
+			"--ela longs 3,4",
+			"--efa ratios 0.5,2",
+			"--esa names a,b",
+			"--grant-read-uri-permission",
+		] {
+			assert!(display.contains(expected), "{expected} not found in {display}");
+		}
+	}
+
+	#[test]
+	fn test_service_state() {
+		assert_eq!(ServiceState::Running, ServiceState::from("running\n"));
+		assert_eq!(ServiceState::Stopped, ServiceState::from("stopped"));
+		assert_eq!(ServiceState::Restarting, ServiceState::from("restarting"));
+		assert_eq!(ServiceState::Unknown, ServiceState::from(""));
+		assert_eq!(ServiceState::Unknown, ServiceState::from("stopping"));
+	}
+
+	#[test]
+	fn test_selinux_type() {
+		assert_eq!(SELinuxType::Enforcing, SELinuxType::try_from("Enforcing\n").unwrap());
+		assert_eq!(SELinuxType::Permissive, SELinuxType::try_from("Permissive").unwrap());
+		assert_eq!(SELinuxType::Enforcing, SELinuxType::try_from("1\n").unwrap());
+		assert_eq!(SELinuxType::Permissive, SELinuxType::try_from(b"0".to_vec()).unwrap());
+		assert!(SELinuxType::try_from("/system/bin/sh: getenforce: not found").is_err());
+		assert!(SELinuxType::try_from("2").is_err());
+
+		for enforce in [
+			SELinuxType::Enforcing,
+			SELinuxType::Permissive,
+		] {
+			let arg = enforce.as_arg();
+			assert_eq!(enforce, SELinuxType::try_from(arg.to_str().unwrap()).unwrap());
+		}
+	}
+
+	#[test]
+	fn test_keycode_from() {
+		assert_eq!(KeyCode::KEYCODE_HOME, KeyCode::try_from(3).unwrap());
+		assert_eq!(KeyCode::KEYCODE_A, KeyCode::try_from(29).unwrap());
+		assert_eq!(KeyCode::KEYCODE_MEDIA_FAST_FORWARD, KeyCode::try_from(90).unwrap());
+		assert_eq!(90, u32::from(KeyCode::KEYCODE_FAST_FORWARD));
+		assert!(KeyCode::try_from(100_000).is_err());
+
+		assert_eq!(KeyCode::KEYCODE_HOME, KeyCode::from_str("KEYCODE_HOME").unwrap());
+		assert_eq!(KeyCode::KEYCODE_HOME, KeyCode::from_str("home").unwrap());
+		assert_eq!(KeyCode::KEYCODE_3, KeyCode::from_str("3").unwrap());
+		assert!(KeyCode::from_str("NOT_A_KEY").is_err());
+	}
+
+	#[test]
+	fn test_ffplay_check_available() {
+		let options = FFPlayOptions {
+			binary: Some("/invalid/path/to/ffplay".into()),
+			..Default::default()
+		};
+		assert!(matches!(options.check_available(), Err(Error::FFPlayNotFound)));
+
+		let options = FFPlayOptions {
+			binary: Some(which::which("true").unwrap()),
+			..Default::default()
+		};
+		assert_eq!(which::which("true").unwrap(), options.check_available().unwrap());
+	}
+
+	#[test]
+	fn test_density_bucket() {
+		for (dpi, bucket) in [
+			(120, DensityBucket::Ldpi),
+			(160, DensityBucket::Mdpi),
+			(180, DensityBucket::Mdpi),
+			(213, DensityBucket::Tv),
+			(240, DensityBucket::Hdpi),
+			(280, DensityBucket::Hdpi),
+			(320, DensityBucket::Xhdpi),
+			(420, DensityBucket::Xxhdpi),
+			(480, DensityBucket::Xxhdpi),
+			(560, DensityBucket::Xxhdpi),
+			(640, DensityBucket::Xxxhdpi),
+		] {
+			assert_eq!(bucket, DensityBucket::from(dpi), "dpi {dpi}");
+		}
+
+		for bucket in [
+			DensityBucket::Ldpi,
+			DensityBucket::Mdpi,
+			DensityBucket::Tv,
+			DensityBucket::Hdpi,
+			DensityBucket::Xhdpi,
+			DensityBucket::Xxhdpi,
+			DensityBucket::Xxxhdpi,
+		] {
+			assert_eq!(bucket, DensityBucket::from(bucket.dpi()));
+		}
+		assert_eq!("tvdpi", DensityBucket::Tv.to_string());
+		assert_eq!("xxhdpi", DensityBucket::from(420).to_string());
+	}
+
+	#[test]
+	fn test_reboot_type_display() {
+		assert_eq!("bootloader", RebootType::Bootloader.to_string());
+		assert_eq!("recovery", RebootType::Recovery.to_string());
+		assert_eq!("sideload", RebootType::Sideload.to_string());
+		assert_eq!("sideload-auto-reboot", RebootType::SideloadAutoReboot.to_string());
+		assert_eq!("dra", RebootType::Dra.to_string());
+		assert_eq!("fastboot", RebootType::Fastboot.to_string());
+		assert_eq!("edl", RebootType::Edl.to_string());
+	}
+}
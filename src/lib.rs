@@ -15,8 +15,10 @@ pub(crate) mod adb;
 pub(crate) mod am;
 pub(crate) mod client;
 pub(crate) mod connection_type;
+pub(crate) mod content;
 pub(crate) mod dump_util;
 pub(crate) mod impls;
 pub(crate) mod pm;
 pub(crate) mod shell;
+pub(crate) mod sync;
 pub(crate) mod test;
@@ -1,20 +1,25 @@
+use std::collections::HashMap;
 use std::ffi::OsString;
+use std::path::Path;
 use std::time::Duration;
 
 use lazy_static::lazy_static;
 use regex::Regex;
 use rustix::path::Arg;
+use simple_cmd::prelude::OutputExt;
 
+use crate::client::make_temp_path;
 use crate::dump_util::{package_flags, runtime_permissions};
 use crate::error::Error;
 use crate::result::Result;
-use crate::shell::handle_result;
+use crate::shell::{handle_result, shell_quote};
 use crate::types::{
-	InstallOptions, InstallPermission, ListPackageDisplayOptions, ListPackageFilter, Package, PackageFlags, PackageManager,
-	RuntimePermission, SimplePackageReader, UninstallOptions,
+	InstallOptions, InstallPermission, ListPackageDisplayOptions, ListPackageFilter, MemInfo, Package, PackageFlags,
+	PackageManager, PackageSize, RuntimePermission, SimplePackageReader, UninstallOptions, User,
 };
 
 static DUMP_TIMEOUT: Option<Duration> = Some(Duration::from_secs(1));
+static STAT_BATCH_SIZE: usize = 64;
 
 macro_rules! build_pm_operation {
 	($name:tt, $operation_name:tt, $typ:ty, $typ2:ty) => {
@@ -24,6 +29,125 @@ macro_rules! build_pm_operation {
 	};
 }
 
+lazy_static! {
+	static ref RE_INSTALL_SESSION: Regex = Regex::new("Success: created install session \\[(\\d+)]").unwrap();
+	static ref RE_USER_INFO: Regex = Regex::new(r"UserInfo\{(\d+):(.*):([0-9a-fA-F]+)}(\s+running)?").unwrap();
+	static ref RE_CREATED_USER: Regex = Regex::new(r"Success: created user id (\d+)").unwrap();
+}
+
+/// Parse the session id printed by `pm install-create`
+pub(crate) fn parse_install_session(output: &str) -> Option<u32> {
+	RE_INSTALL_SESSION.captures(output)?.get(1)?.as_str().parse::<u32>().ok()
+}
+
+/// Parse the output of `pm list users`, e.g. `UserInfo{0:Owner:c13} running`
+pub(crate) fn parse_users(output: &str) -> Vec<User> {
+	output
+		.lines()
+		.filter_map(|line| {
+			let captures = RE_USER_INFO.captures(line)?;
+			Some(User {
+				id: captures.get(1)?.as_str().parse().ok()?,
+				name: captures.get(2)?.as_str().to_string(),
+				flags: u32::from_str_radix(captures.get(3)?.as_str(), 16).ok()?,
+				running: captures.get(4).is_some(),
+			})
+		})
+		.collect()
+}
+
+/// Parse the user id printed by `pm create-user`
+pub(crate) fn parse_created_user(output: &str) -> Option<u32> {
+	RE_CREATED_USER.captures(output)?.get(1)?.as_str().parse::<u32>().ok()
+}
+
+/// Parse the output of `pm get-app-links` into a domain to verification state map.
+/// Android 12+ prints a `Domain verification state:` section with a `domain: state` line for each
+/// domain, while the previous versions print a `Domains:` line followed by a `Status:` line shared
+/// by all the domains.
+pub(crate) fn parse_app_links(output: &str) -> HashMap<String, String> {
+	let mut result = HashMap::new();
+	let mut in_state_section = false;
+	let mut legacy_domains: Vec<&str> = vec![];
+
+	for line in output.lines() {
+		let line = line.trim();
+		if line == "Domain verification state:" {
+			in_state_section = true;
+		} else if in_state_section {
+			match line.rsplit_once(": ") {
+				Some((domain, state)) if !domain.contains(' ') => {
+					result.insert(domain.to_string(), state.trim().to_string());
+				}
+				_ => in_state_section = false,
+			}
+		} else if let Some(domains) = line.strip_prefix("Domains:") {
+			legacy_domains = domains.split_whitespace().collect();
+		} else if let Some(status) = line.strip_prefix("Status:") {
+			let status = status.split(':').next().unwrap_or_default().trim();
+			for domain in legacy_domains.drain(..) {
+				result.insert(domain.to_string(), status.to_string());
+			}
+		}
+	}
+	result
+}
+
+/// Returns the first number following the given label in a `dumpsys meminfo` output
+fn parse_meminfo_value(output: &str, label: &str) -> Option<u64> {
+	output.lines().find_map(|line| {
+		line.trim_start()
+			.strip_prefix(label)?
+			.split_whitespace()
+			.next()?
+			.parse()
+			.ok()
+	})
+}
+
+/// Parse the output of `dumpsys meminfo <package>`.
+/// Since API 26 the totals are reported as `TOTAL PSS:` in the App Summary, while older releases
+/// report them as `TOTAL:` or, without an App Summary, as the first column of the `TOTAL` row.
+/// The detail values are read from the App Summary, falling back to the `Pss Total` column of the
+/// main table, when available.
+pub(crate) fn parse_meminfo(output: &str) -> Option<MemInfo> {
+	let value = |label: &str, fallback: &str| {
+		parse_meminfo_value(output, label)
+			.or_else(|| parse_meminfo_value(output, fallback))
+			.unwrap_or(0)
+	};
+
+	Some(MemInfo {
+		total_pss: parse_meminfo_value(output, "TOTAL PSS:")
+			.or_else(|| parse_meminfo_value(output, "TOTAL:"))
+			.or_else(|| parse_meminfo_value(output, "TOTAL "))?,
+		java_heap: value("Java Heap:", "Dalvik Heap "),
+		native_heap: value("Native Heap:", "Native Heap "),
+		code: parse_meminfo_value(output, "Code:").unwrap_or(0),
+		stack: value("Stack:", "Stack "),
+		graphics: parse_meminfo_value(output, "Graphics:").unwrap_or(0),
+	})
+}
+
+/// Parse the output of `du -s -k`, returning the size in bytes
+pub(crate) fn parse_du_size(output: &str) -> Option<u64> {
+	output
+		.lines()
+		.next()
+		.and_then(|line| line.split_whitespace().next())
+		.and_then(|size| size.parse::<u64>().ok())
+		.map(|size| size * 1024)
+}
+
+/// Parse the output of `stat -c '%s %n'`, returning the size in bytes of each file
+pub(crate) fn parse_stat_sizes(output: &str) -> HashMap<String, u64> {
+	output
+		.lines()
+		.filter_map(|line| line.trim().split_once(' '))
+		.filter_map(|(size, file)| Some((file.to_string(), size.parse::<u64>().ok()?)))
+		.collect()
+}
+
 impl<'a> PackageManager<'a> {
 	/// Return the path of a given package name
 	pub fn path(&self, package_name: &str, user: Option<&str>) -> Result<String> {
@@ -74,6 +198,59 @@ impl<'a> PackageManager<'a> {
 		handle_result(self.parent.exec(args, None, None)?)
 	}
 
+	/// List the users of the device
+	pub fn list_users(&self) -> Result<Vec<User>> {
+		let output = self.parent.exec(
+			vec![
+				"pm", "list", "users",
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_users(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Create a new user with the given name, returning its id
+	pub fn create_user(&self, name: &str) -> Result<u32> {
+		let name = shell_quote(name);
+		let output = self.parent.exec(
+			vec![
+				"pm",
+				"create-user",
+				name.as_str(),
+			],
+			None,
+			None,
+		)?;
+		match parse_created_user(Arg::as_str(&output.stdout)?) {
+			Some(id) => Ok(id),
+			None if output.error() => Err(output.into()),
+			None => Err(Error::ParseInputError),
+		}
+	}
+
+	/// Remove the user with the given id, together with its data
+	pub fn remove_user(&self, id: u32) -> Result<()> {
+		let id = id.to_string();
+		let output = self.parent.exec(
+			vec![
+				"pm",
+				"remove-user",
+				id.as_str(),
+			],
+			None,
+			None,
+		)?;
+		if output.error() || !Arg::as_str(&output.stdout)?.contains("Success") {
+			Err(output.into())
+		} else {
+			Ok(())
+		}
+	}
+
 	/// Revert all runtime permissions to their default state
 	pub fn reset_permissions(&self) -> Result<()> {
 		handle_result(self.parent.exec(
@@ -147,6 +324,7 @@ impl<'a> PackageManager<'a> {
 						file_name,
 						version_code,
 						uid,
+						apk_size: None,
 					})
 				} else {
 					None
@@ -156,6 +334,42 @@ impl<'a> PackageManager<'a> {
 		Ok(result)
 	}
 
+	/// Same as [`PackageManager::list_packages`], but the apk file is always requested and its size
+	/// is stored in [`Package::apk_size`]. The apk files are stat'ed on the device in batches.
+	pub fn list_packages_with_apk_size(
+		&self,
+		filters: ListPackageFilter,
+		display: ListPackageDisplayOptions,
+		name_filter: Option<&str>,
+	) -> Result<Vec<Package>> {
+		let display = ListPackageDisplayOptions {
+			show_apk_file: true,
+			..display
+		};
+		let mut packages = self.list_packages(filters, display, name_filter)?;
+		let files = packages.iter().filter_map(|p| p.file_name.as_deref()).collect::<Vec<_>>();
+		let sizes = self.file_sizes(&files)?;
+		for package in packages.iter_mut() {
+			package.apk_size = package.file_name.as_ref().and_then(|file| sizes.get(file).copied());
+		}
+		Ok(packages)
+	}
+
+	/// Returns the size in bytes of the given files. Files which cannot be read are omitted
+	fn file_sizes(&self, files: &[&str]) -> Result<HashMap<String, u64>> {
+		let mut result = HashMap::new();
+		for chunk in files.chunks(STAT_BATCH_SIZE) {
+			let mut args = vec![
+				"stat", "-c", "'%s %n'",
+			];
+			args.extend(chunk);
+			// stat fails if any of the files is missing, but still prints the others
+			let output = self.parent.exec(args, None, None)?;
+			result.extend(parse_stat_sizes(Arg::as_str(&output.stdout)?));
+		}
+		Ok(result)
+	}
+
 	/// dump a package
 	pub fn dump(&self, package_name: &str, timeout: Option<Duration>) -> Result<String> {
 		let args = vec![
@@ -163,8 +377,61 @@ impl<'a> PackageManager<'a> {
 			"dump",
 			package_name.into(),
 		];
-		let result = self.parent.exec(args, None, timeout)?.stdout;
-		Ok(Arg::as_str(&result)?.to_string())
+		let output = self.parent.exec(args, None, timeout)?;
+		Ok(Arg::as_str(&output.stdout)?.to_string())
+	}
+
+	/// Returns the memory usage of the given package, which must be running
+	pub fn meminfo(&self, package_name: &str) -> Result<MemInfo> {
+		let output = self.parent.exec(
+			vec![
+				"dumpsys",
+				"meminfo",
+				package_name,
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		let output = Arg::as_str(&output.stdout)?;
+		if output.contains("No process found for") {
+			return Err(Error::PackageNotFoundError(package_name.to_string()));
+		}
+		parse_meminfo(output).ok_or(Error::ParseInputError)
+	}
+
+	/// Re-run the app links verification of the given package. Requires Android 12+
+	pub fn verify_app_links(&self, package_name: &str) -> Result<()> {
+		let output = self.parent.exec(
+			vec![
+				"pm",
+				"verify-app-links",
+				"--re-verify",
+				package_name,
+			],
+			None,
+			None,
+		)?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+
+	/// Returns the verification state of each of the app links domains of the given package
+	pub fn app_link_state(&self, package_name: &str) -> Result<HashMap<String, String>> {
+		let output = self.parent.exec(
+			vec![
+				"pm",
+				"get-app-links",
+				package_name,
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_app_links(Arg::as_str(&output.stdout)?))
 	}
 
 	/// get requested runtime permissions for package
@@ -187,6 +454,61 @@ impl<'a> PackageManager<'a> {
 		SimplePackageReader::new(dump.as_str(), sdk_int).and_then(|pr| Ok(pr.requested_permissions().unwrap_or(vec![])))
 	}
 
+	/// Returns the sdk level targeted by the package, if found in its dump
+	pub fn target_sdk(&self, package_name: &str) -> Result<Option<u16>> {
+		let dump = self.dump(package_name, DUMP_TIMEOUT)?;
+		let sdk_int = self.parent.build_version_sdk()?;
+		Ok(SimplePackageReader::new(dump.as_str(), sdk_int)?.target_sdk())
+	}
+
+	/// Returns the disk usage of a package.
+	/// Reading the data and cache directories requires root: without it only `app_bytes`
+	/// is returned, while `data_bytes` and `cache_bytes` are left empty.
+	pub fn package_size(&self, package_name: &str) -> Result<PackageSize> {
+		let dump = self.dump(package_name, DUMP_TIMEOUT)?;
+		let sdk_int = self.parent.build_version_sdk()?;
+		let reader = SimplePackageReader::new(dump.as_str(), sdk_int)?;
+
+		let code_path = match reader.get_code_path() {
+			Some(code_path) => code_path.to_string(),
+			None => {
+				let path = self.path(package_name, None)?;
+				path.rsplit_once('/').map(|s| s.0.to_string()).unwrap_or(path)
+			}
+		};
+
+		let app_bytes = self.disk_usage(code_path.as_str())?.ok_or(Error::PackageNotFoundError(package_name.to_string()))?;
+		let (data_bytes, cache_bytes) = match reader.get_data_dir() {
+			Some(data_dir) => (
+				self.disk_usage(data_dir)?,
+				self.disk_usage(format!("{data_dir}/cache").as_str())?,
+			),
+			None => (None, None),
+		};
+
+		Ok(PackageSize {
+			app_bytes,
+			data_bytes,
+			cache_bytes,
+		})
+	}
+
+	/// Returns the size in bytes of the given path, or None if it cannot be read
+	fn disk_usage(&self, path: &str) -> Result<Option<u64>> {
+		let output = self.parent.exec(
+			vec![
+				"du", "-s", "-k", path,
+			],
+			None,
+			None,
+		)?;
+		if output.success() {
+			Ok(parse_du_size(Arg::as_str(&output.stdout)?))
+		} else {
+			Ok(None)
+		}
+	}
+
 	pub fn package_flags(&self, package_name: &str) -> Result<Vec<PackageFlags>> {
 		let result = self.dump(package_name, DUMP_TIMEOUT)?;
 		package_flags(result.as_str())
@@ -242,6 +564,102 @@ impl<'a> PackageManager<'a> {
 		handle_result(self.parent.exec(args, None, None)?)
 	}
 
+	/// Push the local apk to a temporary file on the device and install it.
+	/// The pushed file is always removed afterwards; when the install fails its error is returned.
+	pub fn deploy(&self, local_apk: &Path, options: Option<InstallOptions>) -> Result<()> {
+		let remote = make_temp_path(".apk");
+		let result = self.push_and_install(local_apk, &remote, options);
+		let cleanup = self.parent.rm(remote.as_str(), vec!["-f"]);
+		result.and(cleanup)
+	}
+
+	/// Same as [`PackageManager::deploy`], failing with a not found [`Error::IoError`] before pushing
+	/// anything when the local apk doesn't exist
+	pub fn install_local<T: AsRef<Path>>(&self, local: T, options: Option<InstallOptions>) -> Result<()> {
+		let local = local.as_ref();
+		if !local.is_file() {
+			return Err(Error::IoError(std::io::Error::new(
+				std::io::ErrorKind::NotFound,
+				format!("{} not found", local.display()),
+			)));
+		}
+		self.deploy(local, options)
+	}
+
+	fn push_and_install(&self, local_apk: &Path, remote: &str, options: Option<InstallOptions>) -> Result<()> {
+		let output = self.parent.parent.push(local_apk, remote)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		self.install(remote, options)
+	}
+
+	/// Install a base apk together with its splits, using an install session.
+	/// The apks must be already on the device. If any of them fails to be written to the session,
+	/// the session is abandoned.
+	pub fn install_multiple<T: Arg>(&self, apks: &[T], options: Option<InstallOptions>) -> Result<()> {
+		let mut args: Vec<OsString> = vec![
+			"pm".into(),
+			"install-create".into(),
+		];
+		if let Some(options) = options {
+			args.extend(options);
+		}
+
+		let output = self.parent.exec(args, None, None)?;
+		let session = match parse_install_session(Arg::as_str(&output.stdout)?) {
+			Some(session) => session.to_string(),
+			None if output.error() => return Err(output.into()),
+			None => return Err(Error::ParseInputError),
+		};
+
+		for (index, apk) in apks.iter().enumerate() {
+			let result = apk.as_str().map_err(Error::from).and_then(|path| {
+				let name = format!("{index}_{}", path.rsplit('/').next().unwrap_or(path));
+				self.session_write(session.as_str(), name.as_str(), path)
+			});
+
+			if let Err(err) = result {
+				let _ = self.parent.exec(
+					vec![
+						"pm",
+						"install-abandon",
+						session.as_str(),
+					],
+					None,
+					None,
+				);
+				return Err(err);
+			}
+		}
+
+		let output = self.parent.exec(
+			vec![
+				"pm",
+				"install-commit",
+				session.as_str(),
+			],
+			None,
+			None,
+		)?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+
+	fn session_write(&self, session: &str, name: &str, path: &str) -> Result<()> {
+		let output = self.parent.exec(
+			vec![
+				"pm",
+				"install-write",
+				session,
+				name,
+				path,
+			],
+			None,
+			None,
+		)?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+
 	build_pm_operation!(clear, "clear", &str, Option<&str>);
 
 	build_pm_operation!(suspend, "suspend", &str, Option<&str>);
@@ -278,10 +696,21 @@ impl<'a> PackageManager<'a> {
 
 #[cfg(test)]
 mod test {
+	use std::path::PathBuf;
+	use std::str::FromStr;
+
 	use itertools::Itertools;
 
+	use crate::client::{TEMP_DIR, TEMP_PREFIX};
+	use crate::error::Error;
+	use crate::pm::{
+		parse_app_links, parse_created_user, parse_du_size, parse_install_session, parse_meminfo, parse_stat_sizes, parse_users,
+	};
 	use crate::test::test::*;
-	use crate::types::{InstallLocationOption, InstallOptions, ListPackageDisplayOptions, ListPackageFilter, SimplePackageReader};
+	use crate::types::{
+		Adb, Client, ConnectionType, InstallLocationOption, InstallOptions, ListPackageDisplayOptions, ListPackageFilter, MemInfo,
+		SimplePackageReader, User,
+	};
 
 	#[test]
 	fn test_path() {
@@ -433,6 +862,51 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn test_parse_stat_sizes() {
+		let sizes = parse_stat_sizes(
+			"2204544 /data/app/~~Zx3w==/com.example-Ab1==/base.apk\n1048576 /system/app/Foo/Foo.apk\nstat: '/missing.apk': No such file or directory\n",
+		);
+		assert_eq!(2, sizes.len());
+		assert_eq!(Some(&2204544), sizes.get("/data/app/~~Zx3w==/com.example-Ab1==/base.apk"));
+		assert_eq!(Some(&1048576), sizes.get("/system/app/Foo/Foo.apk"));
+		assert!(parse_stat_sizes("").is_empty());
+	}
+
+	#[test]
+	fn test_list_packages_with_apk_size() {
+		init_log();
+		let client = connect_emulator();
+		let filters = ListPackageFilter {
+			show_only_disabled: false,
+			show_only_enabed: false,
+			show_only_system: true,
+			show_only3rd_party: false,
+			apex_only: false,
+			uid: None,
+			user: None,
+		};
+		let display = ListPackageDisplayOptions {
+			show_uid: false,
+			show_version_code: false,
+			include_uninstalled: false,
+			show_apk_file: true,
+		};
+
+		let shell = client.shell();
+		let pm = shell.pm();
+		let packages = pm.list_packages(filters.clone(), display.clone(), None).expect("failed to list packages");
+		assert!(!packages.is_empty());
+		assert!(packages.iter().all(|p| p.apk_size.is_none()));
+
+		let packages = pm
+			.list_packages_with_apk_size(filters, display, None)
+			.expect("failed to list packages");
+		assert!(!packages.is_empty());
+		assert!(packages.iter().any(|p| p.apk_size.unwrap_or(0) > 0));
+		assert!(packages.iter().filter(|p| p.file_name.is_none()).all(|p| p.apk_size.is_none()));
+	}
+
 	#[test]
 	fn test_dump() {
 		init_log();
@@ -496,6 +970,15 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn test_target_sdk() {
+		init_log();
+		let client = connect_emulator();
+		let target_sdk = client.shell().pm().target_sdk("com.android.settings").expect("failed to get target sdk");
+		println!("target sdk: {target_sdk:?}");
+		assert!(target_sdk.is_some());
+	}
+
 	#[test]
 	fn test_requested_permissions() {
 		init_log();
@@ -527,6 +1010,298 @@ mod test {
 		println!("package flags: {}", flags.iter().map(|p| format!("{}", p)).join(","));
 	}
 
+	#[test]
+	fn test_parse_app_links() {
+		let output = "  com.example.app:
+    ID: 01234567-89ab-cdef-0123-456789abcdef
+    Signatures: [AA:BB:CC]
+    Domain verification state:
+      example.com: verified
+      www.example.com: 1024
+";
+		let links = parse_app_links(output);
+		assert_eq!(2, links.len());
+		assert_eq!(Some(&"verified".to_string()), links.get("example.com"));
+		assert_eq!(Some(&"1024".to_string()), links.get("www.example.com"));
+
+		let output = "App verification status:
+
+  Package: com.example.app
+  Domains: example.com www.example.com
+  Status:  always : 200000002
+";
+		let links = parse_app_links(output);
+		assert_eq!(2, links.len());
+		assert_eq!(Some(&"always".to_string()), links.get("example.com"));
+		assert_eq!(Some(&"always".to_string()), links.get("www.example.com"));
+
+		assert!(parse_app_links("").is_empty());
+	}
+
+	#[test]
+	fn test_app_links() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let pm = shell.pm();
+		pm.verify_app_links("com.android.chrome").expect("failed to verify app links");
+		let links = pm.app_link_state("com.android.chrome").expect("failed to get app links");
+		println!("app links: {links:?}");
+	}
+
+	#[test]
+	fn test_parse_users() {
+		let users = parse_users("Users:\n\tUserInfo{0:Owner:c13} running\n\tUserInfo{10:Work profile: test:1030}\n");
+		assert_eq!(
+			vec![
+				User {
+					id: 0,
+					name: "Owner".to_string(),
+					running: true,
+					flags: 0xc13,
+				},
+				User {
+					id: 10,
+					name: "Work profile: test".to_string(),
+					running: false,
+					flags: 0x1030,
+				},
+			],
+			users
+		);
+		assert!(parse_users("").is_empty());
+
+		assert_eq!(Some(11), parse_created_user("Success: created user id 11\n"));
+		assert_eq!(None, parse_created_user("Error: couldn't create User."));
+	}
+
+	#[test]
+	fn test_users() {
+		init_log();
+		let client = connect_emulator();
+		root_client(&client);
+		let shell = client.shell();
+		let pm = shell.pm();
+
+		let users = pm.list_users().expect("failed to list users");
+		assert!(users.iter().any(|user| user.id == 0 && user.running));
+
+		let id = pm.create_user("radb test").expect("failed to create user");
+		let users = pm.list_users().expect("failed to list users");
+		assert!(users.iter().any(|user| user.id == id && user.name == "radb test"));
+
+		pm.remove_user(id).expect("failed to remove user");
+		let users = pm.list_users().expect("failed to list users");
+		assert!(users.iter().all(|user| user.id != id));
+	}
+
+	#[test]
+	fn test_parse_install_session() {
+		assert_eq!(Some(1234567), parse_install_session("Success: created install session [1234567]\n"));
+		assert_eq!(None, parse_install_session("Error: java.lang.IllegalArgumentException"));
+		assert_eq!(None, parse_install_session(""));
+	}
+
+	#[test]
+	fn test_install_multiple() {
+		init_log();
+		let client = connect_emulator();
+		let path = test_files_dir().join("app-debug.apk");
+		let target_dir = "/data/local/tmp";
+		let target_file = format!("{target_dir}/app-debug.apk");
+		let package_name = "it.sephiroth.android.app.app";
+
+		let _ = client.push(path, target_dir).expect("failed to push file");
+		client
+			.shell()
+			.pm()
+			.install_multiple(&[target_file.as_str()], None)
+			.expect("failed to install package");
+		assert!(
+			client
+				.shell()
+				.pm()
+				.is_installed(package_name, None)
+				.expect("failed to check if package is installed")
+		);
+
+		let result = client
+			.shell()
+			.pm()
+			.install_multiple(&[target_file.as_str(), "/data/local/tmp/missing.apk"], None);
+		assert!(result.is_err());
+	}
+
+	#[test]
+	fn test_install_local_missing_file() {
+		let client = Client::new(
+			Adb::from(PathBuf::from("true")),
+			ConnectionType::from_str("127.0.0.1:5555").unwrap(),
+			false,
+		);
+		let shell = client.shell();
+		let err = shell
+			.pm()
+			.install_local(std::env::temp_dir().join("radb_missing.apk"), None)
+			.expect_err("missing apk should fail");
+		assert!(matches!(err, Error::IoError(err) if err.kind() == std::io::ErrorKind::NotFound));
+	}
+
+	#[test]
+	fn test_install_local() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		shell
+			.pm()
+			.install_local(test_files_dir().join("app-debug.apk"), None)
+			.expect("failed to install local apk");
+		assert!(shell.pm().is_installed("it.sephiroth.android.app.app", None).unwrap());
+	}
+
+	#[test]
+	fn test_deploy() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let pm = shell.pm();
+		let package_name = "it.sephiroth.android.app.app";
+		let deployed_files = || {
+			shell
+				.ls(TEMP_DIR, None)
+				.expect("failed to list files")
+				.into_iter()
+				.filter(|name| name.contains(TEMP_PREFIX))
+				.count()
+		};
+
+		pm.deploy(&test_files_dir().join("app-debug.apk"), None)
+			.expect("failed to deploy package");
+		assert!(pm.is_installed(package_name, None).expect("failed to check if package is installed"));
+		assert_eq!(0, deployed_files());
+
+		let invalid_apk = std::env::temp_dir().join("radb_invalid.apk");
+		std::fs::write(&invalid_apk, b"not an apk").unwrap();
+		assert!(pm.deploy(&invalid_apk, None).is_err());
+		assert_eq!(0, deployed_files());
+		std::fs::remove_file(invalid_apk).unwrap();
+	}
+
+	#[test]
+	fn test_parse_meminfo() {
+		let output = "Applications Memory Usage (in Kilobytes):
+Uptime: 123456 Realtime: 123456
+
+** MEMINFO in pid 4321 [com.android.settings] **
+                   Pss  Private  Private  SwapPss      Rss     Heap     Heap     Heap
+                 Total    Dirty    Clean    Dirty    Total     Size    Alloc     Free
+                ------   ------   ------   ------   ------   ------   ------   ------
+  Native Heap     5432     5400        0        0     7000    12000     9000     3000
+  Dalvik Heap     3210     3200        0        0     6000     8000     4000     4000
+        Stack      345      345        0        0      350
+        TOTAL    34567    20000     8000        0    90000    20000    13000     7000
+
+ App Summary
+                       Pss(KB)                        Rss(KB)
+                        ------                         ------
+           Java Heap:     4567                          12345
+         Native Heap:     5400                           7000
+                Code:     8901                          30000
+               Stack:      345                            350
+            Graphics:     1234                           1234
+       Private Other:      567
+              System:     9876
+             Unknown:                                    1000
+
+           TOTAL PSS:    34567            TOTAL RSS:    90000       TOTAL SWAP PSS:        0
+";
+		assert_eq!(
+			Some(MemInfo {
+				total_pss: 34567,
+				java_heap: 4567,
+				native_heap: 5400,
+				code: 8901,
+				stack: 345,
+				graphics: 1234,
+			}),
+			parse_meminfo(output)
+		);
+
+		// api < 26
+		let output = " App Summary
+                       Pss(KB)
+                        ------
+           Java Heap:     4000
+         Native Heap:     5000
+                Code:     6000
+               Stack:      300
+            Graphics:     1000
+       Private Other:      500
+              System:     7000
+
+               TOTAL:    23800      TOTAL SWAP PSS:        0
+";
+		let meminfo = parse_meminfo(output).unwrap();
+		assert_eq!(23800, meminfo.total_pss);
+		assert_eq!(4000, meminfo.java_heap);
+		assert_eq!(1000, meminfo.graphics);
+
+		// no app summary
+		let output = "                 Shared  Private     Heap     Heap     Heap
+                   Pss    Dirty    Dirty     Size    Alloc     Free
+                ------   ------   ------   ------   ------   ------
+  Native Heap     2000     1000     1900     4000     3000     1000
+  Dalvik Heap     3000     2000     2900     8000     6000     2000
+        TOTAL     9000     5000     8000    12000     9000     3000
+";
+		let meminfo = parse_meminfo(output).unwrap();
+		assert_eq!(9000, meminfo.total_pss);
+		assert_eq!(3000, meminfo.java_heap);
+		assert_eq!(2000, meminfo.native_heap);
+		assert_eq!(0, meminfo.code);
+
+		assert_eq!(None, parse_meminfo("No process found for: com.example\n"));
+	}
+
+	#[test]
+	fn test_meminfo() {
+		init_log();
+		let client = connect_emulator();
+		let meminfo = client
+			.shell()
+			.pm()
+			.meminfo("com.android.systemui")
+			.expect("failed to get meminfo");
+		println!("meminfo: {meminfo:?}");
+		assert!(meminfo.total_pss > 0);
+	}
+
+	#[test]
+	fn test_parse_du_size() {
+		assert_eq!(Some(2048), parse_du_size("2\t/data/app/com.example-1\n"));
+		assert_eq!(Some(0), parse_du_size("0\t/data/data/com.example/cache"));
+		assert_eq!(None, parse_du_size(""));
+		assert_eq!(None, parse_du_size("du: /data/data/com.example: Permission denied"));
+	}
+
+	#[test]
+	fn test_package_size() {
+		init_log();
+		let client = connect_emulator();
+		let size = client
+			.shell()
+			.pm()
+			.package_size("com.android.bluetooth")
+			.expect("failed to get package size");
+		println!("package size: {size:?}");
+		assert!(size.app_bytes > 0);
+
+		if client.shell().is_root().unwrap() {
+			assert!(size.data_bytes.is_some());
+			assert!(size.cache_bytes.is_some());
+		}
+	}
+
 	#[test]
 	fn test_is_system() {
 		init_log();
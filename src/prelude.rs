@@ -1,42 +1,112 @@
-use simple_cmd::CommandBuilder;
+use std::ffi::{OsStr, OsString};
+use std::process::Stdio;
+use std::time::Duration;
 
-use crate::types::{Adb, Client, ConnectionType};
+use crossbeam_channel::Receiver;
+use simple_cmd::{Cmd, CommandBuilder};
 
-pub(crate) trait CommandBuilderExt {
-	#[allow(dead_code)]
-	fn client<C>(self, client: C) -> Self
-	where
-		C: Into<Client>;
+use crate::types::{Adb, Client, CommandObserver, ConnectionType};
 
-	fn addr<C>(self, addr: C) -> Self
-	where
-		C: Into<ConnectionType>;
+/// A [`CommandBuilder`] running adb, which keeps track of the arguments so that the command observer
+/// of the [`Adb`] (see [`Adb::set_command_observer`]) can be invoked when the command is built
+pub(crate) struct AdbCommandBuilder {
+	builder: CommandBuilder,
+	args: Vec<OsString>,
+	observer: Option<CommandObserver>,
+}
+
+impl AdbCommandBuilder {
+	pub(crate) fn new(adb: &Adb) -> AdbCommandBuilder {
+		AdbCommandBuilder {
+			builder: CommandBuilder::new(adb),
+			args: vec![],
+			observer: adb.command_observer(),
+		}
+	}
 
-	fn shell(client: &Client) -> CommandBuilder;
+	pub(crate) fn shell(client: &Client) -> AdbCommandBuilder {
+		AdbCommandBuilder::from(client).arg("shell")
+	}
 
-	fn adb(adb: &Adb) -> CommandBuilder;
-}
+	pub(crate) fn addr<C>(self, addr: C) -> Self
+	where
+		C: Into<ConnectionType>,
+	{
+		self.args(addr.into())
+	}
 
-impl CommandBuilderExt for CommandBuilder {
-	fn client<C>(self, client: C) -> Self
+	pub(crate) fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
+		self.args.push(arg.as_ref().to_os_string());
+		self.builder = self.builder.arg(arg);
+		self
+	}
+
+	pub(crate) fn args<I, S>(mut self, args: I) -> Self
 	where
-		C: Into<Client>,
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
 	{
-		self.args(client.into().addr)
+		for arg in args {
+			self = self.arg(arg);
+		}
+		self
 	}
 
-	fn addr<C>(self, addr: C) -> Self
+	pub(crate) fn with_args<I, S>(self, args: I) -> Self
 	where
-		C: Into<ConnectionType>,
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
 	{
-		self.args(addr.into())
+		self.args(args)
 	}
 
-	fn shell(client: &Client) -> CommandBuilder {
-		CommandBuilder::from(client).arg("shell")
+	pub(crate) fn with_debug(mut self, debug: bool) -> Self {
+		self.builder = self.builder.with_debug(debug);
+		self
 	}
 
-	fn adb(adb: &Adb) -> CommandBuilder {
-		CommandBuilder::new(adb)
+	pub(crate) fn timeout(mut self, timeout: Option<Duration>) -> Self {
+		self.builder = self.builder.timeout(timeout);
+		self
+	}
+
+	pub(crate) fn with_timeout(self, timeout: Duration) -> Self {
+		self.timeout(Some(timeout))
+	}
+
+	pub(crate) fn signal(mut self, signal: Option<Receiver<()>>) -> Self {
+		self.builder = self.builder.signal(signal);
+		self
+	}
+
+	pub(crate) fn with_signal(self, signal: Receiver<()>) -> Self {
+		self.signal(Some(signal))
+	}
+
+	pub(crate) fn stdout<T: Into<Stdio>>(mut self, cfg: Option<T>) -> Self {
+		self.builder = self.builder.stdout(cfg);
+		self
+	}
+
+	pub(crate) fn stderr<T: Into<Stdio>>(mut self, cfg: Option<T>) -> Self {
+		self.builder = self.builder.stderr(cfg);
+		self
+	}
+
+	/// Build the command, invoking the command observer (if any) with its arguments
+	pub(crate) fn build(self) -> Cmd {
+		if let Some(observer) = self.observer {
+			observer(&self.args);
+		}
+		self.builder.build()
+	}
+}
+
+impl From<&Client> for AdbCommandBuilder {
+	fn from(value: &Client) -> Self {
+		AdbCommandBuilder::new(&value.adb)
+			.addr(value.addr)
+			.with_debug(value.debug)
+			.timeout(value.config.default_command_timeout)
 	}
 }
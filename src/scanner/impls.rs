@@ -1,19 +1,31 @@
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::net::{Ipv4Addr, SocketAddr, TcpStream};
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use crate::error::Error;
+use crate::result::Result;
 use crate::scanner::{ClientResult, Scanner};
 use crate::types::{Adb, AdbDevice, Client, ConnectionType};
-use cidr_utils::cidr::InetIterator;
+use cidr_utils::cidr::{InetIterator, Ipv4Cidr, Ipv4Inet};
 use crossbeam_channel::Sender;
 use itertools::Either;
 use tracing::{debug, info, trace, warn};
 
 static TCP_TIMEOUT_MS: u64 = 200;
 static ADB_TIMEOUT_MS: u64 = 100;
+static ADB_PORT: u16 = 5555;
+
+/// Parse an ipv4 network such as `192.168.1.0/24`. Host bits are ignored (`192.168.1.12/24` is the same network),
+/// while a bare address is a single host.
+pub(crate) fn parse_cidr(cidr: &str) -> Result<Ipv4Cidr> {
+	Ipv4Inet::from_str(cidr.trim())
+		.map(|inet| inet.network())
+		.map_err(|_| Error::ParseInputError)
+}
 
 impl Display for ClientResult {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -61,6 +73,8 @@ impl Scanner {
 			tcp_timeout,
 			adb_timeout,
 			debug,
+			workers: None,
+			port: ADB_PORT,
 		}
 	}
 
@@ -79,36 +93,67 @@ impl Scanner {
 		self
 	}
 
+	/// Set the number of hosts probed concurrently. Defaults to the available parallelism.
+	/// Since the probes mostly wait on the network, a value well above the number of cpus
+	/// (e.g. 64) makes the scan of a /24 considerably faster.
+	pub fn with_workers(mut self, workers: usize) -> Self {
+		self.workers = Some(workers.max(1));
+		self
+	}
+
+	/// Set the port probed on each host. Defaults to 5555
+	pub fn with_port(mut self, port: u16) -> Self {
+		self.port = port;
+		self
+	}
+
+	/// The number of hosts probed concurrently
+	pub fn workers(&self) -> usize {
+		self.workers.unwrap_or_else(|| {
+			std::thread::available_parallelism()
+				.map(|s| s.get())
+				.unwrap_or(num_cpus::get())
+		})
+	}
+
 	pub fn scan<I>(&self, adb: &Adb, iterator: I, tx: Sender<Either<String, ClientResult>>)
 	where
 		I: Into<InetIterator<Ipv4Addr>>,
 	{
 		let adb = Arc::new(adb.clone());
-		let cpus = std::thread::available_parallelism()
-			.map(|s| s.get())
-			.unwrap_or(num_cpus::get());
+		let workers = self.workers();
 
-		let tp = threadpool::ThreadPool::new(cpus);
+		let tp = threadpool::ThreadPool::new(workers);
 		let tcp_timeout = self.tcp_timeout.clone();
 		let adb_timeout = self.adb_timeout.clone();
 		let debug = self.debug;
+		let port = self.port;
 
 		if debug {
 			debug!("scanning");
 			trace!("adb: {:#}", adb);
-			trace!("num cpus: {}", cpus);
+			trace!("workers: {}", workers);
 			trace!("tcp timeout: {:?}", tcp_timeout);
 			trace!("adb timeout: {:?}", adb_timeout);
 		}
 
+		let found = Arc::new(Mutex::new(HashSet::new()));
+
 		for ip in iterator.into() {
 			let adb = Arc::clone(&adb);
+			let found = Arc::clone(&found);
 			let tx = tx.clone();
 
 			tp.execute(move || {
-				let addr = format!("{}:5555", ip.address());
+				let addr = format!("{}:{}", ip.address(), port);
 				let _ = tx.send(Either::Left(addr.clone()));
 				if let Some(result) = connect(adb, &addr, tcp_timeout, adb_timeout, debug) {
+					if !found.lock().unwrap().insert(result.conn.normalized()) {
+						if debug {
+							trace!("[{:}] already found", result.conn);
+						}
+						return;
+					}
 					if debug {
 						info!("Found device: {:?}", result.device);
 					}
@@ -120,6 +165,23 @@ impl Scanner {
 	}
 }
 
+impl Scanner {
+	/// Scan all the hosts of the given network, e.g. `192.168.1.0/24`.
+	/// Returns [`Error::ParseInputError`] if `cidr` is not a valid ipv4 network, before probing any host.
+	pub fn scan_cidr(&self, adb: &Adb, cidr: &str, tx: Sender<Either<String, ClientResult>>) -> Result<()> {
+		let cidr = parse_cidr(cidr)?;
+		self.scan(adb, cidr.iter(), tx);
+		Ok(())
+	}
+
+	/// Remove the duplicated results, keeping the first occurrence of each device.
+	/// Results are compared using their normalized connection.
+	pub fn dedup<I: IntoIterator<Item = ClientResult>>(results: I) -> Vec<ClientResult> {
+		let mut seen = HashSet::new();
+		results.into_iter().filter(|result| seen.insert(result.conn.normalized())).collect()
+	}
+}
+
 impl ClientResult {
 	pub fn new(addr: SocketAddr) -> ClientResult {
 		ClientResult {
@@ -136,7 +198,7 @@ impl ClientResult {
 impl TryFrom<&ClientResult> for Client {
 	type Error = Error;
 
-	fn try_from(value: &ClientResult) -> Result<Self, Self::Error> {
+	fn try_from(value: &ClientResult) -> std::result::Result<Self, Self::Error> {
 		Client::try_from(value.conn.clone())
 	}
 }
@@ -156,7 +218,15 @@ impl From<&AdbDevice> for ClientResult {
 
 impl PartialEq for ClientResult {
 	fn eq(&self, other: &Self) -> bool {
-		self.conn == other.conn
+		self.conn.normalized() == other.conn.normalized()
+	}
+}
+
+impl Eq for ClientResult {}
+
+impl Hash for ClientResult {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.conn.normalized().hash(state);
 	}
 }
 
@@ -250,9 +320,62 @@ pub(crate) mod test {
 	use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 	use itertools::Either;
 
-	use crate::scanner::Scanner;
+	use crate::error::Error;
+	use crate::scanner::impls::parse_cidr;
+	use crate::scanner::{ClientResult, Scanner};
 	use crate::test::test::init_log;
-	use crate::types::Adb;
+	use crate::types::{Adb, ConnectionType};
+
+	#[test]
+	fn test_dedup() {
+		let results = vec![
+			ClientResult::new("192.168.1.10:5555".parse().unwrap()),
+			ClientResult::new("127.0.0.1:5555".parse().unwrap()),
+			ClientResult::new("[::ffff:192.168.1.10]:5555".parse().unwrap()),
+			ClientResult::new("[::1]:5555".parse().unwrap()),
+			ClientResult::new("192.168.1.10:5556".parse().unwrap()),
+		];
+		assert_eq!(results[0], results[2]);
+		assert_eq!(results[1], results[3]);
+
+		let results = Scanner::dedup(results);
+		assert_eq!(3, results.len());
+		assert_eq!(ConnectionType::from_str("192.168.1.10:5555").unwrap(), results[0].conn);
+		assert_eq!(ConnectionType::from_str("127.0.0.1:5555").unwrap(), results[1].conn);
+		assert_eq!(ConnectionType::from_str("192.168.1.10:5556").unwrap(), results[2].conn);
+	}
+
+	#[test]
+	fn test_workers() {
+		assert!(Scanner::default().workers() >= 1);
+		assert_eq!(64, Scanner::default().with_workers(64).workers());
+		assert_eq!(1, Scanner::default().with_workers(0).workers());
+	}
+
+	#[test]
+	fn test_parse_cidr() {
+		let cidr = parse_cidr("192.168.1.0/24").unwrap();
+		assert_eq!(256, cidr.size());
+		assert_eq!("192.168.1.0", cidr.first_address().to_string());
+		assert_eq!("192.168.1.255", cidr.last_address().to_string());
+
+		assert_eq!(cidr, parse_cidr("192.168.1.42/24").unwrap());
+		assert_eq!(4, parse_cidr("10.0.0.0/30").unwrap().size());
+		assert_eq!(1, parse_cidr("10.0.0.1").unwrap().size());
+
+		assert!(matches!(parse_cidr("192.168.1.0/33"), Err(Error::ParseInputError)));
+		assert!(matches!(parse_cidr("192.168.1.256/24"), Err(Error::ParseInputError)));
+		assert!(matches!(parse_cidr("::1/128"), Err(Error::ParseInputError)));
+	}
+
+	#[test]
+	fn test_scan_cidr_invalid() {
+		let (tx, rx) = unbounded();
+		let adb = Adb::from(std::path::PathBuf::from("adb"));
+		let result = Scanner::default().scan_cidr(&adb, "not a network", tx);
+		assert!(matches!(result, Err(Error::ParseInputError)));
+		assert!(rx.try_recv().is_err());
+	}
 
 	#[test]
 	fn test_tcp_stream() {
@@ -286,7 +409,8 @@ pub(crate) mod test {
 		let scanner = Scanner::default()
 			.with_debug(true)
 			.with_tcp_timeout(Duration::from_millis(500))
-			.with_adb_timeout(Duration::from_millis(500));
+			.with_adb_timeout(Duration::from_millis(500))
+			.with_workers(64);
 
 		let start = Instant::now();
 		scanner.scan(&adb, cidr.iter(), tx.clone());
@@ -11,9 +11,12 @@ pub struct Scanner {
 	tcp_timeout: core::time::Duration,
 	adb_timeout: core::time::Duration,
 	debug: bool,
+	workers: Option<usize>,
+	port: u16,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg(feature = "scanner")]
 pub struct ClientResult {
 	pub conn: ConnectionType,
@@ -1,34 +1,60 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
 use std::process::{ExitStatus, Output};
 use std::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use cached::{Cached, SizedCache};
 use cmd_lib::AsOsStr;
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, select};
+use itertools::Itertools;
 use lazy_static::lazy_static;
+use mac_address::MacAddress;
 use regex::Regex;
 use rustix::path::Arg;
-use simple_cmd::CommandBuilder;
 use simple_cmd::debug::CommandDebug;
 use simple_cmd::prelude::OutputExt;
+use uuid::Uuid;
 
+use crate::client::{handle_exit_status, make_temp_path};
 use crate::error::Error;
 use crate::prelude::*;
 use crate::result::Result;
 use crate::traits::AsArg;
 use crate::types::{
-	ActivityManager, DumpsysPriority, FFPlayOptions, InputSource, KeyCode, KeyEventType, MotionEvent, PackageManager, PropType,
-	Property, SELinuxType, ScreenRecordOptions, SettingsType, Shell,
+	ActivityManager, ArpEntry, BatteryHealth, BatteryInfo, BatteryStatus, ContentProvider, CpuEntry, CpuRow, DensityBucket,
+	DeviceInfo, DirEntry, DiskUsage, DumpsysPriority, FFPlayOptions, InputSource, KeyCode, KeyEventType, LoadAverage, LsOptions,
+	MonkeyResult, MotionEvent, PackageManager, PluggedSource, ProcessInfo, PropSnapshot, PropType, PropValue, Property,
+	SELinuxType, ScreenRecordOptions, ServiceState, SettingsType, Shell, StayAwakeMode, ThreadInfo,
 };
 
+static THREADS_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+static CLOCK_TICKS_PER_SEC: f32 = 100.0;
+static SAMPLES_SEPARATOR: &str = "---";
+static BATCH_SEPARATOR_PREFIX: &str = "__RADB_SEP_";
+static COMMANDS_CACHE_SIZE: usize = 64;
+static DUMPSYS_TIMEOUT_GRACE: Duration = Duration::from_secs(2);
+static PROP_PERSIST_LOCALE: &str = "persist.sys.locale";
+static PROP_PRODUCT_LOCALE: &str = "ro.product.locale";
+static SCREENRECORD_FINALIZE_TIMEOUT: Duration = Duration::from_secs(5);
+static PROP_POLL_INTERVAL: Duration = Duration::from_millis(200);
+static SELINUX_ENFORCE_FILE: &str = "/sys/fs/selinux/enforce";
+static SETTING_SYSTEM_LOCALES: &str = "system_locales";
+static SYSTEM_LOCALES_MIN_SDK: u16 = 24;
+
 lazy_static! {
+	static ref RE_MONKEY_EVENTS: Regex = Regex::new("(?m)^\\s*Events injected: (\\d+)").unwrap();
 	static ref RE_GET_PROPS: Regex = Regex::new("(?m)^\\[(.*)\\]\\s*:\\s*\\[([^\\]]*)\\]$").unwrap();
-	static ref COMMANDS_CACHE: Mutex<SizedCache<String, Option<String>>> = Mutex::new(SizedCache::with_size(10));
+	static ref COMMANDS_CACHE: Mutex<SizedCache<String, Option<String>>> =
+		Mutex::new(SizedCache::with_size(COMMANDS_CACHE_SIZE));
+	static ref SCREENRECORD_AUDIO_CACHE: Mutex<SizedCache<String, Option<&'static str>>> = Mutex::new(SizedCache::with_size(10));
 }
 
+/// A process killed by its cancel signal is not an error. Timeouts never get here, since [`Shell::exec`]
+/// already returns them as [`Error::Timeout`]
 pub(crate) fn handle_result(result: Output) -> Result<()> {
 	if result.error() && !result.kill() && !result.interrupt() {
 		Err(result.into())
@@ -37,18 +63,504 @@ pub(crate) fn handle_result(result: Output) -> Result<()> {
 	}
 }
 
-fn make_keyevent_combination<I, S>(source: Option<InputSource>, keycodes: I) -> Vec<OsString>
-where
-	I: IntoIterator<Item = S>,
-	S: Into<KeyCode>,
-{
-	let mut args = vec!["input".into()];
+/// Converts a process killed by its `timeout` into [`Error::Timeout`], so that a slow device can be told
+/// apart from a failing command. A process killed before the timeout expired (eg. cancelled) is returned unchanged.
+pub(crate) fn handle_timeout(command: &str, started: Instant, timeout: Option<Duration>, output: Output) -> Result<Output> {
+	let elapsed = started.elapsed();
+	match timeout {
+		Some(timeout) if output.kill() && elapsed >= timeout => Err(Error::Timeout {
+			command: command.to_string(),
+			elapsed,
+		}),
+		_ => Ok(output),
+	}
+}
+
+/// Converts a dumpsys process killed by the timeout into [`Error::DumpsysTimeout`], keeping the partial stdout
+fn handle_dumpsys_result(service: Option<&str>, output: Output) -> Result<Output> {
+	if output.kill() {
+		Err(Error::DumpsysTimeout {
+			service: service.unwrap_or("all").to_string(),
+			partial: output.stdout,
+		})
+	} else {
+		Ok(output)
+	}
+}
+
+/// Parse the `PID NAME` lines printed by `ps`, returning the pids of the package processes,
+/// including its secondary processes (eg. `com.example:remote`)
+fn parse_package_pids(ps: &str, package: &str) -> Vec<i32> {
+	ps.lines()
+		.filter_map(|line| {
+			let mut split = line.split_whitespace();
+			let pid = split.next()?.parse::<i32>().ok()?;
+			let name = split.next()?;
+			match name.strip_prefix(package) {
+				Some(suffix) if suffix.is_empty() || suffix.starts_with(':') => Some(pid),
+				_ => None,
+			}
+		})
+		.collect()
+}
+
+/// Split the first `count` whitespace separated fields of the line, returning them with the remainder of the line
+fn split_fields(line: &str, count: usize) -> Option<(Vec<&str>, &str)> {
+	let mut fields = Vec::with_capacity(count);
+	let mut rest = line.trim_start();
+	for _ in 0..count {
+		let end = rest.find(char::is_whitespace)?;
+		fields.push(&rest[..end]);
+		rest = rest[end..].trim_start();
+	}
+	Some((fields, rest))
+}
+
+/// Strip the type indicator appended by `ls -pF` (`/`, `*`, `@`, `|`, `=`)
+fn strip_type_indicator(name: &str) -> &str {
+	name.strip_suffix(['/', '*', '@', '|', '=']).unwrap_or(name)
+}
+
+/// Parse a line of `ls -l`: `mode links owner group size date time name[ -> link]`.
+/// Character and block devices print `major, minor` in place of the size.
+/// With `classify` the `-F` type indicator is stripped from the name and from the link target.
+fn parse_ls_long_line(line: &str, classify: bool) -> Option<DirEntry> {
+	let (fields, rest) = split_fields(line, 5)?;
+	let (size, rest) = if fields[4].ends_with(',') {
+		(None, split_fields(rest, 1)?.1)
+	} else {
+		(fields[4].parse::<u64>().ok(), rest)
+	};
+	let (date, rest) = split_fields(rest, 2)?;
+	let date = chrono::NaiveDateTime::parse_from_str(&format!("{} {}", date[0], date[1]), "%Y-%m-%d %H:%M").ok();
+
+	let strip = |name: &str| if classify { strip_type_indicator(name) } else { name }.to_string();
+	let (name, link) = match rest.split_once(" -> ") {
+		Some((name, link)) if fields[0].starts_with('l') => (name, Some(strip(link))),
+		_ => (rest, None),
+	};
+
+	Some(DirEntry {
+		name: strip(name),
+		parent: None,
+		mode: Some(fields[0].to_string()),
+		links: fields[1].parse().ok(),
+		owner: Some(fields[2].to_string()),
+		group: Some(fields[3].to_string()),
+		size,
+		date,
+		link,
+	})
+}
+
+/// Parse the output of `ls`. In recursive mode every directory listing is preceded by a `dir:` header
+fn parse_ls(output: &str, options: &LsOptions) -> Vec<DirEntry> {
+	let mut parent: Option<String> = None;
+	let mut entries = vec![];
+
+	for line in output.lines() {
+		if line.is_empty() || (options.long && line.starts_with("total ")) {
+			continue;
+		}
+
+		if options.recursive {
+			if let Some(dir) = line.strip_suffix(':') {
+				parent = Some(dir.to_string());
+				continue;
+			}
+		}
+
+		let entry = if options.long {
+			parse_ls_long_line(line, options.classify)
+		} else {
+			Some(DirEntry {
+				name: if options.classify { strip_type_indicator(line) } else { line }.to_string(),
+				..Default::default()
+			})
+		};
+
+		if let Some(mut entry) = entry {
+			entry.parent = parent.clone();
+			entries.push(entry);
+		}
+	}
+	entries
+}
+
+/// Pick the input source from the `getevent -p` output: [`InputSource::touchscreen`] when a device
+/// reports multi-touch positions (`ABS_MT_POSITION_X`, 0x35), [`InputSource::dpad`] otherwise
+fn parse_default_input_source(output: &str) -> InputSource {
+	let mut abs = false;
+	for line in output.lines().map(str::trim) {
+		let codes = match line.split_once("):") {
+			Some((event_type, codes)) => {
+				abs = event_type.starts_with("ABS ");
+				codes
+			}
+			None if line.starts_with("add device") || line.ends_with(':') => {
+				abs = false;
+				continue;
+			}
+			None => line,
+		};
+		if abs && codes.split_whitespace().next() == Some("0035") {
+			return InputSource::touchscreen;
+		}
+	}
+	InputSource::dpad
+}
+
+/// Returns the value of the `Override <name>:` line of a `wm` command output, falling back
+/// to the `Physical <name>:` line
+fn parse_wm_value<'a>(output: &'a str, name: &str) -> Option<&'a str> {
+	let find = |prefix: &str| {
+		let prefix = format!("{prefix} {name}:");
+		output.lines().find_map(|line| line.trim().strip_prefix(prefix.as_str()).map(|v| v.trim()))
+	};
+	find("Override").or_else(|| find("Physical"))
+}
+
+/// Returns the value of the first `key=value` token in a dumpsys output
+fn parse_dumpsys_value<'a>(output: &'a str, key: &str) -> Option<&'a str> {
+	output
+		.split_whitespace()
+		.find_map(|token| token.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// Parse the output of `dumpsys input_method`, using `mInputShown` and falling back to
+/// `mShowRequested` on the releases not reporting it
+fn parse_keyboard_visible(output: &str) -> Option<bool> {
+	parse_dumpsys_value(output, "mInputShown")
+		.or_else(|| parse_dumpsys_value(output, "mShowRequested"))?
+		.parse()
+		.ok()
+}
+
+/// Returns the (key, value) pairs to set in order to go from `current` back to `snapshot`,
+/// sorted by key. Properties missing from the snapshot are cleared.
+pub(crate) fn diff_props(snapshot: &PropSnapshot, current: &PropSnapshot) -> Vec<(String, String)> {
+	let mut changes: Vec<(String, String)> = snapshot
+		.props
+		.iter()
+		.filter(|(key, value)| current.props.get(*key) != Some(*value))
+		.map(|(key, value)| (key.clone(), value.clone()))
+		.chain(
+			current
+				.props
+				.iter()
+				.filter(|(key, value)| !value.is_empty() && !snapshot.props.contains_key(*key))
+				.map(|(key, _)| (key.clone(), String::new())),
+		)
+		.collect();
+	changes.sort();
+	changes
+}
+
+/// Join the commands into a single shell command line, echoing the separator followed by the exit status after each command
+fn make_batch_command(commands: &[Vec<&str>], separator: &str) -> String {
+	commands
+		.iter()
+		.map(|command| format!("{}; echo {separator}$?", command.join(" ")))
+		.collect::<Vec<_>>()
+		.join("; ")
+}
+
+/// Split the output of a batch command into the stdout and exit code of each command
+fn split_batch_output(stdout: &[u8], separator: &str) -> Result<Vec<(Vec<u8>, i32)>> {
+	let separator = separator.as_bytes();
+	let mut result = vec![];
+	let mut rest = stdout;
+
+	while let Some(index) = rest.windows(separator.len()).position(|window| window == separator) {
+		let output = rest[..index].to_vec();
+		rest = &rest[index + separator.len()..];
+		let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+		let code = String::from_utf8_lossy(&rest[..end]).trim().parse::<i32>()?;
+		rest = &rest[(end + 1).min(rest.len())..];
+		result.push((output, code));
+	}
+	Ok(result)
+}
 
+/// The exit status of a process which exited with the given code
+#[cfg(unix)]
+fn exit_status_from_code(code: i32) -> ExitStatus {
+	use std::os::unix::process::ExitStatusExt;
+	ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status_from_code(code: i32) -> ExitStatus {
+	use std::os::windows::process::ExitStatusExt;
+	ExitStatus::from_raw(code as u32)
+}
+
+/// Build the [`DeviceInfo`] from the device properties. Missing properties are left empty,
+/// except for the sdk version which is required
+pub(crate) fn parse_device_info(props: &[Property]) -> Result<DeviceInfo> {
+	let value = |key: &str| {
+		props
+			.iter()
+			.find(|prop| prop.key == key)
+			.map(|prop| prop.value.trim().to_string())
+			.unwrap_or_default()
+	};
+	Ok(DeviceInfo {
+		manufacturer: value("ro.product.manufacturer"),
+		model: value("ro.product.model"),
+		brand: value("ro.product.brand"),
+		name: value("ro.product.name"),
+		device: value("ro.product.device"),
+		board: value("ro.product.board"),
+		hardware: value("ro.hardware"),
+		abilist: value("ro.product.cpu.abilist")
+			.split(',')
+			.filter(|abi| !abi.is_empty())
+			.map(|abi| abi.to_string())
+			.collect(),
+		sdk: value("ro.build.version.sdk")
+			.parse()
+			.map_err(|_| Error::ParseInputError)?,
+	})
+}
+
+/// Parse the output of `wm size`
+fn parse_wm_size(output: &str) -> Option<(u32, u32)> {
+	let (width, height) = parse_wm_value(output, "size")?.split_once('x')?;
+	Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Parse the output of `wm density`
+fn parse_wm_density(output: &str) -> Option<u32> {
+	parse_wm_value(output, "density")?.parse().ok()
+}
+
+/// Parse the `<size>\t<path>` lines printed by `du`, multiplying the sizes by `unit`.
+/// The entries are sorted by size, largest first.
+fn parse_disk_usage(output: &str, unit: u64) -> Vec<(String, u64)> {
+	let mut entries: Vec<(String, u64)> = output
+		.lines()
+		.filter_map(|line| {
+			let (size, path) = line.split_once(char::is_whitespace)?;
+			Some((path.trim().to_string(), size.parse::<u64>().ok()? * unit))
+		})
+		.collect();
+	entries.sort_by_key(|entry| Reverse(entry.1));
+	entries
+}
+
+/// Parse the output of `df <path>`: `filesystem 1K-blocks used available use% mounted-on`.
+/// Older devices wrap the line after a long filesystem name, so the fields are read across lines.
+fn parse_df(output: &str) -> Option<DiskUsage> {
+	let body = output.lines().skip_while(|line| !line.starts_with("Filesystem")).skip(1).collect::<Vec<_>>().join(" ");
+	let (fields, mounted_on) = split_fields(&body, 5)?;
+	Some(DiskUsage {
+		filesystem: fields[0].to_string(),
+		total_kb: fields[1].parse().ok()?,
+		used_kb: fields[2].parse().ok()?,
+		available_kb: fields[3].parse().ok()?,
+		use_percent: fields[4].strip_suffix('%')?.parse().ok()?,
+		mounted_on: mounted_on.trim().to_string(),
+	})
+}
+
+/// Extract the keys of the `NotificationRecord` entries printed by `dumpsys notification`, without duplicates
+fn parse_notification_keys(output: &str) -> Vec<String> {
+	output
+		.lines()
+		.filter(|line| line.trim_start().starts_with("NotificationRecord("))
+		.filter_map(|line| {
+			let (_, key) = line.split_once(" key=")?;
+			let key = key.split_whitespace().next()?.trim_end_matches(':');
+			Some(key.to_string())
+		})
+		.unique()
+		.collect()
+}
+
+/// Build the arguments of `locksettings <command> [--old <old>] [value]`
+fn make_locksettings_args(command: &str, value: Option<&str>, old: Option<&str>) -> Vec<String> {
+	let mut args = vec![
+		"locksettings".to_string(),
+		command.to_string(),
+	];
+	if let Some(old) = old {
+		args.push("--old".to_string());
+		args.push(shell_quote(old));
+	}
+	args.extend(value.map(shell_quote));
+	args
+}
+
+/// Parse the `key: value` lines printed by `dumpsys battery`
+fn parse_battery_info(output: &str) -> Result<BatteryInfo> {
+	let values: HashMap<&str, &str> = output
+		.lines()
+		.filter_map(|line| line.split_once(':'))
+		.map(|(key, value)| (key.trim(), value.trim()))
+		.collect();
+
+	let get = |key: &str| values.get(key).copied().ok_or(Error::ParseInputError);
+	let powered = |source: &str| values.get(format!("{source} powered").as_str()) == Some(&"true");
+
+	let plugged = if powered("AC") {
+		PluggedSource::Ac
+	} else if powered("USB") {
+		PluggedSource::Usb
+	} else if powered("Wireless") {
+		PluggedSource::Wireless
+	} else if powered("Dock") {
+		PluggedSource::Dock
+	} else {
+		PluggedSource::None
+	};
+
+	Ok(BatteryInfo {
+		level: get("level")?.parse()?,
+		scale: get("scale")?.parse()?,
+		status: BatteryStatus::from(get("status")?.parse::<u8>()?),
+		health: BatteryHealth::from(get("health")?.parse::<u8>()?),
+		plugged,
+		temperature: get("temperature")?.parse::<i32>()? as f32 / 10.0,
+		voltage_mv: get("voltage")?.parse()?,
+		present: get("present")? == "true",
+	})
+}
+
+/// Parse the output of `ps -A -o PID,PPID,USER,RSS,S,NAME`
+fn parse_processes(output: &str) -> Vec<ProcessInfo> {
+	output
+		.lines()
+		.filter_map(|line| {
+			let (fields, name) = split_fields(line, 5)?;
+			Some(ProcessInfo {
+				pid: fields[0].parse().ok()?,
+				ppid: fields[1].parse().ok()?,
+				user: fields[2].to_string(),
+				rss_kb: fields[3].parse().ok()?,
+				state: fields[4].chars().next()?,
+				name: name.to_string(),
+			})
+		})
+		.collect()
+}
+
+/// Parse the output of the legacy toolbox `ps`:
+/// `USER PID PPID VSIZE RSS WCHAN PC S NAME`
+fn parse_legacy_processes(output: &str) -> Vec<ProcessInfo> {
+	output
+		.lines()
+		.filter_map(|line| {
+			let (fields, name) = split_fields(line, 8)?;
+			Some(ProcessInfo {
+				pid: fields[1].parse().ok()?,
+				ppid: fields[2].parse().ok()?,
+				user: fields[0].to_string(),
+				rss_kb: fields[4].parse().ok()?,
+				state: fields[7].chars().next()?,
+				name: name.to_string(),
+			})
+		})
+		.collect()
+}
+
+/// Parse the process rows of `top -n 1 -b`, locating the columns from the header so that the toybox
+/// (`PID USER ... S[%CPU] %MEM TIME+ ARGS`), toolbox (`PID PR CPU% S ... UID Name`) and busybox
+/// (`PID PPID USER STAT ... %CPU COMMAND`) layouts are all supported
+fn parse_top(output: &str) -> Vec<CpuEntry> {
+	let mut lines = output.lines();
+	let header: Vec<String> = match lines.find(|line| {
+		let line = line.replace(['[', ']'], " ");
+		line.split_whitespace().next() == Some("PID") && line.contains("CPU")
+	}) {
+		Some(header) => header.replace(['[', ']'], " ").split_whitespace().map(|s| s.to_string()).collect(),
+		None => return vec![],
+	};
+
+	let column = |names: &[&str]| header.iter().position(|name| names.contains(&name.as_str()));
+	let (pid, user, cpu, name) = match (
+		column(&["PID"]),
+		column(&["USER", "UID"]),
+		column(&["%CPU", "CPU%"]),
+		column(&["ARGS", "CMD", "COMMAND", "NAME", "Name"]),
+	) {
+		(Some(pid), Some(user), Some(cpu), Some(name)) if name == header.len() - 1 => (pid, user, cpu, name),
+		_ => return vec![],
+	};
+	let mem = column(&["%MEM"]);
+	let percent = |value: &str| value.trim_end_matches('%').parse::<f32>().ok();
+
+	lines
+		.filter_map(|line| {
+			let (fields, rest) = split_fields(line, name)?;
+			Some(CpuEntry {
+				pid: fields[pid].parse().ok()?,
+				user: fields[user].to_string(),
+				cpu_percent: percent(fields[cpu])?,
+				mem_percent: mem.and_then(|mem| percent(fields[mem])),
+				name: rest.trim_end().to_string(),
+			})
+		})
+		.filter(|entry| !entry.name.is_empty())
+		.collect()
+}
+
+/// Build a single shell command printing `1` or `0` for each path, depending on whether it exists
+fn make_exists_many_command(paths: &[&str]) -> String {
+	let paths = paths.iter().map(|path| shell_quote(path)).join(" ");
+	format!("for p in {paths}; do test -e \"$p\" && echo 1 || echo 0; done")
+}
+
+/// Parse the output of [`make_exists_many_command`], which must contain a result for each of the `count` paths
+fn parse_exists_many(output: &str, count: usize) -> Result<Vec<bool>> {
+	let result = output
+		.lines()
+		.map(|line| match line.trim() {
+			"1" => Ok(true),
+			"0" => Ok(false),
+			_ => Err(Error::ParseInputError),
+		})
+		.collect::<Result<Vec<bool>>>()?;
+	if result.len() == count { Ok(result) } else { Err(Error::ParseInputError) }
+}
+
+/// Returns the locale language tag (eg. `en-US`) for the given language and country
+fn make_locale_tag(lang: &str, country: &str) -> String {
+	if country.is_empty() {
+		lang.to_lowercase()
+	} else {
+		format!("{}-{}", lang.to_lowercase(), country.to_uppercase())
+	}
+}
+
+/// Quote the value for the device shell, so that it's passed unchanged as a single argument
+pub(crate) fn shell_quote(value: &str) -> String {
+	format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Returns the audio capture flag listed in the `screenrecord --help` output, if any
+fn parse_screenrecord_audio_flag(help: &str) -> Option<&'static str> {
+	["--capture-audio", "--audio"]
+		.into_iter()
+		.find(|flag| help.lines().any(|line| line.trim_start().starts_with(flag)))
+}
+
+/// Build the arguments of `input [source] <command>`
+fn make_input(source: Option<InputSource>, command: &str) -> Vec<OsString> {
+	let mut args = vec!["input".into()];
 	if let Some(source) = source {
 		args.push(source.into());
 	}
+	args.push(command.into());
+	args
+}
 
-	args.push("keycombination".into());
+fn make_keyevent_combination<I, S>(source: Option<InputSource>, keycodes: I) -> Vec<OsString>
+where
+	I: IntoIterator<Item = S>,
+	S: Into<KeyCode>,
+{
+	let mut args = make_input(source, "keycombination");
 	args.extend(keycodes.into_iter().map(|k| k.into().as_os_str()));
 	args
 }
@@ -57,13 +569,7 @@ fn make_keycode_combination<I>(source: Option<InputSource>, keycodes: I) -> Vec<
 where
 	I: IntoIterator<Item = u32>,
 {
-	let mut args = vec!["input".into()];
-
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("keycombination".into());
+	let mut args = make_input(source, "keycombination");
 	args.extend(keycodes.into_iter().map(|k| k.to_string().into()));
 	args
 }
@@ -74,11 +580,7 @@ fn make_draganddrop(
 	from_pos: (i32, i32),
 	to_pos: (i32, i32),
 ) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-	args.push("draganddrop".into());
+	let mut args = make_input(source, "draganddrop");
 
 	let pos0 = from_pos.0.to_string();
 	let pos1 = from_pos.1.to_string();
@@ -97,21 +599,11 @@ fn make_draganddrop(
 }
 
 fn make_press(source: Option<InputSource>) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-	args.push("press".into());
-	args
+	make_input(source, "press")
 }
 
 fn make_swipe(from_pos: (i32, i32), to_pos: (i32, i32), duration: Option<Duration>, source: Option<InputSource>) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("swipe".into());
+	let mut args = make_input(source, "swipe");
 
 	let pos_string = format!("{:?} {:?} {:?} {:?}", from_pos.0, from_pos.1, to_pos.0, to_pos.1);
 	args.push(pos_string.into());
@@ -122,14 +614,16 @@ fn make_swipe(from_pos: (i32, i32), to_pos: (i32, i32), duration: Option<Duratio
 	args
 }
 
-fn make_keyevent(keycode: KeyCode, event_type: Option<KeyEventType>, source: Option<InputSource>) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-
-	if let Some(source) = source {
-		args.push(source.into());
+fn make_long_press(position: (i32, i32), duration: Duration, source: Option<InputSource>) -> Result<Vec<OsString>> {
+	if duration.is_zero() {
+		return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput).into());
 	}
+	// a swipe which starts and ends at the same position is a long press
+	Ok(make_swipe(position, position, Some(duration), source))
+}
 
-	args.push("keyevent".into());
+fn make_keyevent(keycode: KeyCode, event_type: Option<KeyEventType>, source: Option<InputSource>) -> Vec<OsString> {
+	let mut args = make_input(source, "keyevent");
 
 	if let Some(event_type) = event_type {
 		args.push(event_type.into());
@@ -140,13 +634,7 @@ fn make_keyevent(keycode: KeyCode, event_type: Option<KeyEventType>, source: Opt
 }
 
 fn make_keycode(keycode: u32, event_type: Option<KeyEventType>, source: Option<InputSource>) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("keyevent".into());
+	let mut args = make_input(source, "keyevent");
 
 	if let Some(event_type) = event_type {
 		args.push(event_type.into());
@@ -157,12 +645,7 @@ fn make_keycode(keycode: u32, event_type: Option<KeyEventType>, source: Option<I
 }
 
 fn make_tap(position: (i32, i32), source: Option<InputSource>) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("tap".into());
+	let mut args = make_input(source, "tap");
 
 	let pos0 = format!("{:?}", position.0);
 	let pos1 = format!("{:?}", position.1);
@@ -173,23 +656,13 @@ fn make_tap(position: (i32, i32), source: Option<InputSource>) -> Vec<OsString>
 }
 
 fn make_char(chr: char, source: Option<InputSource>) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("text".into());
+	let mut args = make_input(source, "text");
 	args.push(format!("{:}", chr).into());
 	args
 }
 
 fn make_text(text: &str, source: Option<InputSource>) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("text".into());
+	let mut args = make_input(source, "text");
 	args.push(format!("{:?}", text).into());
 	args
 }
@@ -205,11 +678,7 @@ fn make_event(event: &str, code_type: i32, code: i32, value: i32) -> Vec<OsStrin
 }
 
 fn make_motion(source: Option<InputSource>, motion: MotionEvent, pos: (i32, i32)) -> Vec<OsString> {
-	let mut args = vec!["input".into()];
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-	args.push("motionevent".into());
+	let mut args = make_input(source, "motionevent");
 	args.push(motion.into());
 
 	let pos0 = pos.0.to_string();
@@ -225,13 +694,7 @@ where
 	I: IntoIterator<Item = S>,
 	S: Into<KeyCode>,
 {
-	let mut args = vec!["input".into()];
-
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("keyevent".into());
+	let mut args = make_input(source, "keyevent");
 	args.extend(keycodes.into_iter().map(|k| k.into().into()).collect::<Vec<OsString>>());
 	args
 }
@@ -240,18 +703,165 @@ fn make_keycodes<I>(keycodes: I, source: Option<InputSource>) -> Vec<OsString>
 where
 	I: IntoIterator<Item = u32>,
 {
-	let mut args = vec!["input".into()];
-
-	if let Some(source) = source {
-		args.push(source.into());
-	}
-
-	args.push("keyevent".into());
+	let mut args = make_input(source, "keyevent");
 	let keycodes_string = keycodes.into_iter().map(|k| k.to_string().into()).collect::<Vec<OsString>>();
 	args.extend(keycodes_string);
 	args
 }
 
+/// Parse a /proc/<pid>/task/<tid>/stat line into (tid, name, state, utime + stime)
+fn parse_thread_stat(line: &str) -> Option<(u32, String, char, u64)> {
+	let (tid, rest) = line.trim().split_once(" (")?;
+	// the thread name can contain both spaces and parenthesis
+	let (name, rest) = rest.rsplit_once(") ")?;
+	let fields: Vec<&str> = rest.split_whitespace().collect();
+	let state = fields.first()?.chars().next()?;
+	let utime = fields.get(11)?.parse::<u64>().ok()?;
+	let stime = fields.get(12)?.parse::<u64>().ok()?;
+	Some((tid.parse().ok()?, name.to_string(), state, utime + stime))
+}
+
+/// Compute the threads cpu usage given two samples of their stat files, taken `interval` apart.
+/// Threads which are gone in the second sample are discarded, while threads spawned in between
+/// are accounted from zero.
+fn parse_threads(before: &str, after: &str, interval: Duration) -> Vec<ThreadInfo> {
+	let before: HashMap<u32, u64> = before
+		.lines()
+		.filter_map(parse_thread_stat)
+		.map(|(tid, _, _, ticks)| (tid, ticks))
+		.collect();
+	let elapsed_ticks = interval.as_secs_f32() * CLOCK_TICKS_PER_SEC;
+
+	after
+		.lines()
+		.filter_map(parse_thread_stat)
+		.map(|(tid, name, state, ticks)| {
+			let delta = ticks.saturating_sub(before.get(&tid).copied().unwrap_or(0));
+			ThreadInfo {
+				tid,
+				name,
+				cpu_percent: delta as f32 * 100.0 / elapsed_ticks,
+				state,
+			}
+		})
+		.collect()
+}
+
+/// Parse the `cpu` lines of /proc/stat into (name, [user, nice, system, idle, iowait, irq, softirq, steal]) jiffies
+fn parse_cpu_stat(stat: &str) -> Vec<(String, [u64; 8])> {
+	stat.lines()
+		.filter(|line| line.starts_with("cpu"))
+		.filter_map(|line| {
+			let mut fields = line.split_whitespace();
+			let name = fields.next()?.to_string();
+			let mut jiffies = [0u64; 8];
+			for value in jiffies.iter_mut() {
+				*value = fields.next()?.parse().ok()?;
+			}
+			Some((name, jiffies))
+		})
+		.collect()
+}
+
+/// Parse the content of /proc/loadavg, e.g. `0.52 0.58 0.59 2/1205 8763`
+pub(crate) fn parse_loadavg(output: &str) -> Option<LoadAverage> {
+	let mut fields = output.split_whitespace();
+	let one = fields.next()?.parse().ok()?;
+	let five = fields.next()?.parse().ok()?;
+	let fifteen = fields.next()?.parse().ok()?;
+	let (running, total) = fields.next()?.split_once('/')?;
+	Some(LoadAverage {
+		one,
+		five,
+		fifteen,
+		running: running.parse().ok()?,
+		total: total.parse().ok()?,
+	})
+}
+
+/// Parse /proc/net/arp: `ip hw-type flags mac mask device`, skipping the incomplete entries
+pub(crate) fn parse_arp_table(output: &str) -> Vec<ArpEntry> {
+	output
+		.lines()
+		.skip(1)
+		.filter_map(|line| {
+			let fields: Vec<&str> = line.split_whitespace().collect();
+			if fields.len() < 6 {
+				return None;
+			}
+			let mac = MacAddress::try_from(fields[3]).ok()?;
+			if mac.bytes() == [0; 6] {
+				return None;
+			}
+			Some(ArpEntry {
+				ip: fields[0].parse().ok()?,
+				mac,
+				device: fields[5].to_string(),
+				flags: u8::from_str_radix(fields[2].trim_start_matches("0x"), 16).ok()?,
+			})
+		})
+		.collect()
+}
+
+/// Compute the cpu usage given two samples of /proc/stat. Cpus missing in the first sample
+/// (eg. brought online in between) are discarded.
+fn parse_cpu_rows(before: &str, after: &str) -> Vec<CpuRow> {
+	let before: HashMap<String, [u64; 8]> = parse_cpu_stat(before).into_iter().collect();
+
+	parse_cpu_stat(after)
+		.into_iter()
+		.filter_map(|(name, after)| {
+			let before = before.get(&name)?;
+			let delta: Vec<f32> = after.iter().zip(before).map(|(a, b)| a.saturating_sub(*b) as f32).collect();
+			let total: f32 = delta.iter().sum();
+			let percent = |value: f32| if total > 0.0 { value * 100.0 / total } else { 0.0 };
+			Some(CpuRow {
+				name,
+				usage_percent: percent(total - delta[3] - delta[4]),
+				user_percent: percent(delta[0] + delta[1]),
+				system_percent: percent(delta[2] + delta[5] + delta[6]),
+				iowait_percent: percent(delta[4]),
+				idle_percent: percent(delta[3]),
+			})
+		})
+		.collect()
+}
+
+/// Parse the output of `monkey -v`. When the run is aborted the last reported event count is used.
+fn parse_monkey_output(output: &str) -> Option<MonkeyResult> {
+	let events_injected = RE_MONKEY_EVENTS.captures_iter(output).last().and_then(|c| c[1].parse().ok());
+	let crashed = output.lines().any(|line| line.starts_with("// CRASH"));
+	let anr = output
+		.lines()
+		.any(|line| line.starts_with("// ANR") || line.starts_with("// NOT RESPONDING"));
+
+	if events_injected.is_none() && !crashed && !anr {
+		return None;
+	}
+
+	Some(MonkeyResult {
+		events_injected: events_injected.unwrap_or(0),
+		crashed,
+		anr,
+		raw: output.to_string(),
+	})
+}
+
+/// Parse a property value according to its declared type
+fn parse_prop_value(value: &str, prop_type: &PropType) -> Result<PropValue> {
+	match prop_type {
+		PropType::String | PropType::Unknown(_) => Ok(PropValue::String(value.to_string())),
+		PropType::Bool => match value {
+			"true" | "1" => Ok(PropValue::Bool(true)),
+			"false" | "0" => Ok(PropValue::Bool(false)),
+			_ => Err(Error::ParseInputError),
+		},
+		PropType::Int => value.parse().map(PropValue::Int).map_err(|_| Error::ParseInputError),
+		PropType::Enum(values) if values.iter().any(|v| v == value) => Ok(PropValue::Enum(value.to_string())),
+		PropType::Enum(_) => Err(Error::ParseInputError),
+	}
+}
+
 macro_rules! ro_build_property {
 	($name:tt, $key:tt, $typ:ty) => {
 		pub fn $name(&self) -> Result<$typ> {
@@ -261,14 +871,65 @@ macro_rules! ro_build_property {
 }
 
 impl<'a> Shell<'a> {
-	/// executes custom command over the shell interface
+	/// executes custom command over the shell interface.
+	/// Returns [`Error::Timeout`] if the command is killed because the timeout expired.
 	pub fn exec<I, S>(&self, args: I, cancel: Option<Receiver<()>>, timeout: Option<Duration>) -> Result<Output>
 	where
 		I: IntoIterator<Item = S>,
 		S: AsRef<OsStr>,
 	{
-		let builder = CommandBuilder::shell(self.parent).args(args).signal(cancel).timeout(timeout);
-		Ok(builder.build().output()?)
+		let args = args.into_iter().map(|arg| arg.as_ref().to_os_string()).collect::<Vec<_>>();
+		let command = args.iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" ");
+		let timeout = timeout.or(self.parent.config.default_command_timeout);
+		self.parent.run_with_retry(|| {
+			let started = Instant::now();
+			let output = self.shell_command(&args, cancel.clone(), timeout).build().output()?;
+			handle_timeout(&command, started, timeout, output)
+		})
+	}
+
+	/// Same as [`Shell::exec`], but the output of a command killed by the timeout is returned as is
+	fn exec_killable(&self, args: Vec<String>, timeout: Option<Duration>) -> Result<Output> {
+		let timeout = timeout.or(self.parent.config.default_command_timeout);
+		self.parent.run_with_retry(|| Ok(self.shell_command(&args, None, timeout).build().output()?))
+	}
+
+	fn shell_command<S: AsRef<OsStr>>(
+		&self,
+		args: &[S],
+		cancel: Option<Receiver<()>>,
+		timeout: Option<Duration>,
+	) -> AdbCommandBuilder {
+		AdbCommandBuilder::shell(self.parent).args(args).signal(cancel).timeout(timeout)
+	}
+
+	/// Run the given commands in a single adb shell invocation, returning the output of each of them.
+	/// Arguments are passed to the device shell unquoted. The stderr of the commands is not separated
+	/// and is returned with the last command.
+	pub fn exec_batch(&self, commands: &[Vec<&str>]) -> Result<Vec<Output>> {
+		if commands.is_empty() {
+			return Ok(vec![]);
+		}
+
+		// unique for each call, so that it can't be found in the output of the commands
+		let separator = format!("{BATCH_SEPARATOR_PREFIX}{}__", Uuid::new_v4().simple());
+		let output = self.exec(vec![make_batch_command(commands, &separator)], None, None)?;
+		let results = split_batch_output(&output.stdout, &separator)?;
+		// a command exited the shell before the remaining ones could run
+		if results.len() != commands.len() {
+			return Err(Error::ParseInputError);
+		}
+
+		let count = results.len();
+		Ok(results
+			.into_iter()
+			.enumerate()
+			.map(|(index, (stdout, code))| Output {
+				status: exit_status_from_code(code),
+				stdout,
+				stderr: if index == count - 1 { output.stderr.clone() } else { vec![] },
+			})
+			.collect())
 	}
 
 	pub fn try_exec<I, S>(&self, args: I, cancel: Option<Receiver<()>>, timeout: Option<Duration>) -> Result<Option<ExitStatus>>
@@ -276,7 +937,10 @@ impl<'a> Shell<'a> {
 		I: IntoIterator<Item = S>,
 		S: AsRef<OsStr>,
 	{
-		let builder = CommandBuilder::shell(self.parent).args(args).signal(cancel).timeout(timeout);
+		let builder = AdbCommandBuilder::shell(self.parent)
+			.args(args)
+			.signal(cancel)
+			.timeout(timeout.or(self.parent.config.default_command_timeout));
 		Ok(builder.build().run()?)
 	}
 
@@ -341,9 +1005,25 @@ impl<'a> Shell<'a> {
 		.map(|s| s.stdout)
 	}
 
+	/// Stream the content of the file into `writer`, returning the number of bytes copied.
+	/// Unlike [`Shell::cat`] the file is never loaded in memory, which suits large files
+	pub fn cat_to_writer<P: Arg, W: Write>(&self, path: P, mut writer: W) -> Result<u64> {
+		let args = vec![
+			"exec-out".into(),
+			"cat".into(),
+			path.as_str()?.into(),
+		];
+		let (result, status) = self
+			.parent
+			.pipe_stdout(args, None, None, false, |mut stdout| std::io::copy(&mut stdout, &mut writer))?;
+		let copied = result?;
+		handle_exit_status(status)?;
+		Ok(copied)
+	}
+
 	/// Check if avbctl is available on the connected device
 	pub fn check_avbctl(&self) -> Result<()> {
-		self.get_command_path("avbctl").map(|_| ()).ok_or(Error::AvbctlNotInstalled)
+		if self.command_exists("avbctl") { Ok(()) } else { Err(Error::AvbctlNotInstalled) }
 	}
 
 	/// Returns if avbctl is available
@@ -358,6 +1038,18 @@ impl<'a> Shell<'a> {
 		}
 	}
 
+	/// Returns true if the command is available on the device. The result is cached, see [`Shell::get_command_path`]
+	pub fn command_exists(&self, command: &str) -> bool {
+		self.get_command_path(command).is_some()
+	}
+
+	/// Change the size of the cache used by [`Shell::get_command_path`], shared by all the devices.
+	/// The cached entries are discarded.
+	pub fn set_commands_cache_size(size: usize) {
+		*COMMANDS_CACHE.lock().unwrap() = SizedCache::with_size(size);
+	}
+
+	/// Returns the path of the given command, caching the result per device
 	pub fn get_command_path<T: Arg>(&self, command: T) -> Option<String> {
 		if let Ok(command_string) = command.as_str() {
 			let mut binding = COMMANDS_CACHE.lock().unwrap();
@@ -418,6 +1110,7 @@ impl<'a> Shell<'a> {
 
 	/// Disable verity using the avbctl service, if available
 	pub fn disable_verity(&self) -> Result<()> {
+		self.parent.ensure_root()?;
 		let _ = self.check_avbctl()?;
 		let output = self.exec(
 			vec![
@@ -433,6 +1126,7 @@ impl<'a> Shell<'a> {
 
 	/// Enable verity using the avbctl service, if available
 	pub fn enable_verity(&self) -> Result<()> {
+		self.parent.ensure_root()?;
 		let _ = self.check_avbctl()?;
 		let output = self.exec(
 			vec![
@@ -445,11 +1139,27 @@ impl<'a> Shell<'a> {
 		if output.error() { Err(output.into()) } else { Ok(()) }
 	}
 
-	/// Returns the selinux enforce status
+	/// Returns the selinux enforce type. When `getenforce` is not available, `/sys/fs/selinux/enforce` is read instead
 	pub fn get_enforce(&self) -> Result<SELinuxType> {
-		let result = self.exec(vec!["getenforce"], None, None)?.stdout;
-		let enforce: SELinuxType = SELinuxType::try_from(result)?;
-		Ok(enforce)
+		let output = self.exec(vec!["getenforce"], None, None)?;
+		if output.success() {
+			if let Ok(enforce) = SELinuxType::try_from(output.stdout) {
+				return Ok(enforce);
+			}
+		}
+
+		let output = self.exec(
+			vec![
+				"cat",
+				SELINUX_ENFORCE_FILE,
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		SELinuxType::try_from(output.stdout)
 	}
 
 	/// Change the selinux enforce type. root is required
@@ -472,6 +1182,12 @@ impl<'a> Shell<'a> {
 		Ok(result)
 	}
 
+	/// Returns true if the soft keyboard is currently shown
+	pub fn is_keyboard_visible(&self) -> Result<bool> {
+		let output = self.dumpsys(Some("input_method"), None, None, false, false, false, None)?;
+		parse_keyboard_visible(Arg::as_str(&output.stdout)?).ok_or(Error::ParseInputError)
+	}
+
 	pub fn send_keyevent(&self, keycode: KeyCode, event_type: Option<KeyEventType>, source: Option<InputSource>) -> Result<()> {
 		let result = self.exec(make_keyevent(keycode, event_type, source), None, None)?;
 		handle_result(result)
@@ -496,6 +1212,11 @@ impl<'a> Shell<'a> {
 		handle_result(result)
 	}
 
+	/// Long press at the given position for the given duration, which must be greater than zero
+	pub fn long_press(&self, position: (i32, i32), duration: Duration, source: Option<InputSource>) -> Result<()> {
+		handle_result(self.exec(make_long_press(position, duration, source)?, None, None)?)
+	}
+
 	pub fn send_event(&self, event: &str, code_type: i32, code: i32, value: i32) -> Result<()> {
 		handle_result(self.exec(make_event(event, code_type, code, value), None, None)?)
 	}
@@ -527,6 +1248,14 @@ impl<'a> Shell<'a> {
 		handle_result(self.exec(make_press(source), None, None)?)
 	}
 
+	/// Run `input [source] <command> [args...]`, for the subcommands without a dedicated method
+	/// (ie. `roll`, `gesture`)
+	pub fn send_input(&self, source: Option<InputSource>, command: &str, args: &[&str]) -> Result<()> {
+		let mut input = make_input(source, command);
+		input.extend(args.iter().map(OsString::from));
+		handle_result(self.exec(input, None, None)?)
+	}
+
 	pub fn send_keyevents<I, S>(&self, keycodes: I, source: Option<InputSource>) -> Result<()>
 	where
 		I: IntoIterator<Item = S>,
@@ -621,6 +1350,15 @@ impl<'a> Shell<'a> {
 		self.try_exec(make_tap(position, source), None, None)
 	}
 
+	pub fn try_long_press(
+		&self,
+		position: (i32, i32),
+		duration: Duration,
+		source: Option<InputSource>,
+	) -> Result<Option<ExitStatus>> {
+		self.try_exec(make_long_press(position, duration, source)?, None, None)
+	}
+
 	pub fn try_send_keyevent(
 		&self,
 		keycode: KeyCode,
@@ -689,6 +1427,213 @@ impl<'a> Shell<'a> {
 		Ok(v)
 	}
 
+	/// Returns the input source to use for the `input` commands: the touchscreen when the device has one,
+	/// the dpad otherwise
+	pub fn default_input_source(&self) -> Result<InputSource> {
+		let output = self.exec(
+			vec![
+				"getevent", "-p",
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_default_input_source(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Returns the list of all the running processes
+	pub fn list_processes(&self) -> Result<Vec<ProcessInfo>> {
+		let output = self.exec(
+			vec![
+				"ps", "-A", "-o", "PID,PPID,USER,RSS,S,NAME",
+			],
+			None,
+			None,
+		)?;
+		if output.success() && !output.stdout.is_empty() {
+			return Ok(parse_processes(Arg::as_str(&output.stdout)?));
+		}
+
+		// toolbox ps, before Android 8
+		let output = self.exec(vec!["ps"], None, None)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_legacy_processes(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Returns the pids of the processes with the given name
+	pub fn pidof(&self, process: &str) -> Result<Vec<u32>> {
+		let output = self.exec(
+			vec![
+				"pidof", process,
+			],
+			None,
+			None,
+		)?;
+		// pidof exits with 1 when no process is found
+		Ok(Arg::as_str(&output.stdout)?
+			.split_whitespace()
+			.filter_map(|pid| pid.parse::<u32>().ok())
+			.collect())
+	}
+
+	/// Returns the pids of all the running processes of the given package
+	pub fn pid_of_package(&self, package: &str) -> Result<Vec<i32>> {
+		let output = self.exec(
+			vec![
+				"ps", "-A", "-o", "PID,NAME",
+			],
+			None,
+			None,
+		)?;
+		Ok(parse_package_pids(Arg::as_str(&output.stdout)?, package))
+	}
+
+	/// Returns the system cpu usage sampled over the given interval: the aggregate `cpu` row, followed by
+	/// a row for each online cpu
+	pub fn system_cpu_usage(&self, interval: Duration) -> Result<Vec<CpuRow>> {
+		let command = format!(
+			"cat /proc/stat; echo {SAMPLES_SEPARATOR}; sleep {:.3}; cat /proc/stat",
+			interval.as_secs_f32()
+		);
+		let output = self.exec(vec![command.as_str()], None, None)?;
+		let string = Arg::as_str(&output.stdout)?;
+		let (before, after) = string.split_once(SAMPLES_SEPARATOR).ok_or(Error::ParseInputError)?;
+		Ok(parse_cpu_rows(before, after))
+	}
+
+	/// Returns the cpu and memory usage of the running processes, as reported by `top -n 1 -b`.
+	/// When `package_or_pid` is given, only the processes with that pid, or belonging to that package, are returned
+	pub fn cpu_usage(&self, package_or_pid: Option<&str>) -> Result<Vec<CpuEntry>> {
+		let output = self.exec(
+			vec![
+				"top", "-n", "1", "-b",
+			],
+			None,
+			None,
+		)?;
+		let entries = parse_top(Arg::as_str(&output.stdout)?);
+		Ok(match package_or_pid {
+			None => entries,
+			Some(filter) => entries
+				.into_iter()
+				.filter(|entry| {
+					entry.pid.to_string() == filter
+						|| entry.name == filter
+						|| entry.name.strip_prefix(filter).is_some_and(|rest| rest.starts_with(':'))
+				})
+				.collect(),
+		})
+	}
+
+	/// Sample the cpu usage every `interval`, invoking `on_sample` with the usage since the previous sample.
+	/// Stops when `on_sample` returns false or the `cancel` channel fires.
+	pub fn monitor_cpu<F: FnMut(Vec<CpuRow>) -> bool>(
+		&self,
+		interval: Duration,
+		mut on_sample: F,
+		cancel: Option<Receiver<()>>,
+	) -> Result<()> {
+		let mut cancel = cancel.unwrap_or(crossbeam_channel::never());
+		let mut before = self.cat("/proc/stat")?;
+
+		loop {
+			select! {
+				recv(cancel) -> msg => match msg {
+					Ok(_) => return Ok(()),
+					// the sender was dropped, keep sampling every `interval`
+					Err(_) => {
+						cancel = crossbeam_channel::never();
+						continue;
+					}
+				},
+				recv(crossbeam_channel::after(interval)) -> _ => {},
+			}
+
+			let after = self.cat("/proc/stat")?;
+			let rows = parse_cpu_rows(Arg::as_str(&before)?, Arg::as_str(&after)?);
+			if !on_sample(rows) {
+				return Ok(());
+			}
+			before = after;
+		}
+	}
+
+	/// Returns the 1, 5 and 15 minutes load averages, together with the running and total threads count
+	pub fn load_average(&self) -> Result<LoadAverage> {
+		let output = self.cat("/proc/loadavg")?;
+		parse_loadavg(Arg::as_str(&output)?).ok_or(Error::ParseInputError)
+	}
+
+	/// Returns the neighbors of the device (other hosts on the same networks), reading /proc/net/arp
+	pub fn arp_table(&self) -> Result<Vec<ArpEntry>> {
+		let output = self.cat("/proc/net/arp")?;
+		Ok(parse_arp_table(Arg::as_str(&output)?))
+	}
+
+	/// Run the `monkey` stress test against the given package, injecting `event_count` pseudo-random events.
+	/// The run can be interrupted with the `cancel` channel, in which case the events injected so far are reported.
+	pub fn monkey(
+		&self,
+		package: &str,
+		event_count: u32,
+		seed: Option<u64>,
+		throttle_ms: Option<u32>,
+		cancel: Option<Receiver<()>>,
+	) -> Result<MonkeyResult> {
+		let mut args = vec![
+			"monkey".to_string(),
+			"-p".to_string(),
+			package.to_string(),
+			"-v".to_string(),
+		];
+		if let Some(seed) = seed {
+			args.push("-s".to_string());
+			args.push(seed.to_string());
+		}
+		if let Some(throttle_ms) = throttle_ms {
+			args.push("--throttle".to_string());
+			args.push(throttle_ms.to_string());
+		}
+		args.push(event_count.to_string());
+
+		let output = self.exec(args, cancel, None)?;
+		match parse_monkey_output(Arg::as_str(&output.stdout)?) {
+			Some(result) => Ok(result),
+			None if output.kill() || output.interrupt() => Ok(MonkeyResult {
+				events_injected: 0,
+				crashed: false,
+				anr: false,
+				raw: Arg::as_str(&output.stdout)?.to_string(),
+			}),
+			None if output.error() => Err(output.into()),
+			None => Err(Error::ParseInputError),
+		}
+	}
+
+	/// Returns the threads of the given process, with their cpu usage sampled over a short interval.
+	/// Threads terminating while the sample is taken are not returned.
+	pub fn threads(&self, pid: u32) -> Result<Vec<ThreadInfo>> {
+		let stat = format!("/proc/{pid}/task/*/stat");
+		let command = format!(
+			"cat {stat} 2>/dev/null; echo {SAMPLES_SEPARATOR}; sleep {:.3}; cat {stat} 2>/dev/null",
+			THREADS_SAMPLE_INTERVAL.as_secs_f32()
+		);
+		let output = self.exec(vec![command.as_str()], None, None)?;
+		let string = Arg::as_str(&output.stdout)?;
+		let (before, after) = string.split_once(SAMPLES_SEPARATOR).ok_or(Error::ParseInputError)?;
+		let threads = parse_threads(before, after, THREADS_SAMPLE_INTERVAL);
+
+		if threads.is_empty() {
+			Err(Error::NameNotFoundError(pid.to_string()))
+		} else {
+			Ok(threads)
+		}
+	}
+
 	pub fn file_mode<T: Arg>(&self, path: T) -> Result<file_mode::Mode> {
 		let output = Arg::as_str(
 			&self
@@ -713,6 +1658,7 @@ impl<'a> Shell<'a> {
 	}
 
 	pub fn list_settings(&self, settings_type: SettingsType) -> Result<Vec<Property>> {
+		self.parent.ensure_root()?;
 		let output = self.exec(
 			vec![
 				"settings",
@@ -766,6 +1712,42 @@ impl<'a> Shell<'a> {
 		handle_result(result)
 	}
 
+	/// Poll the given setting every `interval`, invoking `on_change` with the new value whenever it changes
+	/// (an empty string when the setting is deleted). Stops when `on_change` returns false or the `cancel` channel fires.
+	pub fn watch_setting<F: FnMut(String) -> bool>(
+		&self,
+		settings_type: SettingsType,
+		key: &str,
+		interval: Duration,
+		mut on_change: F,
+		cancel: Option<Receiver<()>>,
+	) -> Result<()> {
+		let mut cancel = cancel.unwrap_or(crossbeam_channel::never());
+		let mut current = self.get_setting(settings_type, key)?;
+
+		loop {
+			select! {
+				recv(cancel) -> msg => match msg {
+					Ok(_) => return Ok(()),
+					// the sender was dropped, keep polling every `interval`
+					Err(_) => {
+						cancel = crossbeam_channel::never();
+						continue;
+					}
+				},
+				recv(crossbeam_channel::after(interval)) -> _ => {},
+			}
+
+			let value = self.get_setting(settings_type, key)?;
+			if value != current {
+				if !on_change(value.clone().unwrap_or_default()) {
+					return Ok(());
+				}
+				current = value;
+			}
+		}
+	}
+
 	pub fn delete_setting(&self, settings_type: SettingsType, key: &str) -> Result<()> {
 		let result = self.exec(
 			vec![
@@ -794,10 +1776,105 @@ impl<'a> Shell<'a> {
 		Ok(lines)
 	}
 
+	/// Returns the disk usage, in bytes, of the given path and of its subdirectories up to `depth`,
+	/// sorted by size, largest first.
+	/// When `du -b` is not supported, the sizes are computed from `du -k` with a 1KiB granularity.
+	pub fn disk_usage<T: Arg>(&self, path: T, depth: u32) -> Result<Vec<(String, u64)>> {
+		let path = path.as_str()?;
+		let depth = depth.to_string();
+
+		// du exits with an error when some of the subdirectories can't be read,
+		// still printing the sizes of the other ones
+		let output = self.exec(
+			vec![
+				"du",
+				"-b",
+				"-d",
+				depth.as_str(),
+				path,
+			],
+			None,
+			None,
+		)?;
+		if !output.error() || !output.stdout.is_empty() {
+			return Ok(parse_disk_usage(Arg::as_str(&output.stdout)?, 1));
+		}
+
+		let output = self.exec(
+			vec![
+				"du",
+				"-k",
+				"-d",
+				depth.as_str(),
+				path,
+			],
+			None,
+			None,
+		)?;
+		if output.error() && output.stdout.is_empty() {
+			return Err(output.into());
+		}
+		Ok(parse_disk_usage(Arg::as_str(&output.stdout)?, 1024))
+	}
+
+	/// Returns the space of the filesystem containing the given path, using `df`
+	pub fn disk_free(&self, path: &str) -> Result<DiskUsage> {
+		let output = self.exec(
+			vec![
+				"df", path,
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		parse_df(Arg::as_str(&output.stdout)?).ok_or(Error::ParseInputError)
+	}
+
+	/// List the given path using the typed options, parsing the listed entries
+	pub fn ls_opts<T: Arg>(&self, path: T, opts: LsOptions) -> Result<Vec<DirEntry>> {
+		let mut args = vec!["ls".as_os_str()];
+		args.extend(opts);
+		args.push(path.as_str()?.as_os_str());
+
+		let output = self.exec(args, None, None)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_ls(Arg::as_str(&output.stdout)?, &opts))
+	}
+
+	/// List the content of the given directory (hidden files included) using `ls -pALF`
+	pub fn list_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+		self.ls_opts(
+			path,
+			LsOptions {
+				all: true,
+				long: true,
+				classify: true,
+				..Default::default()
+			},
+		)
+	}
+
 	pub fn exists<T: Arg>(&self, path: T) -> Result<bool> {
 		self.test_file(path, "e")
 	}
 
+	/// Check if each of the paths exists, with a single shell invocation.
+	/// The results are returned in the same order of the paths
+	pub fn exists_many(&self, paths: &[&str]) -> Result<Vec<bool>> {
+		if paths.is_empty() {
+			return Ok(vec![]);
+		}
+		let output = self.exec(vec![make_exists_many_command(paths)], None, None)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		parse_exists_many(Arg::as_str(&output.stdout)?, paths.len())
+	}
+
 	pub fn rm<T: Arg>(&self, path: T, arguments: Vec<&str>) -> Result<()> {
 		let mut args = vec!["rm".as_os_str()];
 		for arg in arguments {
@@ -865,6 +1942,25 @@ impl<'a> Shell<'a> {
 		Ok(output)
 	}
 
+	/// Returns true if the given service is listed by `dumpsys -l`
+	pub fn has_service(&self, name: &str) -> Result<bool> {
+		Ok(self.dumpsys_list(false, None)?.iter().any(|service| service == name))
+	}
+
+	/// Same as [`Shell::dumpsys`] for a single service, which is first looked up in the list of the
+	/// available services, returning [`Error::ServiceNotFound`] if it is missing.
+	pub fn dumpsys_verified(
+		&self,
+		service: &str,
+		arguments: Option<Vec<String>>,
+		timeout: Option<Duration>,
+	) -> Result<Output> {
+		if !self.has_service(service)? {
+			return Err(Error::ServiceNotFound(service.to_string()));
+		}
+		self.dumpsys(Some(service), arguments, timeout, false, false, false, None)
+	}
+
 	///
 	/// usage: dumpsys
 	///         To dump all services.
@@ -882,6 +1978,9 @@ impl<'a> Shell<'a> {
 	///               LEVEL must be one of CRITICAL | HIGH | NORMAL
 	///         --skip SERVICES: dumps all services but SERVICES (comma-separated list)
 	///         SERVICE \[ARGS\]: dumps only service SERVICE, optionally passing ARGS to it
+	///
+	/// When a `timeout` is given and the service does not return in time, the dumpsys process is killed
+	/// and [`Error::DumpsysTimeout`] is returned with whatever output was captured so far.
 	pub fn dumpsys(
 		&self,
 		service: Option<&str>,
@@ -920,7 +2019,8 @@ impl<'a> Shell<'a> {
 			}
 		}
 
-		self.exec(args, None, None)
+		let output = self.exec_killable(args, timeout.map(|t| t + DUMPSYS_TIMEOUT_GRACE))?;
+		handle_dumpsys_result(service, output)
 	}
 
 	pub fn screen_record<T: Arg>(
@@ -932,29 +2032,83 @@ impl<'a> Shell<'a> {
 		let mut args = vec!["screenrecord".into()];
 
 		if let Some(options) = options {
+			if options.audio {
+				args.push(self.screenrecord_audio_flag()?.into());
+			}
 			args.extend(options);
 		}
 
 		args.push(output.as_str()?.into());
-		let command = CommandBuilder::shell(self.parent).args(args).signal(cancel);
+		let command = AdbCommandBuilder::shell(self.parent).args(args).signal(cancel);
 		Ok(command.build().output()?)
 	}
 
+	/// Record the screen to a temporary file on the device, then pull it to `local`.
+	/// The recording stops when the time limit of the options is reached or the `cancel` channel fires.
+	/// The remote file is always removed, even when the recording or the pull fails.
+	pub fn record_to_local(&self, options: Option<ScreenRecordOptions>, local: &Path, cancel: Option<Receiver<()>>) -> Result<()> {
+		let remote = make_temp_path(".mp4");
+		let result = self.record_and_pull(options, remote.as_str(), local, cancel);
+		let cleanup = self.rm(remote.as_str(), vec!["-f"]);
+		result.and(cleanup)
+	}
+
+	fn record_and_pull(
+		&self,
+		options: Option<ScreenRecordOptions>,
+		remote: &str,
+		local: &Path,
+		cancel: Option<Receiver<()>>,
+	) -> Result<()> {
+		handle_result(self.screen_record(options, remote, cancel)?)?;
+
+		// when interrupted, screenrecord needs a moment to finalize the file
+		let deadline = Instant::now() + SCREENRECORD_FINALIZE_TIMEOUT;
+		while Instant::now() < deadline && !self.pidof("screenrecord")?.is_empty() {
+			std::thread::sleep(Duration::from_millis(200));
+		}
+
+		let output = self.parent.pull(remote, local)?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+
+	/// Returns the flag used by the device screenrecord to capture audio, or
+	/// [`Error::AudioUnsupported`] if audio capture is not available.
+	/// The result is cached per connection.
+	pub fn screenrecord_audio_flag(&self) -> Result<&'static str> {
+		let mut binding = SCREENRECORD_AUDIO_CACHE.lock().unwrap();
+		let cache_key = self.parent.addr.to_string();
+
+		let flag = match binding.cache_get(&cache_key) {
+			Some(flag) => *flag,
+			None => {
+				let output = self.exec(vec!["screenrecord --help 2>&1"], None, None)?;
+				let flag = parse_screenrecord_audio_flag(Arg::as_str(&output.stdout)?);
+				binding.cache_set(cache_key, flag);
+				flag
+			}
+		};
+		flag.ok_or(Error::AudioUnsupported)
+	}
+
+	/// Mirror the device screen piping `screenrecord` to ffplay.
+	/// Fails with [`Error::FFPlayNotFound`] before starting the recording if ffplay isn't available
 	pub fn screen_mirror(
 		&self,
 		screenrecord_options: ScreenRecordOptions,
 		play_options: FFPlayOptions,
 		cancel: Option<Receiver<()>>,
 	) -> Result<Output> {
+		let ffplay = play_options.check_available()?;
 		let screenrecord_arg = format!("screenrecord --output-format=h264 {:} -", screenrecord_options);
 
-		let builder = CommandBuilder::shell(self.parent)
+		let builder = AdbCommandBuilder::shell(self.parent)
 			.args(vec![screenrecord_arg.as_str()])
 			.signal(cancel)
 			.with_debug(true);
 
 		let command1 = builder.build();
-		let mut command2 = std::process::Command::new(which::which("ffplay")?);
+		let mut command2 = std::process::Command::new(ffplay);
 		let ffplay_options = play_options;
 		command2.args(ffplay_options.into_iter());
 		command2.args(&[
@@ -973,6 +2127,196 @@ impl<'a> Shell<'a> {
 		command1.pipe(command2).map_err(|e| Error::from(e))
 	}
 
+	/// Enable or disable the wifi
+	pub fn set_wifi(&self, enabled: bool) -> Result<()> {
+		self.svc("wifi", if enabled { "enable" } else { "disable" })
+	}
+
+	/// Enable or disable the mobile data connectivity
+	pub fn set_mobile_data(&self, enabled: bool) -> Result<()> {
+		self.svc("data", if enabled { "enable" } else { "disable" })
+	}
+
+	/// Control when the device stays awake
+	pub fn stay_awake(&self, mode: StayAwakeMode) -> Result<()> {
+		let mode = mode.to_string();
+		self.svc("power", format!("stayon {mode}").as_str())
+	}
+
+	fn svc(&self, service: &str, command: &str) -> Result<()> {
+		handle_result(self.exec(
+			vec![
+				"svc", service, command,
+			],
+			None,
+			None,
+		)?)
+	}
+
+	/// Post a notification from the shell package, replacing the one with the same tag
+	pub fn post_notification(&self, tag: &str, text: &str) -> Result<()> {
+		let tag = shell_quote(tag);
+		let text = shell_quote(text);
+		handle_result(self.exec(
+			vec![
+				"cmd",
+				"notification",
+				"post",
+				tag.as_str(),
+				text.as_str(),
+			],
+			None,
+			None,
+		)?)
+	}
+
+	/// Cancel a notification posted with [`Shell::post_notification`]
+	pub fn cancel_notification(&self, tag: &str) -> Result<()> {
+		let tag = shell_quote(tag);
+		handle_result(self.exec(
+			vec![
+				"cmd",
+				"notification",
+				"cancel",
+				tag.as_str(),
+			],
+			None,
+			None,
+		)?)
+	}
+
+	/// Returns the keys (`user|package|id|tag|uid`) of the active notifications
+	pub fn notification_list(&self) -> Result<Vec<String>> {
+		let output = self.exec(
+			vec![
+				"dumpsys",
+				"notification",
+				"--noredact",
+			],
+			None,
+			None,
+		)?;
+		if output.error() {
+			return Err(output.into());
+		}
+		Ok(parse_notification_keys(Arg::as_str(&output.stdout)?))
+	}
+
+	/// Set the lock screen PIN. `old` is the current credential, if any.
+	/// Root is usually required.
+	pub fn set_pin(&self, pin: &str, old: Option<&str>) -> Result<()> {
+		handle_result(self.exec(make_locksettings_args("set-pin", Some(pin), old), None, None)?)
+	}
+
+	/// Set the lock screen password. `old` is the current credential, if any.
+	/// Root is usually required.
+	pub fn set_password(&self, password: &str, old: Option<&str>) -> Result<()> {
+		handle_result(self.exec(make_locksettings_args("set-password", Some(password), old), None, None)?)
+	}
+
+	/// Remove the lock screen credential, disabling the lock screen. `old` is the current credential, if any.
+	/// Root is usually required.
+	pub fn clear_lock(&self, old: Option<&str>) -> Result<()> {
+		handle_result(self.exec(make_locksettings_args("clear", None, old), None, None)?)
+	}
+
+	/// Returns the battery state, parsing the `dumpsys battery` output
+	pub fn battery_info(&self) -> Result<BatteryInfo> {
+		let output = self.exec(
+			vec![
+				"dumpsys", "battery",
+			],
+			None,
+			None,
+		)?;
+		parse_battery_info(Arg::as_str(&output.stdout)?)
+	}
+
+	/// Simulate the given battery level, until [`Shell::reset_battery`] is called
+	pub fn set_battery_level(&self, level: u8) -> Result<()> {
+		let level = level.to_string();
+		handle_result(self.exec(
+			vec![
+				"dumpsys",
+				"battery",
+				"set",
+				"level",
+				level.as_str(),
+			],
+			None,
+			None,
+		)?)
+	}
+
+	/// Reset the battery state to the one reported by the hardware
+	pub fn reset_battery(&self) -> Result<()> {
+		handle_result(self.exec(
+			vec![
+				"dumpsys", "battery", "reset",
+			],
+			None,
+			None,
+		)?)
+	}
+
+	/// Returns the display size (width, height), taking into account the override size, if any
+	pub fn get_display_size(&self) -> Result<(u32, u32)> {
+		let output = self.exec(
+			vec![
+				"wm", "size",
+			],
+			None,
+			None,
+		)?;
+		parse_wm_size(Arg::as_str(&output.stdout)?).ok_or(Error::ParseInputError)
+	}
+
+	/// Override the display size
+	pub fn set_display_size(&self, width: u32, height: u32) -> Result<()> {
+		let size = format!("{width}x{height}");
+		self.wm(&["size", size.as_str()])
+	}
+
+	/// Reset the display size to the physical size
+	pub fn reset_display_size(&self) -> Result<()> {
+		self.wm(&["size", "reset"])
+	}
+
+	/// Returns the display density (dpi), taking into account the override density, if any
+	pub fn get_display_density(&self) -> Result<u32> {
+		let output = self.exec(
+			vec![
+				"wm", "density",
+			],
+			None,
+			None,
+		)?;
+		parse_wm_density(Arg::as_str(&output.stdout)?).ok_or(Error::ParseInputError)
+	}
+
+	/// Returns the density bucket of the display density, see [`Shell::get_display_density`]
+	pub fn density_bucket(&self) -> Result<DensityBucket> {
+		Ok(DensityBucket::from(self.get_display_density()?))
+	}
+
+	/// Override the display density
+	pub fn set_display_density(&self, dpi: u32) -> Result<()> {
+		let dpi = dpi.to_string();
+		self.wm(&["density", dpi.as_str()])
+	}
+
+	/// Reset the display density to the physical density
+	pub fn reset_display_density(&self) -> Result<()> {
+		self.wm(&["density", "reset"])
+	}
+
+	fn wm(&self, args: &[&str]) -> Result<()> {
+		let mut command = vec!["wm"];
+		command.extend(args);
+		let output = self.exec(command, None, None)?;
+		if output.error() { Err(output.into()) } else { Ok(()) }
+	}
+
 	pub fn save_screencap<T>(&self, path: T) -> Result<Output>
 	where
 		T: Arg,
@@ -988,6 +2332,11 @@ impl<'a> Shell<'a> {
 		)
 	}
 
+	/// Returns the device identity, reading all the properties with a single `getprop` call
+	pub fn device_info(&self) -> Result<DeviceInfo> {
+		parse_device_info(&self.getprops()?)
+	}
+
 	ro_build_property!(build_ab_update, "ro.build.ab_update", String);
 	ro_build_property!(build_characteristics, "ro.build.characteristics", String);
 	ro_build_property!(build_date, "ro.build.date", String);
@@ -1015,6 +2364,53 @@ impl<'a> Shell<'a> {
 		}
 	}
 
+	/// Returns the state of the given init service (ie. `adbd`, `bootanim`)
+	pub fn service_state(&self, name: &str) -> Result<ServiceState> {
+		Ok(ServiceState::from(self.getprop(format!("init.svc.{name}").as_str())?.as_str()))
+	}
+
+	/// Wait for the given init service to reach `state`, failing with a timeout error when `timeout` expires
+	pub fn wait_for_service(&self, name: &str, state: ServiceState, timeout: Duration) -> Result<()> {
+		let key = format!("init.svc.{name}");
+		self.poll_prop(key.as_str(), |value| ServiceState::from(value) == state, timeout, PROP_POLL_INTERVAL)
+	}
+
+	/// Wait for the property `key` to be equal to `expected`, reading it every `poll`.
+	/// Returns [`Error::Timeout`] if the value doesn't match when `timeout` expires.
+	///
+	/// # Example
+	///
+	/// ```rust,no_run
+	/// use std::time::Duration;
+	/// use radb_client::types::Client;
+	///
+	/// fn wait_for_boot(client: &Client) {
+	///     client
+	///         .shell()
+	///         .wait_for_prop("sys.boot_completed", "1", Duration::from_secs(60), Duration::from_millis(500))
+	///         .expect("device not booted");
+	/// }
+	/// ```
+	pub fn wait_for_prop(&self, key: &str, expected: &str, timeout: Duration, poll: Duration) -> Result<()> {
+		self.poll_prop(key, |value| value == expected, timeout, poll)
+	}
+
+	/// Poll the given property until `predicate` accepts its value or the timeout expires
+	fn poll_prop<F: Fn(&str) -> bool>(&self, key: &str, predicate: F, timeout: Duration, poll: Duration) -> Result<()> {
+		let start = Instant::now();
+		while !predicate(self.getprop(key)?.as_str()) {
+			let elapsed = start.elapsed();
+			if elapsed > timeout {
+				return Err(Error::Timeout {
+					command: format!("getprop {key}"),
+					elapsed,
+				});
+			}
+			std::thread::sleep(poll);
+		}
+		Ok(())
+	}
+
 	pub fn getprop(&self, key: &str) -> Result<String> {
 		let result = self
 			.exec(
@@ -1101,6 +2497,35 @@ impl<'a> Shell<'a> {
 		self.setprop(key, "")
 	}
 
+	/// Saves all the properties (or only those starting with `prefix`), to be later
+	/// re-applied with [`Shell::restore_props`]
+	pub fn snapshot_props(&self, prefix: Option<&str>) -> Result<PropSnapshot> {
+		let props = self
+			.getprops()?
+			.into_iter()
+			.filter(|prop| prefix.is_none_or(|prefix| prop.key.starts_with(prefix)))
+			.map(|prop| (prop.key, prop.value))
+			.collect();
+		Ok(PropSnapshot {
+			prefix: prefix.map(|s| s.to_string()),
+			props,
+		})
+	}
+
+	/// Re-applies the properties of the given snapshot, clearing those added since.
+	/// Only the changed properties are written.
+	/// Returns the keys which couldn't be restored (e.g. `ro.*` properties)
+	pub fn restore_props(&self, snapshot: &PropSnapshot) -> Result<Vec<String>> {
+		let current = self.snapshot_props(snapshot.prefix.as_deref())?;
+		let mut failed = Vec::new();
+		for (key, value) in diff_props(snapshot, &current) {
+			if self.setprop(key.as_str(), value.as_str()).is_err() || self.getprop(key.as_str())? != value {
+				failed.push(key);
+			}
+		}
+		Ok(failed)
+	}
+
 	pub fn getprop_type(&self, key: &str) -> Result<PropType> {
 		self.exec(
 			vec![
@@ -1113,6 +2538,36 @@ impl<'a> Shell<'a> {
 		.map(|s| PropType::try_from(s))?
 	}
 
+	/// Returns the value of the given property, parsed according to its declared type
+	pub fn get_typed_prop(&self, key: &str) -> Result<PropValue> {
+		let prop_type = self.getprop_type(key)?;
+		parse_prop_value(&self.getprop(key)?, &prop_type)
+	}
+
+	/// Returns the current device locale, reading `persist.sys.locale` and falling back to
+	/// `ro.product.locale` when not set
+	pub fn get_locale(&self) -> Result<String> {
+		let locale = self.getprop(PROP_PERSIST_LOCALE)?;
+		if locale.is_empty() {
+			self.getprop(PROP_PRODUCT_LOCALE)
+		} else {
+			Ok(locale)
+		}
+	}
+
+	/// Change the device locale by setting `persist.sys.locale` and the `system_locales` system setting.
+	/// Returns true when the device predates the `system_locales` setting (api 24), meaning a reboot
+	/// is required for the new locale to be applied.
+	pub fn set_locale(&self, lang: &str, country: &str) -> Result<bool> {
+		let tag = make_locale_tag(lang, country);
+		self.setprop(PROP_PERSIST_LOCALE, tag.as_str())?;
+		if self.build_version_sdk()? < SYSTEM_LOCALES_MIN_SDK {
+			return Ok(true);
+		}
+		self.put_setting(SettingsType::system, SETTING_SYSTEM_LOCALES, tag)?;
+		Ok(false)
+	}
+
 	pub fn am(&self) -> ActivityManager {
 		ActivityManager { parent: self }
 	}
@@ -1120,20 +2575,44 @@ impl<'a> Shell<'a> {
 	pub fn pm(&self) -> PackageManager {
 		PackageManager { parent: self }
 	}
+
+	pub fn content(&self) -> ContentProvider<'_> {
+		ContentProvider { parent: self }
+	}
 }
 
 #[cfg(test)]
 mod test {
-	use std::time::Duration;
-
+	use std::ffi::OsString;
+	use std::net::Ipv4Addr;
+	use std::os::unix::fs::PermissionsExt;
+	use std::path::PathBuf;
+	use std::str::FromStr;
+	use std::time::{Duration, Instant};
+
+	use cached::Cached;
+	use mac_address::MacAddress;
+	use rustix::path::Arg;
+	use simple_cmd::CommandBuilder;
 	use simple_cmd::prelude::OutputExt;
 	use strum::IntoEnumIterator;
 
+	use crate::error::Error;
+	use crate::shell::{
+		COMMANDS_CACHE, diff_props, handle_dumpsys_result, handle_timeout, make_batch_command, make_exists_many_command,
+		make_input, make_keyevent, make_locale_tag, make_locksettings_args, make_long_press, make_tap, parse_arp_table,
+		parse_battery_info, parse_cpu_rows, parse_default_input_source, parse_device_info, parse_df, parse_disk_usage,
+		parse_dumpsys_value, parse_exists_many, parse_keyboard_visible, parse_legacy_processes, parse_loadavg, parse_ls,
+		parse_monkey_output, parse_notification_keys, parse_package_pids, parse_processes, parse_prop_value,
+		parse_screenrecord_audio_flag, parse_threads, parse_top, parse_wm_density, parse_wm_size, split_batch_output,
+	};
 	use crate::test::test::*;
 	use crate::types::KeyCode::{KEYCODE_1, KEYCODE_2, KEYCODE_3, KEYCODE_DPAD_DOWN, KEYCODE_DPAD_RIGHT, KEYCODE_HOME};
 	use crate::types::{
-		DumpsysPriority, InputSource, Intent, KeyCode, MotionEvent, PropType, RebootType, SELinuxType, ScreenRecordOptions,
-		SettingsType,
+		Adb, ArpEntry, BatteryHealth, BatteryStatus, Client, ClientConfig, ConnectionType, CpuEntry, DensityBucket, DeviceInfo,
+		DiskUsage, DumpsysPriority, FFPlayOptions, InputSource, Intent, KeyCode, LoadAverage, LsOptions, LsSort, MotionEvent,
+		PluggedSource, ProcessInfo, PropSnapshot, PropType, PropValue, Property, RebootType, SELinuxType, ScreenRecordOptions,
+		ServiceState, SettingsType, Shell, StayAwakeMode,
 	};
 
 	#[test]
@@ -1145,6 +2624,19 @@ mod test {
 		assert!(!whoami.is_empty());
 	}
 
+	#[test]
+	fn test_cat_to_writer() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let expected = shell.cat("/system/build.prop").expect("failed to cat file");
+
+		let mut buffer = vec![];
+		let copied = shell.cat_to_writer("/system/build.prop", &mut buffer).expect("failed to stream file");
+		assert_eq!(expected.len() as u64, copied);
+		assert_eq!(expected, buffer);
+	}
+
 	#[test]
 	fn test_is_root() {
 		init_log();
@@ -1205,6 +2697,28 @@ mod test {
 		assert_eq!("/system/bin/sh", path);
 	}
 
+	#[test]
+	fn test_commands_cache_size() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let commands = [
+			"sh", "ls", "cat", "ps", "pm", "am", "wm", "dumpsys", "getprop", "setprop", "input", "screencap",
+		];
+		Shell::set_commands_cache_size(commands.len());
+		for command in commands {
+			assert!(shell.command_exists(command), "{command} not found");
+		}
+		assert!(!shell.command_exists("bogus_command"));
+		assert_eq!(commands.len(), COMMANDS_CACHE.lock().unwrap().cache_size());
+
+		let hits = COMMANDS_CACHE.lock().unwrap().cache_hits().unwrap_or_default();
+		for command in commands.iter().skip(1) {
+			assert!(shell.command_exists(command));
+		}
+		assert_eq!(hits + commands.len() as u64 - 1, COMMANDS_CACHE.lock().unwrap().cache_hits().unwrap_or_default());
+	}
+
 	#[test]
 	fn test_which() {
 		init_log();
@@ -1247,7 +2761,7 @@ mod test {
 
 		client.reboot(None).expect("failed to reboot device");
 		client
-			.wait_for_device(Some(Duration::from_secs(120)))
+			.wait_for_device(Some(Duration::from_secs(120)), false)
 			.expect("failed to wait for device");
 
 		client.root().expect("failed to root");
@@ -1324,6 +2838,390 @@ mod test {
 		println!("events: {:#?}", events);
 	}
 
+	#[test]
+	fn test_parse_default_input_source() {
+		let touch = r#"add device 1: /dev/input/event2
+  name:     "virtio_input_multi_touch_1"
+  events:
+    ABS (0003): 0000  : value 0, min 0, max 32767, fuzz 0, flat 0, resolution 0
+                0001  : value 0, min 0, max 32767, fuzz 0, flat 0, resolution 0
+                002f  : value 0, min 0, max 9, fuzz 0, flat 0, resolution 0
+                0035  : value 0, min 0, max 32767, fuzz 0, flat 0, resolution 0
+                0036  : value 0, min 0, max 32767, fuzz 0, flat 0, resolution 0
+  input props:
+    INPUT_PROP_DIRECT
+"#;
+		let keys = r#"add device 2: /dev/input/event0
+  name:     "gpio-keys"
+  events:
+    KEY (0001): 0035  0066  0067  0069  006a  006c  0072  0073
+                0074  008b  009e  00d4
+    SW  (0005): 0000
+  input props:
+    <none>
+add device 3: /dev/input/event1
+  name:     "sensor"
+  events:
+    REL (0002): 0035
+    ABS (0003): 0000  : value 0, min -1000, max 1000, fuzz 0, flat 0, resolution 0
+                0001  : value 0, min -1000, max 1000, fuzz 0, flat 0, resolution 0
+"#;
+		assert_eq!(InputSource::touchscreen, parse_default_input_source(touch));
+		assert_eq!(InputSource::touchscreen, parse_default_input_source(&format!("{keys}{touch}")));
+		assert_eq!(InputSource::dpad, parse_default_input_source(keys));
+		assert_eq!(InputSource::dpad, parse_default_input_source(""));
+	}
+
+	#[test]
+	fn test_service_state() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		assert_eq!(ServiceState::Running, shell.service_state("adbd").expect("failed to get service state"));
+		assert_eq!(ServiceState::Unknown, shell.service_state("radb_none").expect("failed to get service state"));
+		shell
+			.wait_for_service("adbd", ServiceState::Running, Duration::from_secs(1))
+			.expect("failed to wait for adbd");
+		shell
+			.wait_for_service("radb_none", ServiceState::Running, Duration::from_millis(500))
+			.expect_err("unknown service should time out");
+	}
+
+	#[test]
+	fn test_wait_for_prop() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		shell
+			.wait_for_prop("sys.boot_completed", "1", Duration::from_secs(1), Duration::from_millis(100))
+			.expect("device not booted");
+		let err = shell
+			.wait_for_prop("radb.none", "1", Duration::from_millis(300), Duration::from_millis(100))
+			.expect_err("missing prop should time out");
+		assert!(matches!(err, Error::Timeout { .. }));
+	}
+
+	#[test]
+	fn test_default_input_source() {
+		init_log();
+		let client = connect_emulator();
+		let source = client.shell().default_input_source().expect("failed to get the default input source");
+		println!("source: {source:?}");
+	}
+
+	#[test]
+	fn test_parse_package_pids() {
+		let ps = "  PID NAME\n  1 init\n 1200 com.example\n 1201 com.example:remote\n 1202 com.example.other\n";
+		assert_eq!(
+			vec![
+				1200, 1201,
+			],
+			parse_package_pids(ps, "com.example")
+		);
+		assert!(parse_package_pids(ps, "com.missing").is_empty());
+	}
+
+	#[test]
+	fn test_parse_processes() {
+		let output = "  PID  PPID USER            RSS S NAME
+    1     0 root          10864 S init
+  620     1 system         4080 S servicemanager
+ 1200   580 u0_a45       120040 R com.example.app:remote
+";
+		let processes = parse_processes(output);
+		assert_eq!(3, processes.len());
+		assert_eq!(
+			ProcessInfo {
+				pid: 1200,
+				ppid: 580,
+				user: "u0_a45".to_string(),
+				name: "com.example.app:remote".to_string(),
+				rss_kb: 120040,
+				state: 'R',
+			},
+			processes[2]
+		);
+
+		let output = "USER     PID   PPID  VSIZE  RSS     WCHAN    PC        NAME
+root      1     0     8904   788   ffffffff 00000000 S /init
+u0_a45    1200  580   912340 60312 ffffffff 00000000 S com.example.app
+";
+		let processes = parse_legacy_processes(output);
+		assert_eq!(2, processes.len());
+		assert_eq!(1, processes[0].pid);
+		assert_eq!("/init", processes[0].name);
+		assert_eq!(580, processes[1].ppid);
+		assert_eq!(60312, processes[1].rss_kb);
+		assert_eq!('S', processes[1].state);
+	}
+
+	#[test]
+	fn test_exists_many_command() {
+		assert_eq!(
+			r#"for p in '/system' '/my dir/it'\''s'; do test -e "$p" && echo 1 || echo 0; done"#,
+			make_exists_many_command(&["/system", "/my dir/it's"])
+		);
+		assert_eq!(vec![true, false, true], parse_exists_many("1\n0\n1\n", 3).unwrap());
+		assert!(parse_exists_many("1\n0\n", 3).is_err());
+		assert!(parse_exists_many("1\nx\n", 2).is_err());
+	}
+
+	#[test]
+	fn test_exists_many() {
+		init_log();
+		let client = connect_emulator();
+		let result = client
+			.shell()
+			.exists_many(&["/system/build.prop", "/invalid/path", "/sdcard", "/system/missing file"])
+			.expect("failed to check paths");
+		assert_eq!(vec![true, false, true, false], result);
+		assert!(client.shell().exists_many(&[]).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_parse_top() {
+		// toybox
+		let output = "Tasks: 312 total,   1 running, 311 sleeping,   0 stopped,   0 zombie
+  Mem:  2013724K total,  1889260K used,   124464K free,    12180K buffers
+800%cpu  12%user   0%nice  10%sys 778%idle   0%iow   0%irq   0%sirq   0%host
+  PID USER         PR  NI VIRT  RES  SHR S[%CPU] %MEM     TIME+ ARGS
+ 4521 u0_a145      10 -10  14G 152M  98M S 12.5   7.7   0:03.12 com.example.app
+ 4600 u0_a145      20   0  13G  80M  60M S  1.0   4.0   0:00.40 com.example.app:remote
+  612 system       18  -2  15G 260M 180M S  0.0  13.2  12:40.55 system_server
+";
+		let entries = parse_top(output);
+		assert_eq!(3, entries.len());
+		assert_eq!(
+			CpuEntry {
+				pid: 4521,
+				user: "u0_a145".to_string(),
+				cpu_percent: 12.5,
+				mem_percent: Some(7.7),
+				name: "com.example.app".to_string(),
+			},
+			entries[0]
+		);
+		assert_eq!("com.example.app:remote", entries[1].name);
+		assert_eq!(Some(13.2), entries[2].mem_percent);
+
+		// legacy toolbox: rows without the PCY column (kernel threads) are skipped
+		let output = "User 5%, System 3%, IOW 0%, IRQ 0%
+
+  PID PR CPU% S  #THR     VSS     RSS PCY UID      Name
+ 1200  0   4% S    38 912340K  60312K  fg u0_a45   com.example.app
+    2  0   0% S     1      0K      0K     root     kthreadd
+  580  1   1% S   102 998012K  90112K  fg system   system_server
+";
+		let entries = parse_top(output);
+		assert_eq!(2, entries.len());
+		assert_eq!(1200, entries[0].pid);
+		assert_eq!("u0_a45", entries[0].user);
+		assert_eq!(4.0, entries[0].cpu_percent);
+		assert_eq!(None, entries[0].mem_percent);
+		assert_eq!("system_server", entries[1].name);
+
+		// busybox
+		let output = "Mem: 1889260K used, 124464K free, 0K shrd, 12180K buff, 500000K cached
+  PID  PPID USER     STAT   VSZ %VSZ CPU %CPU COMMAND
+ 1200   580 u0_a45   S     891m  45%   1   3% com.example.app
+";
+		let entries = parse_top(output);
+		assert_eq!(1, entries.len());
+		assert_eq!(3.0, entries[0].cpu_percent);
+		assert_eq!("com.example.app", entries[0].name);
+
+		assert!(parse_top("").is_empty());
+	}
+
+	#[test]
+	fn test_cpu_usage() {
+		init_log();
+		let client = connect_emulator();
+		let entries = client.shell().cpu_usage(None).expect("failed to get cpu usage");
+		assert!(entries.iter().any(|entry| entry.name.contains("system_server")));
+
+		let entries = client
+			.shell()
+			.cpu_usage(Some("system_server"))
+			.expect("failed to get cpu usage");
+		assert!(entries.iter().all(|entry| entry.name == "system_server"));
+	}
+
+	#[test]
+	fn test_list_processes() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let processes = shell.list_processes().expect("failed to list processes");
+		assert!(processes.iter().any(|p| p.name == "system_server"));
+
+		let pids = shell.pidof("system_server").expect("failed to get pids");
+		assert_eq!(1, pids.len());
+		assert!(shell.pidof("bogus_process").expect("failed to get pids").is_empty());
+	}
+
+	#[test]
+	fn test_pid_of_package() {
+		init_log();
+		let client = connect_emulator();
+		let pids = client
+			.shell()
+			.pid_of_package("com.android.systemui")
+			.expect("failed to get pids");
+		println!("pids: {pids:?}");
+		assert!(!pids.is_empty());
+	}
+
+	#[test]
+	fn test_parse_loadavg() {
+		let load = parse_loadavg("0.52 0.58 0.59 2/1205 8763\n").unwrap();
+		assert_eq!(
+			LoadAverage {
+				one: 0.52,
+				five: 0.58,
+				fifteen: 0.59,
+				running: 2,
+				total: 1205,
+			},
+			load
+		);
+		assert!(parse_loadavg("").is_none());
+		assert!(parse_loadavg("0.52 0.58 0.59").is_none());
+		assert!(parse_loadavg("cat: /proc/loadavg: Permission denied").is_none());
+	}
+
+	#[test]
+	fn test_load_average() {
+		init_log();
+		let client = connect_emulator();
+		let load = client.shell().load_average().expect("failed to get load average");
+		println!("load average: {load:?}");
+		assert!(load.total > 0);
+		assert!(load.running <= load.total);
+	}
+
+	#[test]
+	fn test_parse_arp_table() {
+		let output = "IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         a4:91:b1:0c:22:7e     *        wlan0
+192.168.1.42     0x1         0x0         00:00:00:00:00:00     *        wlan0
+10.0.2.2         0x1         0x6         52:54:00:12:35:02     *        eth0
+";
+		assert_eq!(
+			vec![
+				ArpEntry {
+					ip: Ipv4Addr::new(192, 168, 1, 1),
+					mac: MacAddress::new([0xa4, 0x91, 0xb1, 0x0c, 0x22, 0x7e]),
+					device: "wlan0".to_string(),
+					flags: 2,
+				},
+				ArpEntry {
+					ip: Ipv4Addr::new(10, 0, 2, 2),
+					mac: MacAddress::new([0x52, 0x54, 0x00, 0x12, 0x35, 0x02]),
+					device: "eth0".to_string(),
+					flags: 6,
+				},
+			],
+			parse_arp_table(output)
+		);
+		assert!(parse_arp_table("IP address       HW type     Flags       HW address            Mask     Device\n").is_empty());
+	}
+
+	#[test]
+	fn test_arp_table() {
+		init_log();
+		let client = connect_emulator();
+		let entries = client.shell().arp_table().expect("failed to read the arp table");
+		println!("arp: {entries:?}");
+	}
+
+	#[test]
+	fn test_parse_cpu_rows() {
+		let before = "cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 50 0 25 400 25 0 0 0 0 0\nintr 1234\n";
+		let after = "cpu  200 0 100 1000 100 0 0 0 0 0\ncpu0 150 0 25 400 25 0 0 0 0 0\ncpu1 1 1 1 1 1 1 1 1 0 0\nintr 1300\n";
+		let rows = parse_cpu_rows(before, after);
+		assert_eq!(2, rows.len());
+
+		// 100 user + 50 system + 200 idle + 50 iowait = 400 jiffies
+		assert_eq!("cpu", rows[0].name);
+		assert_eq!(37.5, rows[0].usage_percent);
+		assert_eq!(25.0, rows[0].user_percent);
+		assert_eq!(12.5, rows[0].system_percent);
+		assert_eq!(12.5, rows[0].iowait_percent);
+		assert_eq!(50.0, rows[0].idle_percent);
+
+		assert_eq!("cpu0", rows[1].name);
+		assert_eq!(100.0, rows[1].usage_percent);
+
+		// no elapsed jiffies
+		let rows = parse_cpu_rows(before, before);
+		assert!(rows.iter().all(|row| row.usage_percent == 0.0));
+	}
+
+	#[test]
+	fn test_monitor_cpu() {
+		init_log();
+		let client = connect_emulator();
+		let mut samples = vec![];
+		client
+			.shell()
+			.monitor_cpu(
+				Duration::from_millis(500),
+				|rows| {
+					samples.push(rows);
+					samples.len() < 2
+				},
+				None,
+			)
+			.expect("failed to monitor cpu");
+		assert_eq!(2, samples.len());
+		assert!(samples.iter().all(|rows| rows.first().map(|row| row.name.as_str()) == Some("cpu")));
+	}
+
+	#[test]
+	fn test_parse_threads() {
+		let before = "\
+1234 (m.example.app) S 1 1234 0 0 -1 1077952832 30188 0 3 0 120 40 0 0 10 -10 23 0 1032 1 2 3\n\
+1240 (Signal Catcher) S 1 1234 0 0 -1 1077952832 30 0 0 0 0 0 0 0 0 0 23 0 1032 1 2 3\n\
+1250 (worker (1)) R 1 1234 0 0 -1 1077952832 30 0 0 0 200 10 0 0 10 0 23 0 1032 1 2 3\n\
+1260 (gone) S 1 1234 0 0 -1 1077952832 30 0 0 0 5 5 0 0 10 0 23 0 1032 1 2 3\n";
+		let after = "\
+1234 (m.example.app) S 1 1234 0 0 -1 1077952832 30188 0 3 0 121 40 0 0 10 -10 23 0 1032 1 2 3\n\
+1240 (Signal Catcher) S 1 1234 0 0 -1 1077952832 30 0 0 0 0 0 0 0 0 0 23 0 1032 1 2 3\n\
+1250 (worker (1)) R 1 1234 0 0 -1 1077952832 30 0 0 0 240 20 0 0 10 0 23 0 1032 1 2 3\n\
+1270 (new) D 1 1234 0 0 -1 1077952832 30 0 0 0 3 2 0 0 10 0 23 0 1032 1 2 3\n";
+
+		let threads = parse_threads(before, after, Duration::from_secs(1));
+		assert_eq!(4, threads.len());
+
+		assert_eq!(1234, threads[0].tid);
+		assert_eq!("m.example.app", threads[0].name);
+		assert_eq!('S', threads[0].state);
+		assert_eq!(1.0, threads[0].cpu_percent);
+
+		assert_eq!("Signal Catcher", threads[1].name);
+		assert_eq!(0.0, threads[1].cpu_percent);
+
+		assert_eq!("worker (1)", threads[2].name);
+		assert_eq!('R', threads[2].state);
+		assert_eq!(50.0, threads[2].cpu_percent);
+
+		assert_eq!(1270, threads[3].tid);
+		assert_eq!('D', threads[3].state);
+		assert_eq!(5.0, threads[3].cpu_percent);
+
+		assert!(threads.iter().all(|t| t.tid != 1260));
+	}
+
+	#[test]
+	fn test_threads() {
+		init_log();
+		let client = connect_emulator();
+		let threads = client.shell().threads(1).expect("failed to get threads");
+		assert!(!threads.is_empty());
+		println!("threads: {threads:#?}");
+	}
+
 	#[test]
 	fn test_file_mode() {
 		init_log();
@@ -1361,6 +3259,44 @@ mod test {
 		println!("setting: {setting:?}");
 	}
 
+	#[test]
+	fn test_watch_setting() {
+		init_log();
+		let client = connect_emulator();
+		let key = "radb_watch_test";
+		client.shell().put_setting(SettingsType::global, key, "0").expect("failed to put setting");
+
+		let (cancel_sender, cancel_receiver) = crossbeam_channel::bounded(1);
+		let writer = {
+			let client = client.clone();
+			std::thread::spawn(move || {
+				std::thread::sleep(Duration::from_secs(1));
+				client.shell().put_setting(SettingsType::global, key, "1").expect("failed to put setting");
+				std::thread::sleep(Duration::from_secs(1));
+				cancel_sender.send(()).unwrap();
+			})
+		};
+
+		let mut changes = vec![];
+		client
+			.shell()
+			.watch_setting(
+				SettingsType::global,
+				key,
+				Duration::from_millis(200),
+				|value| {
+					changes.push(value);
+					true
+				},
+				Some(cancel_receiver),
+			)
+			.expect("failed to watch setting");
+		writer.join().unwrap();
+
+		assert_eq!(vec!["1".to_string()], changes);
+		client.shell().delete_setting(SettingsType::global, key).expect("failed to delete setting");
+	}
+
 	#[test]
 	fn test_put_setting() {
 		init_log();
@@ -1394,31 +3330,252 @@ mod test {
 			.put_setting(SettingsType::global, "my_custom_setting", "1")
 			.expect("failed to put settings");
 
-		let value = client
-			.shell()
-			.get_setting(SettingsType::global, "my_custom_setting")
-			.expect("failed to read settings")
-			.unwrap();
-		assert_eq!("1", value);
+		let value = client
+			.shell()
+			.get_setting(SettingsType::global, "my_custom_setting")
+			.expect("failed to read settings")
+			.unwrap();
+		assert_eq!("1", value);
+
+		client
+			.shell()
+			.delete_setting(SettingsType::global, "my_custom_setting")
+			.expect("failed to delete settings");
+		let value = client
+			.shell()
+			.get_setting(SettingsType::global, "my_custom_setting")
+			.expect("failed to read settings");
+		assert_eq!(None, value);
+	}
+
+	#[test]
+	fn test_ls() {
+		init_log();
+		let client = connect_emulator();
+		let ls = client.shell().ls("/system", None).expect("failed to list dir");
+		assert!(!ls.is_empty());
+		println!("ls: {:?}", ls);
+	}
+
+	#[test]
+	fn test_parse_disk_usage() {
+		let output = "4096\t/data/local/tmp/a\n1048576\t/data/local/tmp/my dir\n1056768\t/data/local/tmp\n";
+		assert_eq!(
+			vec![
+				("/data/local/tmp".to_string(), 1056768),
+				("/data/local/tmp/my dir".to_string(), 1048576),
+				("/data/local/tmp/a".to_string(), 4096),
+			],
+			parse_disk_usage(output, 1)
+		);
+
+		let output = "4\t/data/local/tmp/a\n1032\t/data/local/tmp\ndu: /data/local/tmp/b: Permission denied\n";
+		assert_eq!(
+			vec![
+				("/data/local/tmp".to_string(), 1032 * 1024),
+				("/data/local/tmp/a".to_string(), 4096),
+			],
+			parse_disk_usage(output, 1024)
+		);
+		assert!(parse_disk_usage("", 1).is_empty());
+	}
+
+	#[test]
+	fn test_disk_usage() {
+		init_log();
+		let client = connect_emulator();
+		let entries = client.shell().disk_usage("/system", 1).expect("failed to get disk usage");
+		assert!(!entries.is_empty());
+		assert_eq!("/system", entries[0].0);
+		println!("entries: {entries:?}");
+	}
+
+	#[test]
+	fn test_parse_df() {
+		let output = "Filesystem     1K-blocks    Used Available Use% Mounted on
+/dev/block/dm-5  6082476 1840612   4225480  31% /data
+";
+		assert_eq!(
+			Some(DiskUsage {
+				filesystem: "/dev/block/dm-5".to_string(),
+				total_kb: 6082476,
+				used_kb: 1840612,
+				available_kb: 4225480,
+				use_percent: 31,
+				mounted_on: "/data".to_string(),
+			}),
+			parse_df(output)
+		);
+
+		let output = "Filesystem           1K-blocks      Used Available Use% Mounted on
+/dev/block/platform/soc/7824900.sdhci/by-name/userdata
+                      24498464   9734536  14747544  40% /data
+";
+		let usage = parse_df(output).unwrap();
+		assert_eq!("/dev/block/platform/soc/7824900.sdhci/by-name/userdata", usage.filesystem);
+		assert_eq!(24498464, usage.total_kb);
+		assert_eq!(14747544, usage.available_kb);
+		assert_eq!(40, usage.use_percent);
+		assert_eq!("/data", usage.mounted_on);
+
+		assert!(parse_df("df: /none: No such file or directory\n").is_none());
+	}
+
+	#[test]
+	fn test_disk_free() {
+		init_log();
+		let client = connect_emulator();
+		let usage = client.shell().disk_free("/data/local/tmp").expect("failed to get disk free");
+		println!("usage: {usage:?}");
+		assert!(usage.total_kb > 0);
+		assert!(usage.used_kb + usage.available_kb <= usage.total_kb);
+	}
+
+	#[test]
+	fn test_ls_options() {
+		let args: Vec<OsString> = LsOptions::default().into_iter().collect();
+		assert!(args.is_empty());
+
+		let options = LsOptions {
+			all: true,
+			long: true,
+			recursive: true,
+			classify: true,
+			sort: LsSort::Size,
+			reverse: true,
+		};
+		let args: Vec<OsString> = options.into_iter().collect();
+		assert_eq!(vec!["-AlpFRSr"], args);
+
+		let options = LsOptions {
+			sort: LsSort::Time,
+			..Default::default()
+		};
+		let args: Vec<OsString> = options.into_iter().collect();
+		assert_eq!(vec!["-t"], args);
+	}
+
+	#[test]
+	fn test_parse_ls() {
+		let options = LsOptions {
+			long: true,
+			recursive: true,
+			..Default::default()
+		};
+		let output = "/data/local/tmp:\n\
+			total 12\n\
+			drwxrwx--x 2 shell shell 4096 2024-01-01 08:00 my dir\n\
+			-rw-rw-rw- 1 shell shell 1234 2024-01-01 08:00 file.txt\n\
+			lrwxrwxrwx 1 root  root    11 2024-01-01 08:00 bin -> /system/bin\n\
+			crw-rw-rw- 1 root  root  1,   3 2024-01-01 08:00 null\n\
+			\n\
+			/data/local/tmp/my dir:\n\
+			total 0\n";
+		let entries = parse_ls(output, &options);
+		assert_eq!(4, entries.len());
+		assert_eq!("my dir", entries[0].name);
+		assert_eq!(Some("drwxrwx--x".to_string()), entries[0].mode);
+		assert_eq!(Some("/data/local/tmp".to_string()), entries[0].parent);
+		assert_eq!(Some(1234), entries[1].size);
+		assert_eq!(Some("shell".to_string()), entries[1].owner);
+		assert_eq!("bin", entries[2].name);
+		assert_eq!(Some("/system/bin".to_string()), entries[2].link);
+		assert_eq!("null", entries[3].name);
+		assert_eq!(None, entries[3].size);
+
+		let entries = parse_ls(".hidden\nfile.txt\n", &LsOptions::default());
+		assert_eq!(
+			vec![
+				".hidden", "file.txt",
+			],
+			entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>()
+		);
+		assert!(entries.iter().all(|e| e.mode.is_none() && e.parent.is_none()));
+	}
+
+	#[test]
+	fn test_ls_opts() {
+		init_log();
+		let client = connect_emulator();
+		let options = LsOptions {
+			all: true,
+			long: true,
+			sort: LsSort::Size,
+			..Default::default()
+		};
+		let entries = client.shell().ls_opts("/system", options).expect("failed to list dir");
+		assert!(!entries.is_empty());
+		assert!(entries.iter().all(|entry| entry.mode.is_some()));
+		println!("entries: {entries:?}");
+	}
+
+	#[test]
+	fn test_parse_ls_classify() {
+		let options = LsOptions {
+			all: true,
+			long: true,
+			classify: true,
+			..Default::default()
+		};
+		let output = "total 48
+drwxr-xr-x  2 root   root    4096 2024-03-01 10:15 app/
+-rwxr-x---  1 root   shell  12345 2024-03-01 10:16 run.sh*
+-rw-rw-rw-  1 shell  shell      0 2009-01-01 00:00 my file.txt
+lrwxrwxrwx  1 root   root      11 2024-03-01 10:17 etc -> /system/etc/
+lrw-r--r--  1 root   root       9 2024-03-01 10:17 sh@ -> /bin/mksh*
+crw-rw-rw-  1 root   root   1,   3 2024-03-01 10:00 null
+";
+		let files = parse_ls(output, &options);
+		assert_eq!(6, files.len());
+
+		assert_eq!("app", files[0].name);
+		assert!(files[0].is_dir());
+		assert_eq!(Some(2), files[0].links);
+		assert_eq!(Some(4096), files[0].size);
+		assert_eq!(
+			chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(10, 15, 0),
+			files[0].date
+		);
+
+		assert_eq!("run.sh", files[1].name);
+		assert!(files[1].is_file());
+		assert_eq!(Some("shell".to_string()), files[1].group);
+
+		assert_eq!("my file.txt", files[2].name);
+
+		assert_eq!("etc", files[3].name);
+		assert!(files[3].is_symlink());
+		assert_eq!(Some("/system/etc".to_string()), files[3].link);
+
+		assert_eq!("sh", files[4].name);
+		assert_eq!(Some("/bin/mksh".to_string()), files[4].link);
+
+		assert_eq!("null", files[5].name);
+		assert_eq!(None, files[5].size);
+		assert!(!files[5].is_file() && !files[5].is_dir());
 
-		client
-			.shell()
-			.delete_setting(SettingsType::global, "my_custom_setting")
-			.expect("failed to delete settings");
-		let value = client
-			.shell()
-			.get_setting(SettingsType::global, "my_custom_setting")
-			.expect("failed to read settings");
-		assert_eq!(None, value);
+		let names = parse_ls(
+			"app/\nrun.sh*\n",
+			&LsOptions {
+				classify: true,
+				..Default::default()
+			},
+		);
+		assert_eq!(
+			vec![
+				"app", "run.sh",
+			],
+			names.iter().map(|e| e.name.as_str()).collect::<Vec<_>>()
+		);
 	}
 
 	#[test]
-	fn test_ls() {
+	fn test_list_dir() {
 		init_log();
 		let client = connect_emulator();
-		let ls = client.shell().ls("/system", None).expect("failed to list dir");
-		assert!(!ls.is_empty());
-		println!("ls: {:?}", ls);
+		let files = client.shell().list_dir("/system").expect("failed to list dir");
+		assert!(files.iter().any(|file| file.name == "bin" && file.is_dir()));
+		println!("files: {files:?}");
 	}
 
 	#[test]
@@ -1446,6 +3603,189 @@ mod test {
 		assert!(!dump.stdout.is_empty());
 	}
 
+	#[test]
+	fn test_has_service() {
+		init_log();
+		let client = connect_emulator();
+		assert!(client.shell().has_service("activity").expect("failed to check service"));
+		assert!(!client.shell().has_service("bogus_service").expect("failed to check service"));
+
+		match client.shell().dumpsys_verified("bogus_service", None, None) {
+			Err(Error::ServiceNotFound(name)) => assert_eq!("bogus_service", name),
+			other => panic!("unexpected result: {other:?}"),
+		}
+	}
+
+	#[test]
+	fn test_handle_dumpsys_result() {
+		init_log();
+		let output = CommandBuilder::new("sh")
+			.args(["-c", "echo partial; exec sleep 5"])
+			.timeout(Some(Duration::from_millis(500)))
+			.build()
+			.output()
+			.expect("failed to run command");
+
+		match handle_dumpsys_result(Some("slow"), output) {
+			Err(Error::DumpsysTimeout { service, partial }) => {
+				assert_eq!("slow", service);
+				assert_eq!("partial", Arg::as_str(&partial).unwrap().trim());
+			}
+			other => panic!("unexpected result: {other:?}"),
+		}
+
+		let output = CommandBuilder::new("sh").args(["-c", "echo complete"]).build().output().unwrap();
+		let output = handle_dumpsys_result(Some("fast"), output).expect("unexpected timeout");
+		assert_eq!("complete", Arg::as_str(&output.stdout).unwrap().trim());
+	}
+
+	#[test]
+	fn test_handle_timeout() {
+		init_log();
+		let timeout = Some(Duration::from_millis(300));
+		let started = Instant::now();
+		let output = CommandBuilder::new("sh")
+			.args(["-c", "exec sleep 5"])
+			.timeout(timeout)
+			.build()
+			.output()
+			.expect("failed to run command");
+		match handle_timeout("sleep", started, timeout, output) {
+			Err(Error::Timeout { command, elapsed }) => {
+				assert_eq!("sleep", command);
+				assert!(elapsed >= Duration::from_millis(300));
+			}
+			other => panic!("unexpected result: {other:?}"),
+		}
+
+		// cancelled before the timeout
+		let (sender, receiver) = crossbeam_channel::unbounded();
+		sender.send(()).unwrap();
+		let timeout = Some(Duration::from_secs(5));
+		let started = Instant::now();
+		let output = CommandBuilder::new("sh")
+			.args(["-c", "exec sleep 5"])
+			.timeout(timeout)
+			.signal(Some(receiver))
+			.build()
+			.output()
+			.expect("failed to run command");
+		assert!(handle_timeout("sleep", started, timeout, output).unwrap().kill());
+
+		let output = CommandBuilder::new("sh").args(["-c", "exit 1"]).build().output().unwrap();
+		assert!(handle_timeout("exit", Instant::now(), timeout, output).unwrap().error());
+	}
+
+	#[test]
+	fn test_exec_timeout() {
+		let adb = temp_dir().join("slow_shell_adb.sh");
+		std::fs::write(&adb, "#!/bin/sh\nexec sleep 5\n").expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("127.0.0.1:5555").unwrap(), false);
+		let shell = client.shell();
+
+		match shell.exec(["echo", "hello"], None, Some(Duration::from_millis(300))) {
+			Err(Error::Timeout { command, elapsed }) => {
+				assert_eq!("echo hello", command);
+				assert!(elapsed >= Duration::from_millis(300));
+			}
+			other => panic!("unexpected result: {other:?}"),
+		}
+
+		let client = client.with_config(ClientConfig {
+			default_command_timeout: Some(Duration::from_millis(300)),
+			..ClientConfig::default()
+		});
+		let result = client.shell().send_keyevent(KEYCODE_HOME, None, None);
+		assert!(matches!(result, Err(Error::Timeout { .. })));
+	}
+
+	#[test]
+	fn test_monitor_cpu_dropped_cancel() {
+		let adb = temp_dir().join("proc_stat_adb.sh");
+		std::fs::write(&adb, "#!/bin/sh\necho 'cpu  10 0 10 100 0 0 0 0 0 0'\n").expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("127.0.0.1:5555").unwrap(), false);
+
+		// a dropped sender must not stop the monitor from waiting the interval between the samples
+		let (sender, receiver) = crossbeam_channel::bounded::<()>(1);
+		drop(sender);
+		let start = Instant::now();
+		let mut samples = 0;
+		client
+			.shell()
+			.monitor_cpu(
+				Duration::from_millis(100),
+				|_| {
+					samples += 1;
+					samples < 3
+				},
+				Some(receiver),
+			)
+			.expect("failed to monitor cpu");
+		assert_eq!(3, samples);
+		assert!(start.elapsed() >= Duration::from_millis(300));
+	}
+
+	#[test]
+	fn test_watch_setting_dropped_cancel() {
+		// every `settings get` returns a different value
+		let adb = temp_dir().join("changing_setting_adb.sh");
+		std::fs::write(&adb, "#!/bin/sh\ndate +%s%N\n").expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("127.0.0.1:5555").unwrap(), false);
+
+		// a dropped sender must not stop the watcher from waiting the interval between the polls
+		let (sender, receiver) = crossbeam_channel::bounded::<()>(1);
+		drop(sender);
+		let start = Instant::now();
+		let mut changes = 0;
+		client
+			.shell()
+			.watch_setting(
+				SettingsType::global,
+				"airplane_mode_on",
+				Duration::from_millis(100),
+				|_| {
+					changes += 1;
+					changes < 3
+				},
+				Some(receiver),
+			)
+			.expect("failed to watch setting");
+		assert_eq!(3, changes);
+		assert!(start.elapsed() >= Duration::from_millis(300));
+	}
+
+	#[test]
+	fn test_make_locale_tag() {
+		assert_eq!("en-US", make_locale_tag("en", "us"));
+		assert_eq!("it-IT", make_locale_tag("IT", "IT"));
+		assert_eq!("fr", make_locale_tag("fr", ""));
+	}
+
+	#[test]
+	fn test_locale() {
+		init_log();
+		let client = connect_emulator();
+		root_client(&client);
+		let shell = client.shell();
+		let original = shell.get_locale().expect("failed to get locale");
+		println!("locale: {original}");
+
+		let reboot_required = shell.set_locale("it", "IT").expect("failed to set locale");
+		assert!(!reboot_required);
+		assert_eq!("it-IT", shell.get_locale().expect("failed to get locale"));
+		assert_eq!(
+			Some("it-IT".to_string()),
+			shell.get_setting(SettingsType::system, "system_locales").expect("failed to get setting")
+		);
+
+		let (lang, country) = original.split_once('-').unwrap_or((original.as_str(), ""));
+		shell.set_locale(lang, country).expect("failed to restore locale");
+		assert_eq!(original, shell.get_locale().expect("failed to get locale"));
+	}
+
 	#[test]
 	fn test_screen_record() {
 		init_log();
@@ -1483,6 +3823,80 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn test_record_to_local() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let options = ScreenRecordOptions {
+			timelimit: Some(Duration::from_secs(3)),
+			..Default::default()
+		};
+		let local_file = temp_dir().join("record_to_local.mp4");
+		if local_file.exists() {
+			std::fs::remove_file(&local_file).unwrap();
+		}
+
+		shell
+			.record_to_local(Some(options), local_file.as_path(), None)
+			.expect("failed to record the screen");
+		assert!(std::fs::metadata(&local_file).unwrap().len() > 0);
+		assert_eq!(0, client.cleanup_temp().unwrap());
+		std::fs::remove_file(&local_file).unwrap();
+	}
+
+	#[test]
+	fn test_parse_screenrecord_audio_flag() {
+		let help = "\
+Usage: screenrecord [options] <filename>
+
+Android screenrecord v1.3.  Records the device's display to a .mp4 file.
+
+Options:
+--size WIDTHxHEIGHT
+    Set the video size, e.g. \"1280x720\".  Default is the device's main
+    display resolution (if supported), 1280x720 if not.  For best results,
+    use a size supported by the AVC encoder.
+--bit-rate RATE
+    Set the video bit rate, in bits per second.  Value may be specified as
+    bits or megabits, e.g. '4000000' is equivalent to '4M'.  Default 20Mbps.
+--bugreport
+    Add additional information, such as a timestamp overlay, that is helpful
+    in videos captured to illustrate bugs.
+--time-limit TIME
+    Set the maximum recording time, in seconds.  Default is 180. Set to 0
+    to remove the time limit.
+--verbose
+    Display interesting information on stdout.
+--help
+    Show this message.
+";
+		assert_eq!(None, parse_screenrecord_audio_flag(help));
+
+		let with_audio = format!("{help}--audio\n    Capture the device internal audio too.\n");
+		assert_eq!(Some("--audio"), parse_screenrecord_audio_flag(&with_audio));
+
+		let with_capture_audio = format!("{help}--capture-audio\n    Capture the device internal audio.\n");
+		assert_eq!(Some("--capture-audio"), parse_screenrecord_audio_flag(&with_capture_audio));
+
+		let options = ScreenRecordOptions {
+			audio: true,
+			..ScreenRecordOptions::new()
+		};
+		assert!(!options.to_string().contains("audio"));
+	}
+
+	#[test]
+	fn test_screen_record_audio() {
+		init_log();
+		let client = connect_emulator();
+		match client.shell().screenrecord_audio_flag() {
+			Ok(flag) => println!("audio flag: {flag}"),
+			Err(Error::AudioUnsupported) => println!("audio not supported"),
+			Err(err) => panic!("{err}"),
+		}
+	}
+
 	#[test]
 	fn test_screen_mirror() {
 		init_log();
@@ -1494,6 +3908,164 @@ mod test {
 			.expect("failed to screen mirror");
 	}
 
+	#[test]
+	fn test_screen_mirror_without_ffplay() {
+		let client = Client::new(
+			Adb::from(PathBuf::from("true")),
+			ConnectionType::try_from("127.0.0.1:5555").unwrap(),
+			false,
+		);
+		let play_options = FFPlayOptions {
+			binary: Some(PathBuf::from("/invalid/path/to/ffplay")),
+			..Default::default()
+		};
+		let result = client.shell().screen_mirror(Default::default(), play_options, None);
+		assert!(matches!(result, Err(Error::FFPlayNotFound)));
+	}
+
+	#[test]
+	fn test_svc() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		shell.set_wifi(false).expect("failed to disable wifi");
+		shell.set_wifi(true).expect("failed to enable wifi");
+		shell.set_mobile_data(false).expect("failed to disable mobile data");
+		shell.set_mobile_data(true).expect("failed to enable mobile data");
+		shell.stay_awake(StayAwakeMode::Usb).expect("failed to set stay awake");
+		shell.stay_awake(StayAwakeMode::Off).expect("failed to set stay awake");
+		assert_eq!("true", StayAwakeMode::On.to_string());
+	}
+
+	#[test]
+	fn test_parse_battery_info() {
+		let output = "Current Battery Service state:
+  AC powered: false
+  USB powered: true
+  Wireless powered: false
+  Dock powered: false
+  Max charging current: 500000
+  Max charging voltage: 5000000
+  Charge counter: 10000
+  status: 2
+  health: 2
+  present: true
+  level: 85
+  scale: 100
+  voltage: 4105
+  temperature: 253
+  technology: Li-ion
+";
+		let info = parse_battery_info(output).expect("failed to parse battery info");
+		assert_eq!(85, info.level);
+		assert_eq!(100, info.scale);
+		assert_eq!(BatteryStatus::Charging, info.status);
+		assert_eq!(BatteryHealth::Good, info.health);
+		assert_eq!(PluggedSource::Usb, info.plugged);
+		assert_eq!(25.3, info.temperature);
+		assert_eq!(4105, info.voltage_mv);
+		assert!(info.present);
+
+		assert!(parse_battery_info("Current Battery Service state:\n").is_err());
+	}
+
+	#[test]
+	fn test_parse_notification_keys() {
+		let output = "Current Notification Manager state:
+  Notification List:
+    NotificationRecord(0x0b3c1d2e: pkg=com.android.shell user=UserHandle{0} id=2020 tag=fixture importance=3 key=0|com.android.shell|2020|fixture|2000: Notification(channel=shell_cmd shortcut=null contentView=null vibrate=null sound=null defaults=0x0 flags=0x0 color=0x00000000 vis=PRIVATE))
+      uid=2000 userId=0
+      key=0|com.android.shell|2020|fixture|2000
+    NotificationRecord(0x04a5b6c7: pkg=android user=UserHandle{-1} id=17041 tag=null importance=2 key=-1|android|17041|null|1000: Notification(channel=DEVELOPER_IMPORTANT))
+      uid=1000 userId=-1
+  Snoozed notifications:
+    NotificationRecord(0x0b3c1d2e: pkg=com.android.shell user=UserHandle{0} id=2020 tag=fixture importance=3 key=0|com.android.shell|2020|fixture|2000: Notification(channel=shell_cmd))
+";
+		assert_eq!(
+			vec![
+				"0|com.android.shell|2020|fixture|2000".to_string(),
+				"-1|android|17041|null|1000".to_string(),
+			],
+			parse_notification_keys(output)
+		);
+		assert!(parse_notification_keys("Current Notification Manager state:\n").is_empty());
+	}
+
+	#[test]
+	fn test_notifications() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		shell.post_notification("radb_test", "hello world").expect("failed to post notification");
+		let keys = shell.notification_list().expect("failed to list notifications");
+		println!("keys: {keys:?}");
+		assert!(keys.iter().any(|key| key.contains("|radb_test|")));
+		shell.cancel_notification("radb_test").expect("failed to cancel notification");
+	}
+
+	#[test]
+	fn test_make_locksettings_args() {
+		assert_eq!(
+			vec!["locksettings", "set-pin", "'1234'"],
+			make_locksettings_args("set-pin", Some("1234"), None)
+		);
+		assert_eq!(
+			vec!["locksettings", "set-password", "--old", "'1234'", "'it'\\''s secret'"],
+			make_locksettings_args("set-password", Some("it's secret"), Some("1234"))
+		);
+		assert_eq!(vec!["locksettings", "clear", "--old", "'1234'"], make_locksettings_args("clear", None, Some("1234")));
+	}
+
+	#[test]
+	fn test_lock_settings() {
+		init_log();
+		let client = connect_emulator();
+		root_client(&client);
+		let shell = client.shell();
+		shell.set_pin("1234", None).expect("failed to set pin");
+		assert!(shell.set_pin("5678", Some("0000")).is_err());
+		shell.set_password("secret", Some("1234")).expect("failed to set password");
+		shell.clear_lock(Some("secret")).expect("failed to clear lock");
+	}
+
+	#[test]
+	fn test_battery_info() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		shell.set_battery_level(42).expect("failed to set battery level");
+		let info = shell.battery_info().expect("failed to get battery info");
+		println!("battery: {info:?}");
+		assert_eq!(42, info.level);
+		shell.reset_battery().expect("failed to reset battery");
+	}
+
+	#[test]
+	fn test_parse_wm() {
+		assert_eq!(Some((1080, 1920)), parse_wm_size("Physical size: 1080x1920\n"));
+		assert_eq!(Some((720, 1280)), parse_wm_size("Physical size: 1080x1920\nOverride size: 720x1280\n"));
+		assert_eq!(None, parse_wm_size(""));
+		assert_eq!(Some(420), parse_wm_density("Physical density: 420\n"));
+		assert_eq!(Some(320), parse_wm_density("Physical density: 420\nOverride density: 320\n"));
+		assert_eq!(None, parse_wm_density("Physical size: 1080x1920\n"));
+	}
+
+	#[test]
+	fn test_display_size() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+
+		shell.set_display_size(720, 1280).expect("failed to set display size");
+		assert_eq!((720, 1280), shell.get_display_size().expect("failed to get display size"));
+		shell.reset_display_size().expect("failed to reset display size");
+
+		shell.set_display_density(320).expect("failed to set display density");
+		assert_eq!(320, shell.get_display_density().expect("failed to get display density"));
+		assert_eq!(DensityBucket::Xhdpi, shell.density_bucket().expect("failed to get density bucket"));
+		shell.reset_display_density().expect("failed to reset display density");
+	}
+
 	#[test]
 	fn test_save_screencap() {
 		init_log();
@@ -1582,6 +4154,109 @@ mod test {
 		assert_eq!(prop.value, prop2);
 	}
 
+	#[test]
+	fn test_diff_props() {
+		let snapshot = PropSnapshot {
+			prefix: None,
+			props: [("a", "1"), ("b", "2"), ("c", "3")]
+				.into_iter()
+				.map(|(k, v)| (k.to_string(), v.to_string()))
+				.collect(),
+		};
+		let current = PropSnapshot {
+			prefix: None,
+			props: [("a", "1"), ("b", "20"), ("d", "4"), ("e", "")]
+				.into_iter()
+				.map(|(k, v)| (k.to_string(), v.to_string()))
+				.collect(),
+		};
+		let changes = diff_props(&snapshot, &current);
+		assert_eq!(
+			vec![
+				("b".to_string(), "2".to_string()),
+				("c".to_string(), "3".to_string()),
+				("d".to_string(), "".to_string()),
+			],
+			changes
+		);
+		assert!(diff_props(&snapshot, &snapshot).is_empty());
+	}
+
+	#[test]
+	fn test_snapshot_restore_props() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let key = "debug.radb.snapshot";
+
+		let snapshot = shell.snapshot_props(Some("debug.")).expect("failed to snapshot props");
+		assert!(snapshot.props.keys().all(|key| key.starts_with("debug.")));
+
+		shell.setprop(key, "changed").expect("failed to set prop");
+		assert_eq!("changed", shell.getprop(key).unwrap());
+
+		let failed = shell.restore_props(&snapshot).expect("failed to restore props");
+		assert!(failed.is_empty(), "failed to restore: {failed:?}");
+		assert_eq!(
+			snapshot.props.get(key).cloned().unwrap_or_default(),
+			shell.getprop(key).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_parse_device_info() {
+		let props = [
+			("ro.product.manufacturer", "Google"),
+			("ro.product.model", "sdk_gphone64_arm64"),
+			("ro.product.brand", "google"),
+			("ro.product.name", "sdk_gphone64_arm64"),
+			("ro.product.device", "emu64a"),
+			("ro.product.board", "goldfish_arm64"),
+			("ro.hardware", "ranchu"),
+			("ro.product.cpu.abilist", "arm64-v8a,armeabi-v7a"),
+			("ro.build.version.sdk", "34"),
+			("ro.build.product", "emu64a"),
+		]
+		.into_iter()
+		.map(|(key, value)| Property {
+			key: key.to_string(),
+			value: value.to_string(),
+		})
+		.collect::<Vec<_>>();
+
+		assert_eq!(
+			DeviceInfo {
+				manufacturer: "Google".to_string(),
+				model: "sdk_gphone64_arm64".to_string(),
+				brand: "google".to_string(),
+				name: "sdk_gphone64_arm64".to_string(),
+				device: "emu64a".to_string(),
+				board: "goldfish_arm64".to_string(),
+				hardware: "ranchu".to_string(),
+				abilist: vec!["arm64-v8a".to_string(), "armeabi-v7a".to_string()],
+				sdk: 34,
+			},
+			parse_device_info(&props).unwrap()
+		);
+
+		let info = parse_device_info(&props[8..]).unwrap();
+		assert_eq!(34, info.sdk);
+		assert!(info.model.is_empty());
+		assert!(info.abilist.is_empty());
+		assert!(parse_device_info(&props[..8]).is_err());
+	}
+
+	#[test]
+	fn test_device_info() {
+		init_log();
+		let client = connect_emulator();
+		let info = client.shell().device_info().expect("failed to get device info");
+		println!("device info: {info:?}");
+		assert_eq!(client.shell().build_version_sdk().unwrap(), info.sdk);
+		assert_eq!(client.shell().getprop("ro.product.model").unwrap(), info.model);
+		assert!(!info.abilist.is_empty());
+	}
+
 	#[test]
 	fn test_get_props_type() {
 		init_log();
@@ -1695,6 +4370,59 @@ mod test {
 		client.shell().try_send_tap((200, 200), None).expect("failed to send tap");
 	}
 
+	#[test]
+	fn test_make_long_press() {
+		let args = make_long_press((100, 200), Duration::from_millis(1500), Some(InputSource::touchscreen))
+			.expect("failed to build long press");
+		assert_eq!(
+			vec![
+				"input",
+				"touchscreen",
+				"swipe",
+				"100 200 100 200",
+				"1500",
+			],
+			args
+		);
+
+		make_long_press((100, 200), Duration::ZERO, None).expect_err("zero duration should fail");
+	}
+
+	#[test]
+	fn test_make_input() {
+		assert_eq!(vec!["input", "roll"], make_input(None, "roll"));
+		assert_eq!(vec!["input", "trackball", "press"], make_input(Some(InputSource::trackball), "press"));
+		assert_eq!(
+			vec!["input", "dpad", "keyevent", "KEYCODE_HOME"],
+			make_keyevent(KEYCODE_HOME, None, Some(InputSource::dpad))
+		);
+		assert_eq!(vec!["input", "tap", "10", "20"], make_tap((10, 20), None));
+	}
+
+	#[test]
+	fn test_send_input() {
+		init_log();
+		let client = connect_emulator();
+		client
+			.shell()
+			.send_input(Some(InputSource::trackball), "roll", &["1", "0"])
+			.expect("failed to send roll");
+	}
+
+	#[test]
+	fn test_long_press() {
+		init_log();
+		let client = connect_emulator();
+		client
+			.shell()
+			.long_press((100, 100), Duration::from_secs(1), None)
+			.expect("failed to send long press");
+		client
+			.shell()
+			.try_long_press((100, 100), Duration::from_secs(1), None)
+			.expect("failed to send long press");
+	}
+
 	#[test]
 	fn test_send_char() {
 		init_log();
@@ -1902,4 +4630,196 @@ mod test {
 
 		let _result = client.shell().am().broadcast(&intent).unwrap();
 	}
+
+	#[test]
+	fn test_parse_monkey_output() {
+		let output = ":Monkey: seed=42 count=500\n\
+			:AllowPackage: com.android.settings\n\
+			Events injected: 500\n\
+			:Sending rotation degree=0, persist=false\n\
+			## Network stats: elapsed time=1234ms (0ms mobile, 0ms wifi, 1234ms not connected)\n\
+			// Monkey finished\n";
+		let result = parse_monkey_output(output).unwrap();
+		assert_eq!(500, result.events_injected);
+		assert!(!result.crashed);
+		assert!(!result.anr);
+
+		let output = ":Monkey: seed=42 count=500\n\
+			// CRASH: com.example (pid 1234)\n\
+			// Short Msg: java.lang.NullPointerException\n\
+			** Monkey aborted due to error.\n\
+			Events injected: 87\n";
+		let result = parse_monkey_output(output).unwrap();
+		assert_eq!(87, result.events_injected);
+		assert!(result.crashed);
+		assert!(!result.anr);
+
+		let output = "// NOT RESPONDING: com.example (pid 1234)\nANR in com.example\n";
+		let result = parse_monkey_output(output).unwrap();
+		assert_eq!(0, result.events_injected);
+		assert!(result.anr);
+
+		assert!(parse_monkey_output("** No activities found to run, monkey aborted.\n").is_none());
+	}
+
+	#[test]
+	fn test_monkey() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let result = shell
+			.monkey("com.android.settings", 50, Some(42), Some(10), None)
+			.expect("failed to run monkey");
+		assert_eq!(50, result.events_injected);
+	}
+
+	#[test]
+	fn test_parse_prop_value() {
+		assert_eq!(
+			PropValue::String("hello world".to_string()),
+			parse_prop_value("hello world", &PropType::String).unwrap()
+		);
+		assert_eq!(
+			PropValue::String("42".to_string()),
+			parse_prop_value("42", &PropType::Unknown("uint".to_string())).unwrap()
+		);
+
+		assert_eq!(PropValue::Bool(true), parse_prop_value("true", &PropType::Bool).unwrap());
+		assert_eq!(PropValue::Bool(true), parse_prop_value("1", &PropType::Bool).unwrap());
+		assert_eq!(PropValue::Bool(false), parse_prop_value("false", &PropType::Bool).unwrap());
+		assert_eq!(PropValue::Bool(false), parse_prop_value("0", &PropType::Bool).unwrap());
+		parse_prop_value("yes", &PropType::Bool).expect_err("Expected error");
+
+		assert_eq!(PropValue::Int(-12), parse_prop_value("-12", &PropType::Int).unwrap());
+		parse_prop_value("", &PropType::Int).expect_err("Expected error");
+
+		let prop_type = PropType::Enum(vec![
+			"enforcing".to_string(),
+			"permissive".to_string(),
+		]);
+		assert_eq!(
+			PropValue::Enum("permissive".to_string()),
+			parse_prop_value("permissive", &prop_type).unwrap()
+		);
+		parse_prop_value("disabled", &prop_type).expect_err("Expected error");
+	}
+
+	#[test]
+	fn test_get_typed_prop() {
+		init_log();
+		let client = connect_emulator();
+		let shell = client.shell();
+		let value = shell.get_typed_prop("ro.build.version.sdk").expect("failed to get prop");
+		assert!(matches!(value, PropValue::Int(_) | PropValue::String(_)));
+	}
+
+	#[test]
+	fn test_parse_keyboard_visible() {
+		let output = "Current Input Method Manager state:
+  Input Methods: mMethodMapUpdateCount=2
+  mCurMethodId=com.android.inputmethod.latin/.LatinIME
+  mCurrentUserId=0 mCurrentProfileIds=[0]
+  mIsInteractive=true
+  mShowRequested=true mShowExplicitlyRequested=false mShowForced=false mInputShown=true
+  mInFullscreenMode=false
+";
+		assert_eq!(Some("com.android.inputmethod.latin/.LatinIME"), parse_dumpsys_value(output, "mCurMethodId"));
+		assert_eq!(Some("false"), parse_dumpsys_value(output, "mShowForced"));
+		assert_eq!(None, parse_dumpsys_value(output, "mShow"));
+		assert_eq!(Some(true), parse_keyboard_visible(output));
+
+		let output = "  mInputShown=false\n  mShowRequested=true\n";
+		assert_eq!(Some(false), parse_keyboard_visible(output));
+
+		let output = "  mShowRequested=false mShowExplicitlyRequested=false\n";
+		assert_eq!(Some(false), parse_keyboard_visible(output));
+
+		assert_eq!(None, parse_keyboard_visible(""));
+	}
+
+	#[test]
+	fn test_is_keyboard_visible() {
+		init_log();
+		let client = connect_emulator();
+		let visible = client.shell().is_keyboard_visible().expect("failed to get keyboard visibility");
+		println!("keyboard visible: {visible}");
+	}
+
+	#[test]
+	fn test_batch_output() {
+		assert_eq!(
+			"getprop ro.product.model; echo __RADB_SEP__$?; ls /missing; echo __RADB_SEP__$?",
+			make_batch_command(
+				&[
+					vec!["getprop", "ro.product.model"],
+					vec!["ls", "/missing"],
+				],
+				"__RADB_SEP__"
+			)
+		);
+
+		let output = b"Pixel 7\n__RADB_SEP__0\n__RADB_SEP__1\nno newline__RADB_SEP__127\n";
+		let results = split_batch_output(output, "__RADB_SEP__").unwrap();
+		assert_eq!(
+			vec![
+				(b"Pixel 7\n".to_vec(), 0),
+				(vec![], 1),
+				(b"no newline".to_vec(), 127),
+			],
+			results
+		);
+		assert!(split_batch_output(b"", "__RADB_SEP__").unwrap().is_empty());
+		assert!(split_batch_output(b"__RADB_SEP__x\n", "__RADB_SEP__").is_err());
+	}
+
+	#[test]
+	fn test_exec_batch_local() {
+		// the fake adb runs the batch command with the local shell
+		let adb = temp_dir().join("local_shell_adb.sh");
+		std::fs::write(&adb, "#!/bin/sh\neval command=\\${$#}\nexec sh -c \"$command\"\n").expect("failed to write script");
+		std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).expect("failed to chmod script");
+		let client = Client::new(Adb::from(adb), ConnectionType::from_str("127.0.0.1:5555").unwrap(), false);
+		let shell = client.shell();
+
+		let outputs = shell
+			.exec_batch(&[
+				vec!["echo", "hello"],
+				vec!["sh", "-c", "'exit 3'"],
+			])
+			.expect("failed to exec batch");
+		assert_eq!(b"hello\n".to_vec(), outputs[0].stdout);
+		assert!(outputs[0].success());
+		assert_eq!(Some(3), outputs[1].status.code());
+
+		// the output of a command can't collide with the separator
+		let outputs = shell.exec_batch(&[vec!["echo", "__RADB_SEP_1"]]).expect("failed to exec batch");
+		assert_eq!(b"__RADB_SEP_1\n".to_vec(), outputs[0].stdout);
+		assert!(outputs[0].success());
+
+		// `exit` stops the batch before the remaining commands run
+		let result = shell.exec_batch(&[
+			vec!["echo", "hello"],
+			vec!["exit", "0"],
+			vec!["echo", "world"],
+		]);
+		assert!(matches!(result, Err(Error::ParseInputError)));
+	}
+
+	#[test]
+	fn test_exec_batch() {
+		init_log();
+		let client = connect_emulator();
+		let outputs = client
+			.shell()
+			.exec_batch(&[
+				vec!["getprop", "ro.build.version.sdk"],
+				vec!["ls", "/missing_dir"],
+				vec!["echo", "hello"],
+			])
+			.expect("failed to exec batch");
+		assert_eq!(3, outputs.len());
+		assert!(outputs[0].success());
+		assert!(outputs[1].error());
+		assert_eq!(b"hello\n".to_vec(), outputs[2].stdout);
+	}
 }
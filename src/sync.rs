@@ -0,0 +1,182 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::types::{ConnectionType, RemoteStat};
+
+static ADB_SERVER_PORT: u16 = 5037;
+static STAT_V2_LEN: usize = 68;
+static SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The adb server port, honoring `ANDROID_ADB_SERVER_PORT`
+fn server_port() -> u16 {
+	std::env::var("ANDROID_ADB_SERVER_PORT")
+		.ok()
+		.and_then(|port| port.parse().ok())
+		.unwrap_or(ADB_SERVER_PORT)
+}
+
+/// The host service selecting the transport of the given connection
+pub(crate) fn transport_service(addr: &ConnectionType) -> String {
+	match addr {
+		ConnectionType::TcpIp(sock) => format!("host:transport:{sock}"),
+		ConnectionType::Transport(id) => format!("host:transport-id:{id}"),
+		ConnectionType::USB => "host:transport-usb".to_string(),
+	}
+}
+
+/// Send a request to the adb server, prefixed by its hex encoded length, and wait for the `OKAY` reply
+fn send_service(stream: &mut TcpStream, service: &str) -> Result<()> {
+	stream.write_all(format!("{:04x}{service}", service.len()).as_bytes())?;
+	let mut status = [0u8; 4];
+	stream.read_exact(&mut status)?;
+	if &status == b"OKAY" {
+		return Ok(());
+	}
+
+	let mut len = [0u8; 4];
+	stream.read_exact(&mut len)?;
+	let len = usize::from_str_radix(std::str::from_utf8(&len).map_err(|_| Error::ParseInputError)?, 16)?;
+	let mut message = vec![0u8; len];
+	stream.read_exact(&mut message)?;
+	Err(std::io::Error::other(String::from_utf8_lossy(&message).to_string()).into())
+}
+
+/// Parse the `STA2` reply of the sync protocol (without its id):
+/// error, dev, ino, mode, nlink, uid, gid, size, atime, mtime, ctime
+pub(crate) fn parse_stat_v2(data: &[u8]) -> Result<RemoteStat> {
+	if data.len() < STAT_V2_LEN {
+		return Err(Error::ParseInputError);
+	}
+	let u32_at = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+	let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+	let error = u32_at(0);
+	if error != 0 {
+		return Err(std::io::Error::from_raw_os_error(error as i32).into());
+	}
+
+	Ok(RemoteStat {
+		mode: u32_at(20),
+		uid: u32_at(28),
+		gid: u32_at(32),
+		size: u64_at(36),
+		atime: u64_at(44) as i64,
+		mtime: u64_at(52) as i64,
+		ctime: u64_at(60) as i64,
+	})
+}
+
+/// Stat the remote path using the `STA2` request of the adb sync protocol, which reports 64-bit sizes and times.
+/// The device must support the `stat_v2` feature.
+/// Connecting to the adb server and each read or write are bounded by the given timeout (10 seconds by default),
+/// returning [`Error::Timeout`] when it expires.
+pub(crate) fn stat_v2(addr: &ConnectionType, path: &str, timeout: Option<Duration>) -> Result<RemoteStat> {
+	let server = SocketAddr::from((Ipv4Addr::LOCALHOST, server_port()));
+	stat_v2_from(server, addr, path, timeout.unwrap_or(SYNC_TIMEOUT))
+}
+
+fn stat_v2_from(server: SocketAddr, addr: &ConnectionType, path: &str, timeout: Duration) -> Result<RemoteStat> {
+	let request = || -> Result<RemoteStat> {
+		let mut stream = TcpStream::connect_timeout(&server, timeout)?;
+		stream.set_read_timeout(Some(timeout))?;
+		stream.set_write_timeout(Some(timeout))?;
+		request_stat_v2(&mut stream, addr, path)
+	};
+	request().map_err(|err| match err {
+		Error::IoError(err) if matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => Error::Timeout {
+			command: format!("sync STA2 {path}"),
+			elapsed: timeout,
+		},
+		err => err,
+	})
+}
+
+fn request_stat_v2(stream: &mut TcpStream, addr: &ConnectionType, path: &str) -> Result<RemoteStat> {
+	send_service(stream, &transport_service(addr))?;
+	send_service(stream, "sync:")?;
+
+	let mut request = b"STA2".to_vec();
+	request.extend((path.len() as u32).to_le_bytes());
+	request.extend(path.as_bytes());
+	stream.write_all(&request)?;
+
+	let mut id = [0u8; 4];
+	stream.read_exact(&mut id)?;
+	if &id != b"STA2" {
+		return Err(Error::ParseInputError);
+	}
+	let mut data = vec![0u8; STAT_V2_LEN];
+	stream.read_exact(&mut data)?;
+
+	// close the sync session
+	let _ = stream.write_all(b"QUIT\0\0\0\0");
+	parse_stat_v2(&data)
+}
+
+#[cfg(test)]
+mod test {
+	use std::net::TcpListener;
+	use std::time::Duration;
+
+	use crate::error::Error;
+	use crate::sync::{parse_stat_v2, stat_v2_from, transport_service};
+	use crate::types::{ConnectionType, RemoteStat};
+
+	#[test]
+	fn test_transport_service() {
+		assert_eq!("host:transport-usb", transport_service(&ConnectionType::USB));
+		assert_eq!("host:transport-id:4", transport_service(&ConnectionType::Transport(4)));
+		assert_eq!(
+			"host:transport:192.168.1.1:5555",
+			transport_service(&"192.168.1.1:5555".parse().unwrap())
+		);
+	}
+
+	#[test]
+	fn test_parse_stat_v2() {
+		let mut data = vec![];
+		data.extend(0u32.to_le_bytes()); // error
+		data.extend(1u64.to_le_bytes()); // dev
+		data.extend(2u64.to_le_bytes()); // ino
+		data.extend(0o100644u32.to_le_bytes()); // mode
+		data.extend(1u32.to_le_bytes()); // nlink
+		data.extend(1000u32.to_le_bytes()); // uid
+		data.extend(1015u32.to_le_bytes()); // gid
+		data.extend(5_000_000_000u64.to_le_bytes()); // size
+		data.extend(1_700_000_000u64.to_le_bytes()); // atime
+		data.extend(1_700_000_001u64.to_le_bytes()); // mtime
+		data.extend(1_700_000_002u64.to_le_bytes()); // ctime
+
+		assert_eq!(
+			RemoteStat {
+				mode: 0o100644,
+				uid: 1000,
+				gid: 1015,
+				size: 5_000_000_000,
+				atime: 1_700_000_000,
+				mtime: 1_700_000_001,
+				ctime: 1_700_000_002,
+			},
+			parse_stat_v2(&data).unwrap()
+		);
+
+		data[0] = 2; // ENOENT
+		assert!(parse_stat_v2(&data).is_err());
+		assert!(parse_stat_v2(&data[..10]).is_err());
+	}
+
+	#[test]
+	fn test_stat_v2_timeout() {
+		// a server accepting the connection but never replying
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let server = listener.local_addr().unwrap();
+		let result = stat_v2_from(server, &ConnectionType::USB, "/sdcard", Duration::from_millis(200));
+		match result {
+			Err(Error::Timeout { elapsed, .. }) => assert_eq!(Duration::from_millis(200), elapsed),
+			other => panic!("unexpected result: {other:?}"),
+		}
+	}
+}
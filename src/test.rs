@@ -128,7 +128,7 @@ pub(crate) mod test {
 	pub(crate) fn reboot_and_wait_for_client(client: &Client) {
 		client.reboot(None).expect("failed to send reboot command");
 		client
-			.wait_for_device(Some(Duration::from_secs(180)))
+			.wait_for_device(Some(Duration::from_secs(180)), false)
 			.expect("failed to wait for device");
 	}
 
@@ -1,13 +1,19 @@
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use strum_macros::{Display, EnumIter, IntoStaticStr};
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-#[repr(transparent)]
-pub struct Adb(pub(crate) PathBuf);
+/// The adb executable. The command observer is shared by the clones of this adb,
+/// see [`Adb::set_command_observer`]
+#[derive(Clone)]
+pub struct Adb(pub(crate) PathBuf, pub(crate) Arc<RwLock<Option<CommandObserver>>>);
+
+/// Callback invoked with the arguments of each adb command, see [`Adb::set_command_observer`]
+pub type CommandObserver = Arc<dyn Fn(&[OsString]) + Send + Sync>;
 
 #[derive(Clone, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -26,6 +32,34 @@ pub struct Client {
 	pub adb: Adb,
 	pub addr: ConnectionType,
 	pub debug: bool,
+	/// retry policy for the transient failures, see [`Client::with_retry`]
+	pub(crate) retry: Option<RetryPolicy>,
+	/// restart adbd as root before the methods requiring it, see [`Client::with_auto_root`]
+	pub auto_root: bool,
+	/// timeouts used by this client, see [`Client::with_config`]
+	pub config: ClientConfig,
+}
+
+/// Timeouts used by a [`Client`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientConfig {
+	/// the timeout of `adb get-state`, used to check whether the device is connected
+	pub get_state_timeout: Duration,
+	/// the time to wait for adbd to restart after `adb root`
+	pub sleep_after_root: Duration,
+	/// the time to wait for adbd to restart after `adb tcpip`, before polling the device state
+	pub sleep_after_tcpip: Duration,
+	/// the timeout of the commands run without an explicit one
+	pub default_command_timeout: Option<Duration>,
+}
+
+/// How many times, and how often, the commands failing for a transient reason are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+	/// the number of retries after the first failure
+	pub attempts: u32,
+	/// the time to wait before each retry
+	pub backoff: Duration,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -43,6 +77,20 @@ pub struct PackageManager<'a> {
 	pub(crate) parent: &'a Shell<'a>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentProvider<'a> {
+	pub(crate) parent: &'a Shell<'a>,
+}
+
+/// A typed value bound to a column by the `content` command
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentValue {
+	S(String),
+	I(i64),
+	B(bool),
+	D(f64),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AdbDevice {
 	pub name: String,
@@ -53,6 +101,28 @@ pub struct AdbDevice {
 	pub addr: ConnectionType,
 }
 
+/// A device listed by `adb devices -l`, whatever its state, see [`Adb::devices`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AdbDeviceEntry {
+	pub serial: String,
+	pub state: DeviceState,
+	pub product: Option<String>,
+	pub model: Option<String>,
+	pub device: Option<String>,
+	pub transport_id: Option<u32>,
+	/// the usb port (e.g. `1-1`) of the devices attached over usb
+	pub usb: Option<String>,
+	pub connection: DeviceConnection,
+}
+
+/// How a device is attached to the adb server
+#[derive(Debug, Display, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum DeviceConnection {
+	Usb,
+	/// network devices and emulators
+	Tcp,
+}
+
 #[derive(Debug, Display, Eq, PartialEq, Hash, Clone)]
 pub enum Wakefulness {
 	Awake,
@@ -60,6 +130,39 @@ pub enum Wakefulness {
 	Dreaming,
 }
 
+/// The device state, as reported by `adb get-state` or `adb devices`
+#[derive(Debug, Display, Eq, PartialEq, Hash, Clone)]
+pub enum DeviceState {
+	Device,
+	Offline,
+	Unauthorized,
+	Bootloader,
+	Recovery,
+	Sideload,
+	/// the adb server cannot access the usb device (e.g. missing udev rules)
+	NoPermissions,
+	/// any other state reported by `adb devices` (e.g. `connecting`, `authorizing`)
+	Unknown(String),
+}
+
+/// The state to wait for, see [`Client::wait_for`]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum WaitState {
+	Device,
+	Recovery,
+	Sideload,
+	Bootloader,
+	Disconnect,
+}
+
+/// The transport used by [`Client::wait_for`]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum WaitTransport {
+	Usb,
+	Local,
+	Any,
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum Reconnect {
 	Device,
@@ -74,6 +177,15 @@ pub enum UserOption {
 	None,
 }
 
+/// The night mode set with `cmd uimode night`
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum NightMode {
+	Yes,
+	No,
+	/// follow the time of the day
+	Auto,
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum MemoryStatus {
 	Hidden,
@@ -85,14 +197,96 @@ pub enum MemoryStatus {
 	Complete,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Display, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum BatteryStatus {
+	Unknown,
+	Charging,
+	Discharging,
+	NotCharging,
+	Full,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Display, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum BatteryHealth {
+	Unknown,
+	Good,
+	Overheat,
+	Dead,
+	OverVoltage,
+	UnspecifiedFailure,
+	Cold,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Display, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum PluggedSource {
+	None,
+	Ac,
+	Usb,
+	Wireless,
+	Dock,
+}
+
+/// The battery state, as reported by `dumpsys battery`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryInfo {
+	pub level: u8,
+	pub scale: u8,
+	pub status: BatteryStatus,
+	pub health: BatteryHealth,
+	pub plugged: PluggedSource,
+	/// temperature in degrees Celsius
+	pub temperature: f32,
+	pub voltage_mv: u32,
+	pub present: bool,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Package {
 	pub package_name: String,
 	pub file_name: Option<String>,
 	pub version_code: Option<i32>,
 	pub uid: Option<i32>,
+	/// size in bytes of the apk file, see [`PackageManager::list_packages_with_apk_size`]
+	pub apk_size: Option<u64>,
+}
+
+/// A user of the device, as reported by `pm list users`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct User {
+	pub id: u32,
+	pub name: String,
+	pub running: bool,
+	/// the `UserInfo` flags (e.g. 0x13 for a primary admin user)
+	pub flags: u32,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct PackageSize {
+	/// size of the package code path (apk, splits, native libs and oat files)
+	pub app_bytes: u64,
+	/// size of the package data dir, cache included. Requires root
+	pub data_bytes: Option<u64>,
+	/// size of the package cache dir. Requires root
+	pub cache_bytes: Option<u64>,
+}
+
+/// The memory usage of a running package, as reported by `dumpsys meminfo`. All the values are in kilobytes
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct MemInfo {
+	pub total_pss: u64,
+	pub java_heap: u64,
+	pub native_heap: u64,
+	pub code: u64,
+	pub stack: u64,
+	pub graphics: u64,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct RuntimePermission {
 	pub name: String,
@@ -100,12 +294,14 @@ pub struct RuntimePermission {
 	pub flags: Vec<String>,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct InstallPermission {
 	pub name: String,
 	pub granted: bool,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Eq, PartialEq, Clone, Copy, IntoStaticStr, Display)]
 pub enum PackageFlags {
 	System,
@@ -193,9 +389,13 @@ pub enum RebootType {
 	Sideload,
 	SideloadAutoReboot,
 	Dra,
+	/// userspace fastboot (fastbootd), needed to flash the dynamic partitions
+	Fastboot,
+	/// Qualcomm emergency download mode
+	Edl,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct LogcatOptions {
 	/// -e    Only prints lines where the log message matches expr, where expr is a regular expression.
 	pub expr: Option<String>,
@@ -219,9 +419,79 @@ pub struct LogcatOptions {
 	// --pid=pid ...
 	pub pid: Option<i32>,
 
+	// --pid=pid repeated for each of the given pids
+	pub pids: Vec<i32>,
+
+	/// -b buffer    Loads an alternate log buffer (main, system, radio, events, crash, all...), repeated for each buffer
+	pub buffers: Vec<String>,
+
 	pub timeout: Option<core::time::Duration>,
 }
 
+/// A logcat line, parsed from the `threadtime` format
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LogcatEntry {
+	pub timestamp: Option<chrono::NaiveDateTime>,
+	pub pid: u32,
+	pub tid: u32,
+	/// single char priority (V, D, I, W, E, F, A)
+	pub level: char,
+	pub tag: String,
+	pub message: String,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CrashKind {
+	/// an uncaught exception (`FATAL EXCEPTION`)
+	Crash,
+	/// an application not responding (`ANR in`)
+	Anr,
+}
+
+/// A component (ie. an activity) identified by its package and its fully qualified class name
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ComponentName {
+	pub package: String,
+	pub class: String,
+}
+
+/// A task of the recents stack, as reported by `dumpsys activity recents`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RecentTask {
+	pub task_id: u32,
+	/// the activity which started the task
+	pub base_activity: Option<ComponentName>,
+	/// the activity on top of the task, only reported by some Android versions
+	pub top_activity: Option<ComponentName>,
+	/// the uptime (in milliseconds) of the last time the task was active
+	pub last_active_ms: Option<u64>,
+}
+
+/// The state of a service, as reported by `dumpsys activity services`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct ServiceInfo {
+	/// the service is created and its process is alive
+	pub running: bool,
+	pub pid: Option<u32>,
+	/// the service was started with `startService`
+	pub started: bool,
+	/// at least one client is bound to the service
+	pub bound: bool,
+	/// the number of client processes bound to the service
+	pub client_count: u32,
+}
+
+/// A crash or an ANR extracted from the logcat
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CrashReport {
+	pub kind: CrashKind,
+	pub package: Option<String>,
+	/// the time of the first line of the report, when it could be parsed
+	pub timestamp: Option<chrono::NaiveDateTime>,
+	/// the report lines following the `FATAL EXCEPTION`/`ANR in` line
+	pub stack: Vec<String>,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum LogcatLevel {
 	Verbose,
@@ -245,14 +515,16 @@ pub enum DumpsysPriority {
 	NORMAL,
 }
 
-#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct FFPlayOptions {
 	pub framerate: Option<u16>,
 	pub size: Option<(u16, u16)>,
 	pub probesize: Option<u16>,
+	/// the ffplay binary to use instead of the one found in the PATH
+	pub binary: Option<PathBuf>,
 }
 
-#[derive(IntoStaticStr)]
+#[derive(IntoStaticStr, Debug, Copy, Clone, PartialEq, Eq, Hash, EnumIter)]
 #[allow(non_camel_case_types)]
 pub enum InputSource {
 	dpad,
@@ -282,7 +554,7 @@ pub enum KeyEventType {
 	DoubleTap,
 }
 
-#[derive(IntoStaticStr, Display, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(IntoStaticStr, Display, Debug, Copy, Clone, PartialEq, Eq, Hash, EnumIter)]
 #[allow(non_camel_case_types)]
 pub enum KeyCode {
 	KEYCODE_0,
@@ -577,12 +849,44 @@ pub enum KeyCode {
 	KEYCODE_ZOOM_OUT,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct Property {
 	pub key: String,
 	pub value: String,
 }
 
+/// The device identity, read from the `ro.product.*` properties, see [`Shell::device_info`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DeviceInfo {
+	pub manufacturer: String,
+	pub model: String,
+	pub brand: String,
+	pub name: String,
+	pub device: String,
+	pub board: String,
+	pub hardware: String,
+	/// the supported abis, in order of preference
+	pub abilist: Vec<String>,
+	pub sdk: u16,
+}
+
+/// A saved copy of the device properties, see [`Shell::snapshot_props`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PropSnapshot {
+	/// the prefix used to filter the properties, if any
+	pub prefix: Option<String>,
+	pub props: HashMap<String, String>,
+}
+
+/// An entry of the `adb forward --list` or `adb reverse --list` output
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ForwardEntry {
+	pub serial: String,
+	pub local: String,
+	pub remote: String,
+}
+
 #[derive(Clone, Debug, IntoStaticStr, PartialEq)]
 pub enum PropType {
 	String,
@@ -592,6 +896,17 @@ pub enum PropType {
 	Unknown(String),
 }
 
+/// A property value, parsed according to its [`PropType`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PropValue {
+	/// string and unknown types
+	String(String),
+	Bool(bool),
+	Int(i64),
+	/// one of the values declared by the enum type
+	Enum(String),
+}
+
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct ScreenRecordOptions {
 	/// --bit-rate 4000000
@@ -619,6 +934,235 @@ pub struct ScreenRecordOptions {
 	/// --verbose
 	/// Display interesting information on stdout
 	pub verbose: bool,
+
+	/// --audio (or --capture-audio, depending on the device)
+	/// Capture the internal audio too. Only supported by recent screenrecord versions.
+	pub audio: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum LsSort {
+	#[default]
+	Name,
+	/// -S
+	Size,
+	/// -t
+	Time,
+	/// -f
+	None,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct LsOptions {
+	/// -A    list all files, including the hidden ones (except `.` and `..`)
+	pub all: bool,
+	/// -l    long listing format
+	pub long: bool,
+	/// -R    recursively list the subdirectories
+	pub recursive: bool,
+	/// -pF   append the type indicator (`/`, `*`, `@`, `|`, `=`) to the names. It's stripped from the parsed entries
+	pub classify: bool,
+	pub sort: LsSort,
+	/// -r    reverse the sort order
+	pub reverse: bool,
+}
+
+/// Options of `am instrument`
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct InstrumentOptions {
+	/// -r    print raw results
+	pub raw: bool,
+	/// -w    wait for the instrumentation to finish before returning
+	pub wait: bool,
+	/// -e class <CLASS>    run only the given test class (or `class#method`)
+	pub class: Option<String>,
+	/// -e <KEY> <VALUE>    additional arguments passed to the runner
+	pub extras: HashMap<String, String>,
+}
+
+/// The outcome of an `am instrument` run
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct InstrumentResult {
+	pub passed: u32,
+	/// tests ended with a failure or an error
+	pub failed: u32,
+	/// tests ignored or with a failed assumption
+	pub ignored: u32,
+	/// the final `INSTRUMENTATION_CODE`; -1 (RESULT_OK) on success
+	pub code: Option<i32>,
+	/// the `INSTRUMENTATION_RESULT` values
+	pub results: HashMap<String, String>,
+	/// the full instrument output
+	pub raw: String,
+}
+
+/// The metadata of a file on the device, returned by [`Client::remote_stat`].
+/// Times are seconds since the epoch.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct RemoteStat {
+	/// file type and permissions, as in `st_mode`
+	pub mode: u32,
+	pub uid: u32,
+	pub gid: u32,
+	pub size: u64,
+	pub atime: i64,
+	pub mtime: i64,
+	pub ctime: i64,
+}
+
+/// The summary of a [`Client::sync`]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct SyncStats {
+	pub pushed: usize,
+	/// files already up to date on the device
+	pub skipped: usize,
+	/// bytes transferred
+	pub bytes: u64,
+}
+
+/// The summary of a single push or pull, see [`Client::push_stats`] and [`Client::pull_stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TransferStats {
+	/// bytes transferred
+	pub bytes: u64,
+	pub duration: Duration,
+	pub rate_bytes_per_sec: f64,
+}
+
+/// An entry returned by [`Shell::ls_opts`] and [`Shell::list_dir`].
+/// `mode`, `links`, `owner`, `group`, `size` and `date` are only available using the long listing format.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DirEntry {
+	pub name: String,
+	/// the directory containing the entry, when listing recursively
+	pub parent: Option<String>,
+	/// the mode string, ie. `drwxr-xr-x`
+	pub mode: Option<String>,
+	pub links: Option<u32>,
+	pub owner: Option<String>,
+	pub group: Option<String>,
+	/// not available for character and block devices
+	pub size: Option<u64>,
+	pub date: Option<chrono::NaiveDateTime>,
+	/// the target of a symbolic link
+	pub link: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ProcessInfo {
+	pub pid: u32,
+	pub ppid: u32,
+	pub user: String,
+	pub name: String,
+	pub rss_kb: u64,
+	/// single char state (R, S, D, Z...)
+	pub state: char,
+}
+
+/// The usage of a cpu (or of all the cpus, for the `cpu` row) over a sampling interval, in percent
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuRow {
+	/// `cpu` for the aggregate of all the cpus, `cpuN` for the single cpu
+	pub name: String,
+	pub usage_percent: f32,
+	pub user_percent: f32,
+	pub system_percent: f32,
+	pub iowait_percent: f32,
+	pub idle_percent: f32,
+}
+
+/// The generalized density of a screen, see [`Shell::density_bucket`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum DensityBucket {
+	Ldpi,
+	Mdpi,
+	Tv,
+	Hdpi,
+	Xhdpi,
+	Xxhdpi,
+	Xxxhdpi,
+}
+
+/// A process row of `top`, see [`Shell::cpu_usage`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuEntry {
+	pub pid: u32,
+	/// the user name, or its uid for the legacy toolbox `top`
+	pub user: String,
+	pub cpu_percent: f32,
+	/// not available with the legacy toolbox `top`
+	pub mem_percent: Option<f32>,
+	pub name: String,
+}
+
+/// The state of an init service, as reported by the `init.svc.<name>` property
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ServiceState {
+	Running,
+	Stopped,
+	Restarting,
+	/// the service doesn't exist, or its state is not one of the above
+	Unknown,
+}
+
+/// The space of the filesystem containing a path, as reported by `df`
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DiskUsage {
+	pub filesystem: String,
+	pub total_kb: u64,
+	pub used_kb: u64,
+	pub available_kb: u64,
+	pub use_percent: u8,
+	pub mounted_on: String,
+}
+
+/// An entry of the device neighbor table, as reported by /proc/net/arp
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ArpEntry {
+	pub ip: std::net::Ipv4Addr,
+	pub mac: mac_address::MacAddress,
+	/// the network interface, ie. `wlan0`
+	pub device: String,
+	/// ATF_* flags (0x2 complete, 0x4 permanent...)
+	pub flags: u8,
+}
+
+/// The system load, as reported by /proc/loadavg
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LoadAverage {
+	/// average number of runnable processes over the last minute
+	pub one: f32,
+	/// average number of runnable processes over the last 5 minutes
+	pub five: f32,
+	/// average number of runnable processes over the last 15 minutes
+	pub fifteen: f32,
+	/// number of currently runnable threads
+	pub running: u32,
+	/// total number of threads
+	pub total: u32,
+}
+
+/// The outcome of a `monkey` run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonkeyResult {
+	/// number of events injected before the run ended
+	pub events_injected: u32,
+	/// the application crashed during the run
+	pub crashed: bool,
+	/// the application stopped responding during the run
+	pub anr: bool,
+	/// the full monkey output
+	pub raw: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadInfo {
+	pub tid: u32,
+	pub name: String,
+	/// cpu usage over the sampling interval, where 100 is a full core
+	pub cpu_percent: f32,
+	/// single char state, as reported by /proc/<pid>/task/<tid>/stat (R, S, D, Z...)
+	pub state: char,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -627,6 +1171,21 @@ pub enum SELinuxType {
 	Permissive,
 }
 
+/// The `svc power stayon` mode
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum StayAwakeMode {
+	/// always stay awake
+	On,
+	/// never stay awake
+	Off,
+	/// stay awake while charging from usb
+	Usb,
+	/// stay awake while charging from ac
+	Ac,
+	/// stay awake while charging wireless
+	Wireless,
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, IntoStaticStr, EnumIter)]
 #[allow(non_camel_case_types)]
 pub enum SettingsType {
@@ -1,9 +1,16 @@
 use std::path::PathBuf;
 
 use anyhow::anyhow;
+use lazy_static::lazy_static;
+use regex::Regex;
 
 use crate::types::Adb;
 
+lazy_static! {
+	static ref RE_TERMINAL_SEQUENCE: Regex =
+		Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]").unwrap();
+}
+
 /// Attempt to find the ANDROID_HOME environment variable, if it is not found, attempt to find it using the adb command location
 pub fn android_home() -> anyhow::Result<PathBuf> {
 	match std::env::var("ANDROID_HOME") {
@@ -45,3 +52,29 @@ pub fn apk_analyzer() -> anyhow::Result<PathBuf> {
 		Err(anyhow::Error::msg("apkanalyzer not found"))
 	}
 }
+
+/// Removes the terminal control sequences (colors, cursor movements, titles) and the carriage returns
+/// from the output of a command run with a tty, see [`crate::types::Client::shell_tty`]
+pub fn strip_terminal_sequences(output: &str) -> String {
+	RE_TERMINAL_SEQUENCE.replace_all(output, "").replace("\r\n", "\n").replace('\r', "")
+}
+
+/// Attempt to find the apksigner command path, in the most recent build-tools version
+pub fn apk_signer() -> anyhow::Result<PathBuf> {
+	let build_tools = android_home()?.join("build-tools");
+	let mut versions = std::fs::read_dir(&build_tools)?
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| path.join("apksigner").exists())
+		.collect::<Vec<_>>();
+	versions.sort_by_key(|path| {
+		path.file_name()
+			.and_then(|name| name.to_str())
+			.map(|name| name.split(['.', '-']).map(|part| part.parse::<u32>().unwrap_or(0)).collect::<Vec<_>>())
+			.unwrap_or_default()
+	});
+	versions
+		.pop()
+		.map(|path| path.join("apksigner"))
+		.ok_or(anyhow::Error::msg("apksigner not found"))
+}